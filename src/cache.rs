@@ -19,6 +19,69 @@ pub struct CachedEvent {
     pub raw_json: String,
 }
 
+/// What to look for in the cached events: every field narrows the result, and
+/// the values inside one field are alternatives (like a relay filter).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EventQuery {
+    /// Words that must all appear in the content, in any order and any case.
+    pub text: Option<String>,
+    /// Hex pubkeys.
+    pub authors: Vec<String>,
+    pub kinds: Vec<u16>,
+    /// Lowercase `t` tag values.
+    pub hashtags: Vec<String>,
+    /// Hex pubkeys in `p` tags.
+    pub mentions: Vec<String>,
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub limit: usize,
+}
+
+/// One event found by [`CacheDb::search_events`].
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub event: CachedEvent,
+    /// The matching part of the content, matches wrapped in `**`; present when
+    /// the full-text index took part in the match.
+    pub snippet: Option<String>,
+    /// BM25 score from the index: lower is a better match.
+    pub rank: Option<f64>,
+}
+
+/// The full-text index tokenizes into trigrams, so it finds any substring of
+/// three characters or more — which also works for text without spaces, such
+/// as Japanese. Shorter words fall back to `LIKE`.
+const MIN_INDEXED_WORD_CHARS: usize = 3;
+
+/// Split a search string into an FTS5 `MATCH` expression (each word a quoted
+/// phrase, all required) and the words too short for the trigram index.
+///
+/// Pure — quoting keeps FTS5 syntax (`-`, `:`, `*`, `OR`) in user input from
+/// being read as operators.
+fn text_terms(text: &str) -> (Option<String>, Vec<String>) {
+    let mut phrases = Vec::new();
+    let mut short = Vec::new();
+    for word in text.split_whitespace() {
+        if word.chars().count() >= MIN_INDEXED_WORD_CHARS {
+            phrases.push(format!("\"{}\"", word.replace('"', "\"\"")));
+        } else {
+            short.push(word.to_string());
+        }
+    }
+    let expression = (!phrases.is_empty()).then(|| phrases.join(" "));
+    (expression, short)
+}
+
+/// `word` as a `LIKE` pattern matching it anywhere, with `%` and `_` taken
+/// literally.
+fn like_pattern(word: &str) -> String {
+    let escaped = word
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
 #[derive(Debug, Clone)]
 pub struct CachedProfile {
     pub pubkey: String,
@@ -29,6 +92,24 @@ pub struct CachedProfile {
     pub updated_at: i64,
 }
 
+/// What the cache knows about a pubkey's NIP-05 address: the one its kind:0
+/// claims, and the outcome of the last check of that claim, if any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedNip05 {
+    pub address: Option<String>,
+    pub verified: Option<bool>,
+    pub checked_at: Option<i64>,
+}
+
+/// One recorded version of a replaceable event of ours (see
+/// [`CacheDb::store_snapshot`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub id: String,
+    pub created_at: i64,
+    pub raw_json: String,
+}
+
 impl CacheDb {
     pub fn open() -> Result<Self> {
         let db_path = Self::db_path();
@@ -41,6 +122,16 @@ impl CacheDb {
         Ok(db)
     }
 
+    /// A cache that lives in memory only, for tests elsewhere in the crate.
+    #[cfg(test)]
+    pub(crate) fn in_memory() -> Result<Self> {
+        let db = Self {
+            conn: Connection::open_in_memory()?,
+        };
+        db.init_tables()?;
+        Ok(db)
+    }
+
     fn db_path() -> PathBuf {
         // Keep the cache alongside whichever config.toml is active (respects
         // --config/NOSTARO_CONFIG) instead of always using ~/.nostaro, so isolated
@@ -70,13 +161,81 @@ impl CacheDb {
                 picture TEXT,
                 updated_at INTEGER NOT NULL
             );
+            CREATE TABLE IF NOT EXISTS nip05 (
+                pubkey TEXT PRIMARY KEY,
+                address TEXT,
+                verified INTEGER,
+                checked_at INTEGER
+            );
+            CREATE TABLE IF NOT EXISTS snapshots (
+                id TEXT PRIMARY KEY,
+                pubkey TEXT NOT NULL,
+                kind INTEGER NOT NULL,
+                created_at INTEGER NOT NULL,
+                raw_json TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_snapshots_owner ON snapshots(pubkey, kind);
+            CREATE TABLE IF NOT EXISTS state (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            );
             CREATE INDEX IF NOT EXISTS idx_events_kind ON events(kind);
             CREATE INDEX IF NOT EXISTS idx_events_pubkey ON events(pubkey);
             CREATE INDEX IF NOT EXISTS idx_events_created ON events(created_at);",
         )?;
+        self.init_search_index()
+    }
+
+    /// The full-text index over event content. It is an external-content FTS5
+    /// table kept in step with `events` by triggers, so every insert is
+    /// indexed without the callers doing anything. `INSERT OR REPLACE` only
+    /// fires the delete trigger for the replaced row with recursive triggers
+    /// on, hence the pragma. A cache created before the index existed is
+    /// indexed once, here.
+    fn init_search_index(&self) -> Result<()> {
+        let existed: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'events_fts'",
+            [],
+            |row| row.get(0),
+        )?;
+        self.conn.execute_batch(
+            "PRAGMA recursive_triggers = ON;
+            CREATE VIRTUAL TABLE IF NOT EXISTS events_fts USING fts5(
+                content,
+                content = 'events',
+                tokenize = 'trigram'
+            );
+            CREATE TRIGGER IF NOT EXISTS events_fts_insert AFTER INSERT ON events BEGIN
+                INSERT INTO events_fts (rowid, content) VALUES (new.rowid, new.content);
+            END;
+            CREATE TRIGGER IF NOT EXISTS events_fts_delete AFTER DELETE ON events BEGIN
+                INSERT INTO events_fts (events_fts, rowid, content)
+                    VALUES ('delete', old.rowid, old.content);
+            END;
+            CREATE TRIGGER IF NOT EXISTS events_fts_update AFTER UPDATE ON events BEGIN
+                INSERT INTO events_fts (events_fts, rowid, content)
+                    VALUES ('delete', old.rowid, old.content);
+                INSERT INTO events_fts (rowid, content) VALUES (new.rowid, new.content);
+            END;",
+        )?;
+        if existed == 0 {
+            self.reindex()?;
+        }
         Ok(())
     }
 
+    /// Rebuild the full-text index from the cached events, returning how many
+    /// events it now covers. Needed only if the index and the events drifted
+    /// apart, e.g. after the database file was vacuumed or edited by hand.
+    pub fn reindex(&self) -> Result<usize> {
+        self.conn
+            .execute_batch("INSERT INTO events_fts (events_fts) VALUES ('rebuild');")?;
+        let events: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM events", [], |row| row.get(0))?;
+        Ok(events as usize)
+    }
+
     #[allow(clippy::too_many_arguments)]
     pub fn store_event(
         &self,
@@ -150,9 +309,110 @@ impl CacheDb {
         }
     }
 
+    /// Record the NIP-05 address a profile claims, `None` when it claims none.
+    /// A row exists for every profile seen since, so a missing row means "not
+    /// looked at yet". A changed claim drops the result of checking the old one.
+    pub fn store_nip05_claim(&self, pubkey: &str, address: Option<&str>) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO nip05 (pubkey, address) VALUES (?1, ?2)
+             ON CONFLICT(pubkey) DO UPDATE SET
+                address = excluded.address, verified = NULL, checked_at = NULL
+             WHERE nip05.address IS NOT excluded.address",
+            rusqlite::params![pubkey, address],
+        )?;
+        Ok(())
+    }
+
+    /// Record the outcome of checking `address` for `pubkey`.
+    pub fn store_nip05_check(
+        &self,
+        pubkey: &str,
+        address: &str,
+        verified: bool,
+        checked_at: i64,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO nip05 (pubkey, address, verified, checked_at) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![pubkey, address, verified, checked_at],
+        )?;
+        Ok(())
+    }
+
+    pub fn get_nip05(&self, pubkey: &str) -> Result<Option<CachedNip05>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT address, verified, checked_at FROM nip05 WHERE pubkey = ?1")?;
+        let mut rows = stmt.query(rusqlite::params![pubkey])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(CachedNip05 {
+                address: row.get(0)?,
+                verified: row.get(1)?,
+                checked_at: row.get(2)?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// Keep a version of a replaceable event (our kind:0, our kind:3). Relays
+    /// only keep the latest one, so this is the only place older versions
+    /// survive — which is why `clear` leaves snapshots alone.
+    pub fn store_snapshot(
+        &self,
+        id: &str,
+        pubkey: &str,
+        kind: u16,
+        created_at: i64,
+        raw_json: &str,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO snapshots (id, pubkey, kind, created_at, raw_json) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![id, pubkey, kind as i64, created_at, raw_json],
+        )?;
+        Ok(())
+    }
+
+    /// Every recorded version of `pubkey`'s `kind`, oldest first.
+    pub fn snapshots(&self, pubkey: &str, kind: u16) -> Result<Vec<Snapshot>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, created_at, raw_json FROM snapshots WHERE pubkey = ?1 AND kind = ?2 ORDER BY created_at, id",
+        )?;
+        let rows = stmt.query_map(rusqlite::params![pubkey, kind as i64], |row| {
+            Ok(Snapshot {
+                id: row.get(0)?,
+                created_at: row.get(1)?,
+                raw_json: row.get(2)?,
+            })
+        })?;
+        let mut snapshots = Vec::new();
+        for row in rows {
+            snapshots.push(row?);
+        }
+        Ok(snapshots)
+    }
+
+    /// A small piece of remembered state, such as a read marker.
+    pub fn get_state(&self, key: &str) -> Result<Option<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT value FROM state WHERE key = ?1")?;
+        let mut rows = stmt.query(rusqlite::params![key])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_state(&self, key: &str, value: &str) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO state (key, value) VALUES (?1, ?2)",
+            rusqlite::params![key, value],
+        )?;
+        Ok(())
+    }
+
     pub fn clear(&self) -> Result<()> {
         self.conn
-            .execute_batch("DELETE FROM events; DELETE FROM profiles;")?;
+            .execute_batch("DELETE FROM events; DELETE FROM profiles; DELETE FROM nip05;")?;
         Ok(())
     }
 
@@ -187,6 +447,99 @@ impl CacheDb {
         }
         Ok(events)
     }
+
+    /// Search the cached events. With text, the best matches come first
+    /// (BM25), each with a snippet; without, the newest do.
+    pub fn search_events(&self, query: &EventQuery) -> Result<Vec<SearchHit>> {
+        use rusqlite::types::Value;
+
+        let (expression, short_words) = query.text.as_deref().map(text_terms).unwrap_or_default();
+        let mut params: Vec<Value> = Vec::new();
+        let mut sql = String::from(
+            "SELECT e.id, e.pubkey, e.kind, e.content, e.created_at, e.tags_json, e.raw_json",
+        );
+        match &expression {
+            Some(expression) => {
+                sql.push_str(
+                    ", snippet(events_fts, 0, '**', '**', '…', 48), bm25(events_fts) \
+                     FROM events_fts JOIN events e ON e.rowid = events_fts.rowid \
+                     WHERE events_fts MATCH ?",
+                );
+                params.push(Value::Text(expression.clone()));
+            }
+            None => sql.push_str(", NULL, NULL FROM events e WHERE 1 = 1"),
+        }
+
+        let placeholders = |count: usize| vec!["?"; count].join(", ");
+        for word in &short_words {
+            sql.push_str(" AND e.content LIKE ? ESCAPE '\\'");
+            params.push(Value::Text(like_pattern(word)));
+        }
+        if !query.authors.is_empty() {
+            sql.push_str(&format!(
+                " AND e.pubkey IN ({})",
+                placeholders(query.authors.len())
+            ));
+            params.extend(query.authors.iter().cloned().map(Value::Text));
+        }
+        if !query.kinds.is_empty() {
+            sql.push_str(&format!(
+                " AND e.kind IN ({})",
+                placeholders(query.kinds.len())
+            ));
+            params.extend(query.kinds.iter().map(|kind| Value::Integer(*kind as i64)));
+        }
+        for (tag, values) in [("t", &query.hashtags), ("p", &query.mentions)] {
+            if values.is_empty() {
+                continue;
+            }
+            sql.push_str(&format!(
+                " AND EXISTS (SELECT 1 FROM json_each(e.tags_json) AS tag \
+                 WHERE json_extract(tag.value, '$[0]') = '{}' \
+                 AND lower(json_extract(tag.value, '$[1]')) IN ({}))",
+                tag,
+                placeholders(values.len())
+            ));
+            params.extend(values.iter().map(|value| Value::Text(value.to_lowercase())));
+        }
+        if let Some(since) = query.since {
+            sql.push_str(" AND e.created_at >= ?");
+            params.push(Value::Integer(since));
+        }
+        if let Some(until) = query.until {
+            sql.push_str(" AND e.created_at <= ?");
+            params.push(Value::Integer(until));
+        }
+        if expression.is_some() {
+            sql.push_str(" ORDER BY bm25(events_fts), e.created_at DESC");
+        } else {
+            sql.push_str(" ORDER BY e.created_at DESC");
+        }
+        sql.push_str(" LIMIT ?");
+        params.push(Value::Integer(query.limit as i64));
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| {
+            Ok(SearchHit {
+                event: CachedEvent {
+                    id: row.get(0)?,
+                    pubkey: row.get(1)?,
+                    kind: row.get::<_, i64>(2)? as u16,
+                    content: row.get(3)?,
+                    created_at: row.get(4)?,
+                    tags_json: row.get(5)?,
+                    raw_json: row.get(6)?,
+                },
+                snippet: row.get(7)?,
+                rank: row.get(8)?,
+            })
+        })?;
+        let mut hits = Vec::new();
+        for row in rows {
+            hits.push(row?);
+        }
+        Ok(hits)
+    }
 }
 
 #[cfg(test)]
@@ -194,10 +547,7 @@ mod tests {
     use super::*;
 
     fn test_db() -> CacheDb {
-        let conn = Connection::open_in_memory().unwrap();
-        let db = CacheDb { conn };
-        db.init_tables().unwrap();
-        db
+        CacheDb::in_memory().unwrap()
     }
 
     #[test]
@@ -243,4 +593,163 @@ mod tests {
         assert_eq!(events[0].content, "third");
         assert_eq!(events[1].content, "second");
     }
+
+    #[test]
+    fn test_state_is_overwritten_in_place() {
+        let db = test_db();
+        assert!(db
+            .get_state("notifications.last_read.pk")
+            .unwrap()
+            .is_none());
+        db.set_state("notifications.last_read.pk", "100").unwrap();
+        db.set_state("notifications.last_read.pk", "200").unwrap();
+        assert_eq!(
+            db.get_state("notifications.last_read.pk")
+                .unwrap()
+                .as_deref(),
+            Some("200")
+        );
+    }
+
+    #[test]
+    fn test_a_changed_nip05_claim_forgets_the_old_check() {
+        let db = test_db();
+        assert!(db.get_nip05("pk").unwrap().is_none());
+
+        db.store_nip05_claim("pk", Some("bob@example.com")).unwrap();
+        db.store_nip05_check("pk", "bob@example.com", true, 100)
+            .unwrap();
+        // Seeing the same claim again keeps the result.
+        db.store_nip05_claim("pk", Some("bob@example.com")).unwrap();
+        assert_eq!(db.get_nip05("pk").unwrap().unwrap().verified, Some(true));
+
+        db.store_nip05_claim("pk", Some("bob@elsewhere.example"))
+            .unwrap();
+        let row = db.get_nip05("pk").unwrap().unwrap();
+        assert_eq!(row.address.as_deref(), Some("bob@elsewhere.example"));
+        assert_eq!((row.verified, row.checked_at), (None, None));
+
+        db.store_nip05_claim("pk", None).unwrap();
+        assert_eq!(db.get_nip05("pk").unwrap().unwrap().address, None);
+    }
+
+    #[test]
+    fn test_snapshots_are_kept_once_in_order_and_survive_clear() {
+        let db = test_db();
+        db.store_snapshot("b", "pk", 0, 200, "{\"v\":2}").unwrap();
+        db.store_snapshot("a", "pk", 0, 100, "{\"v\":1}").unwrap();
+        db.store_snapshot("a", "pk", 0, 100, "{\"v\":1}").unwrap();
+        db.store_snapshot("c", "pk", 3, 300, "{}").unwrap();
+        db.store_snapshot("d", "other", 0, 400, "{}").unwrap();
+        db.clear().unwrap();
+
+        let ids: Vec<String> = db
+            .snapshots("pk", 0)
+            .unwrap()
+            .into_iter()
+            .map(|snapshot| snapshot.id)
+            .collect();
+        assert_eq!(ids, vec!["a", "b"]);
+    }
+
+    fn query(text: &str) -> EventQuery {
+        EventQuery {
+            text: Some(text.to_string()),
+            limit: 10,
+            ..EventQuery::default()
+        }
+    }
+
+    #[test]
+    fn test_inserted_events_are_searchable_with_snippets() {
+        let db = test_db();
+        db.store_event("e1", "pk", 1, "learning Rust and Nostr", 100, "[]", "{}")
+            .unwrap();
+        db.store_event("e2", "pk", 1, "日本語のノストラです", 200, "[]", "{}")
+            .unwrap();
+        db.store_event("e3", "pk", 1, "nothing here", 300, "[]", "{}")
+            .unwrap();
+
+        let hits = db.search_events(&query("nostr rust")).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].event.id, "e1");
+        assert!(hits[0].snippet.as_deref().unwrap().contains("**Rust**"));
+        assert!(hits[0].rank.is_some());
+
+        let hits = db.search_events(&query("ノストラ")).unwrap();
+        assert_eq!(hits[0].event.id, "e2");
+
+        // Too short for the trigram index: matched with LIKE instead.
+        let hits = db.search_events(&query("日本")).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert!(hits[0].snippet.is_none());
+
+        // FTS5 syntax in the input is matched literally, not parsed.
+        assert!(db
+            .search_events(&query("rust-nostr OR"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_replaced_and_cleared_events_leave_the_index() {
+        let db = test_db();
+        db.store_event("e1", "pk", 1, "first draft", 100, "[]", "{}")
+            .unwrap();
+        db.store_event("e1", "pk", 1, "final words", 100, "[]", "{}")
+            .unwrap();
+        assert!(db.search_events(&query("draft")).unwrap().is_empty());
+        assert_eq!(db.search_events(&query("final")).unwrap().len(), 1);
+
+        assert_eq!(db.reindex().unwrap(), 1);
+        assert_eq!(db.search_events(&query("final")).unwrap().len(), 1);
+
+        db.clear().unwrap();
+        assert!(db.search_events(&query("final")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_search_filters_by_author_kind_tags_and_time() {
+        let db = test_db();
+        let tags = r#"[["t","Nostr"],["p","abcd"]]"#;
+        db.store_event("e1", "alice", 1, "gm", 100, tags, "{}")
+            .unwrap();
+        db.store_event("e2", "bob", 1, "gm", 200, "[]", "{}")
+            .unwrap();
+        db.store_event("e3", "alice", 30023, "gm", 300, "[]", "{}")
+            .unwrap();
+
+        let search = |query: EventQuery| -> Vec<String> {
+            db.search_events(&EventQuery { limit: 10, ..query })
+                .unwrap()
+                .into_iter()
+                .map(|hit| hit.event.id)
+                .collect()
+        };
+        assert_eq!(search(EventQuery::default()), vec!["e3", "e2", "e1"]);
+        assert_eq!(
+            search(EventQuery {
+                authors: vec!["alice".to_string()],
+                kinds: vec![1],
+                ..EventQuery::default()
+            }),
+            vec!["e1"]
+        );
+        assert_eq!(
+            search(EventQuery {
+                hashtags: vec!["nostr".to_string()],
+                mentions: vec!["abcd".to_string()],
+                ..EventQuery::default()
+            }),
+            vec!["e1"]
+        );
+        assert_eq!(
+            search(EventQuery {
+                since: Some(150),
+                until: Some(250),
+                ..EventQuery::default()
+            }),
+            vec!["e2"]
+        );
+    }
 }
@@ -1,8 +1,23 @@
 use anyhow::{bail, Result};
 use nostr_sdk::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
 use crate::config::NostaroConfig;
+use crate::utils::EventTarget;
+
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// Turn `--dry-run` on for this process. Called once, before the command runs.
+pub fn set_dry_run(enabled: bool) {
+    DRY_RUN.store(enabled, Ordering::SeqCst);
+}
+
+/// Whether events are being signed and shown instead of sent.
+pub fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::SeqCst)
+}
 
 pub async fn create_client(keys: &Keys, config: &NostaroConfig) -> Result<Client> {
     create_client_with_relay_list(keys, &config.active_relays()).await
@@ -56,41 +71,217 @@ where
 }
 
 /// Publish an event and confirm at least one relay accepted it.
+///
+/// Under `--dry-run` the event is signed and printed instead, and the returned
+/// `Output` names no relay at all — every publishing command goes through
+/// here, so none of them can send anything by accident.
 pub async fn publish(client: &Client, builder: EventBuilder) -> Result<Output<EventId>> {
-    let output = client.send_event_builder(builder).await?;
+    let event = client.sign_event_builder(builder).await?;
+    publish_event(client, &event).await
+}
+
+/// [`publish`] for an event that is already signed, for callers that keep a
+/// copy of what they published.
+pub async fn publish_event(client: &Client, event: &Event) -> Result<Output<EventId>> {
+    if is_dry_run() {
+        show_dry_run(client, event).await?;
+        return Ok(Output {
+            val: event.id,
+            success: HashSet::new(),
+            failed: HashMap::new(),
+        });
+    }
+
+    let output = client.send_event(event).await?;
     check_publish_output(&output)?;
     Ok(output)
 }
 
+/// Print what `--dry-run` would have published: the full signed event, and for
+/// a kind:3 the follows it would add and drop compared with the current list.
+async fn show_dry_run(client: &Client, event: &Event) -> Result<()> {
+    println!("Dry run: signed but not published:");
+    println!("{}", serde_json::to_string_pretty(event)?);
+
+    if event.kind == Kind::ContactList {
+        let current = fetch_contact_list(client, &event.pubkey).await?;
+        let (added, removed) = contact_list_diff(current.as_ref(), event);
+        match &current {
+            Some(current) => println!(
+                "Contact list: {} -> {} follow(s), {} added, {} removed",
+                contact_pubkeys(current).len(),
+                contact_pubkeys(event).len(),
+                added.len(),
+                removed.len()
+            ),
+            None => println!(
+                "Contact list: no current kind:3 found; this would be the whole list ({} follow(s))",
+                contact_pubkeys(event).len()
+            ),
+        }
+        for pubkey in &added {
+            println!("  + {}", pubkey.to_bech32()?);
+        }
+        for pubkey in &removed {
+            println!("  - {}", pubkey.to_bech32()?);
+        }
+    }
+    Ok(())
+}
+
+/// The `p` tag pubkeys of a contact list, in tag order.
+pub fn contact_pubkeys(event: &Event) -> Vec<PublicKey> {
+    event
+        .tags
+        .iter()
+        .filter_map(|tag| match tag.as_standardized() {
+            Some(TagStandard::PublicKey { public_key, .. }) => Some(*public_key),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Follows `new` adds to and drops from `current`, each in tag order.
+///
+/// Pure — it compares two events that are already in hand.
+pub fn contact_list_diff(current: Option<&Event>, new: &Event) -> (Vec<PublicKey>, Vec<PublicKey>) {
+    let before = current.map(contact_pubkeys).unwrap_or_default();
+    let after = contact_pubkeys(new);
+    let before_set: HashSet<PublicKey> = before.iter().copied().collect();
+    let after_set: HashSet<PublicKey> = after.iter().copied().collect();
+
+    let added = after
+        .into_iter()
+        .filter(|pk| !before_set.contains(pk))
+        .collect();
+    let removed = before
+        .into_iter()
+        .filter(|pk| !after_set.contains(pk))
+        .collect();
+    (added, removed)
+}
+
+/// Print a command's "it went out" line — or, under `--dry-run`, that nothing
+/// did, so a dry run never reports a publish that did not happen.
+pub fn report_published(message: &str) {
+    if is_dry_run() {
+        println!("Dry run: nothing was published.");
+    } else {
+        println!("{}", message);
+    }
+}
+
+/// Send an already-signed event and hand back the per-relay outcome as is.
+///
+/// Unlike [`publish`] this does not turn "every relay refused" into an error:
+/// `event --batch` reports the outcome of each event instead of stopping at the
+/// first one nobody stored. Callers handle `--dry-run` themselves.
+pub async fn send_signed(client: &Client, event: &Event) -> Result<Output<EventId>> {
+    Ok(client.send_event(event).await?)
+}
+
 pub async fn post_note(client: &Client, content: &str) -> Result<()> {
     let builder = EventBuilder::text_note(content);
     publish(client, builder).await?;
     Ok(())
 }
 
-pub async fn reply_note(client: &Client, reply_to: &Event, content: &str) -> Result<()> {
+pub async fn reply_note(
+    client: &Client,
+    reply_to: &Event,
+    content: &str,
+    extra_tags: Vec<Tag>,
+) -> Result<()> {
     let reply_id_hex = reply_to.id.to_hex();
-    let tags = vec![
+    let mut tags = vec![
         Tag::parse(["e", &reply_id_hex, "", "reply"])?,
         Tag::public_key(reply_to.pubkey),
     ];
+    tags.extend(extra_tags);
     let builder = EventBuilder::text_note(content).tags(tags);
     publish(client, builder).await?;
     Ok(())
 }
 
-pub async fn repost_event(client: &Client, event: &Event) -> Result<()> {
-    let builder = EventBuilder::repost(event, None);
+/// kind:16, the NIP-18 generic repost for anything that is not a kind:1.
+pub const GENERIC_REPOST_KIND: u16 = 16;
+
+/// The NIP-18 repost of `event`: kind:6 for a text note, kind:16 with a `k`
+/// tag naming the reposted kind for everything else (articles, pictures,
+/// channel messages). Addressable events also get an `a` tag, so clients can
+/// follow the repost to the newest version. `relay_hint` is where the event
+/// can be found.
+///
+/// Pure — it builds the event and talks to no relay.
+pub fn repost_builder(event: &Event, relay_hint: Option<&str>) -> Result<EventBuilder> {
+    let relay = relay_hint.unwrap_or_default();
+    let mut tags = vec![
+        Tag::parse(["e", event.id.to_hex().as_str(), relay])?,
+        Tag::parse(["p", event.pubkey.to_hex().as_str(), relay])?,
+    ];
+
+    let kind = if event.kind == Kind::TextNote {
+        Kind::Repost
+    } else {
+        tags.push(Tag::parse(["k", event.kind.as_u16().to_string().as_str()])?);
+        Kind::from(GENERIC_REPOST_KIND)
+    };
+
+    if (30000..40000).contains(&event.kind.as_u16()) {
+        let identifier = event
+            .tags
+            .iter()
+            .find_map(|tag| match tag.as_slice() {
+                [name, value, ..] if name == "d" => Some(value.clone()),
+                _ => None,
+            })
+            .unwrap_or_default();
+        let coordinate = format!(
+            "{}:{}:{}",
+            event.kind.as_u16(),
+            event.pubkey.to_hex(),
+            identifier
+        );
+        tags.push(Tag::parse(["a", coordinate.as_str(), relay])?);
+    }
+
+    // NIP-70: a protected event must not be republished by anyone but its
+    // author, and embedding it would let any relay re-broadcast it.
+    let protected = event
+        .tags
+        .iter()
+        .any(|tag| tag.as_slice().first().map(String::as_str) == Some("-"));
+    let content = if protected {
+        String::new()
+    } else {
+        event.as_json()
+    };
+
+    Ok(EventBuilder::new(kind, content).tags(tags))
+}
+
+pub async fn repost_event(client: &Client, event: &Event, relay_hint: Option<&str>) -> Result<()> {
+    let builder = repost_builder(event, relay_hint)?;
     publish(client, builder).await?;
     Ok(())
 }
 
-/// The filter behind the global timeline: the newest kind:1, **with no author
-/// constraint at all**.
+/// What a timeline shows: text notes, and reposts (NIP-18) — kind:6 of a
+/// text note, kind:16 of anything else.
+pub fn timeline_kinds() -> [Kind; 3] {
+    [
+        Kind::TextNote,
+        Kind::Repost,
+        Kind::from(GENERIC_REPOST_KIND),
+    ]
+}
+
+/// The filter behind the global timeline: the newest notes and reposts, **with
+/// no author constraint at all**.
 ///
 /// Pure — it takes a limit and builds a filter, it talks to no relay.
 pub fn global_timeline_filter(limit: usize) -> Filter {
-    Filter::new().kind(Kind::TextNote).limit(limit)
+    Filter::new().kinds(timeline_kinds()).limit(limit)
 }
 
 /// The global timeline is built from a limit and nothing else: no
@@ -110,11 +301,11 @@ pub async fn fetch_timeline(client: &Client, limit: usize) -> Result<Vec<Event>>
     Ok(events)
 }
 
-/// The filter behind the follow-based timeline: the newest kind:1 **from these
-/// authors**. The counterpart of [`global_timeline_filter`].
+/// The filter behind the follow-based timeline: the newest notes and reposts
+/// **from these authors**. The counterpart of [`global_timeline_filter`].
 pub fn timeline_filter_for_authors(authors: &[PublicKey], limit: usize) -> Filter {
     Filter::new()
-        .kind(Kind::TextNote)
+        .kinds(timeline_kinds())
         .authors(authors.to_vec())
         .limit(limit)
 }
@@ -131,17 +322,84 @@ pub async fn fetch_timeline_for_authors(
     Ok(events)
 }
 
-pub async fn search_notes(client: &Client, query: &str, limit: usize) -> Result<Vec<Event>> {
-    let filter = Filter::new()
-        .kind(Kind::TextNote)
-        .search(query)
-        .limit(limit);
-    let events = client.fetch_events(filter, Duration::from_secs(10)).await?;
+/// Fetch search results, newest first and at most `limit` of them. With
+/// `relays`, only those relays of the pool are asked: NIP-50 queries go only to
+/// relays that support them, because the others either refuse the
+/// subscription or ignore `search` and answer with unrelated notes.
+pub async fn search_events(
+    client: &Client,
+    filter: Filter,
+    relays: Option<&[String]>,
+    limit: usize,
+) -> Result<Vec<Event>> {
+    let timeout = Duration::from_secs(10);
+    let events = match relays {
+        Some(relays) => {
+            client
+                .fetch_events_from(relays.to_vec(), filter, timeout)
+                .await?
+        }
+        None => client.fetch_events(filter, timeout).await?,
+    };
     let mut events: Vec<Event> = events.into_iter().collect();
     events.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+    events.truncate(limit);
     Ok(events)
 }
 
+/// Also use the relays a pubkey or event reference came with (nprofile,
+/// nevent, NIP-05). Hints that are not usable relay URLs are skipped.
+pub async fn add_relay_hints(client: &Client, hints: &[String]) {
+    for relay in hints {
+        let _ = client.add_relay(relay).await;
+    }
+    if !hints.is_empty() {
+        client.connect().await;
+    }
+}
+
+/// The HTTP(S) URL serving a relay's NIP-11 information document: the relay
+/// URL itself with `ws` swapped for `http`.
+pub fn relay_info_url(relay: &str) -> String {
+    if let Some(rest) = relay.strip_prefix("wss://") {
+        format!("https://{}", rest)
+    } else if let Some(rest) = relay.strip_prefix("ws://") {
+        format!("http://{}", rest)
+    } else {
+        relay.to_string()
+    }
+}
+
+/// Fetch a relay's NIP-11 information document.
+pub async fn fetch_relay_info(relay: &str, timeout: Duration) -> Result<serde_json::Value> {
+    let http_client = reqwest::Client::builder().timeout(timeout).build()?;
+    let info = http_client
+        .get(relay_info_url(relay))
+        .header("Accept", "application/nostr+json")
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(info)
+}
+
+/// The NIPs a NIP-11 document lists in `supported_nips`. Some relays list them
+/// as strings, so `"50"` counts as well as `50`.
+pub fn supported_nips(info: &serde_json::Value) -> Vec<u64> {
+    info.get("supported_nips")
+        .and_then(|nips| nips.as_array())
+        .map(|nips| {
+            nips.iter()
+                .filter_map(|nip| {
+                    nip.as_u64()
+                        .or_else(|| nip.as_str().and_then(|s| s.trim().parse().ok()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 pub async fn fetch_profile(client: &Client, pubkey: &PublicKey) -> Result<Option<Metadata>> {
     fetch_profile_with_timeout(client, pubkey, Duration::from_secs(10)).await
 }
@@ -155,9 +413,17 @@ pub async fn fetch_profile_with_timeout(
     Ok(metadata)
 }
 
+/// The author's latest kind:0 event, if any — the raw event, so that fields
+/// `Metadata` does not model survive an edit. Newest by `created_at`, because
+/// each relay may return its own copy.
+pub async fn fetch_profile_event(client: &Client, pubkey: &PublicKey) -> Result<Option<Event>> {
+    let filter = Filter::new().kind(Kind::Metadata).author(*pubkey).limit(1);
+    let events = client.fetch_events(filter, Duration::from_secs(10)).await?;
+    Ok(events.into_iter().max_by_key(|e| e.created_at))
+}
+
 pub async fn set_metadata(client: &Client, metadata: &Metadata) -> Result<()> {
-    let output = client.set_metadata(metadata).await?;
-    check_publish_output(&output)?;
+    publish(client, EventBuilder::metadata(metadata)).await?;
     Ok(())
 }
 
@@ -231,16 +497,15 @@ pub async fn fetch_followers(client: &Client, pubkey: &PublicKey) -> Result<Vec<
     Ok(followers)
 }
 
-pub async fn publish_contact_list(client: &Client, contacts: &[PublicKey]) -> Result<()> {
-    let mut tags = Vec::new();
-    for contact in contacts {
-        tags.push(Tag::public_key(*contact));
-    }
-
-    let builder = EventBuilder::new(Kind::ContactList, "").tags(tags);
-    publish(client, builder).await?;
-
-    Ok(())
+/// A kind:3 replacing `current` with an edited tag list. The content is kept
+/// as it was — some clients still keep a relay list there — so only the tags
+/// the caller changed differ. Signing and publishing are the caller's, so the
+/// result can be checked against the previous list first.
+pub fn contact_list(current: Option<&Event>, tags: Vec<Tag>) -> EventBuilder {
+    let content = current
+        .map(|event| event.content.clone())
+        .unwrap_or_default();
+    EventBuilder::new(Kind::ContactList, content).tags(tags)
 }
 
 pub async fn fetch_event_by_id(client: &Client, event_id: &EventId) -> Result<Option<Event>> {
@@ -251,7 +516,138 @@ pub async fn fetch_event_by_id(client: &Client, event_id: &EventId) -> Result<Op
     Ok(events.into_iter().next())
 }
 
+/// The newest version of an addressable event (`kind:pubkey:d`). Relays may
+/// still hold superseded copies, so the newest by `created_at` wins.
+pub async fn fetch_addressable_event(
+    client: &Client,
+    kind: Kind,
+    author: &PublicKey,
+    identifier: &str,
+) -> Result<Option<Event>> {
+    let filter = Filter::new()
+        .kind(kind)
+        .author(*author)
+        .identifier(identifier);
+
+    let events = client.fetch_events(filter, Duration::from_secs(10)).await?;
+    Ok(events.into_iter().max_by_key(|e| e.created_at))
+}
+
+/// The event an [`EventTarget`] names: by id, or the newest version at an
+/// address.
+pub async fn fetch_event_target(client: &Client, target: &EventTarget) -> Result<Option<Event>> {
+    match target {
+        EventTarget::Event(event_id, _) => fetch_event_by_id(client, event_id).await,
+        EventTarget::Address(coordinate, _) => {
+            fetch_addressable_event(
+                client,
+                coordinate.kind,
+                &coordinate.public_key,
+                &coordinate.identifier,
+            )
+            .await
+        }
+    }
+}
+
+/// [`fetch_event_target`], asking each relay on its own so the answer also says
+/// which relays actually returned the event (in URL order). A relay that fails
+/// or times out simply does not count as a source.
+pub async fn fetch_event_target_with_sources(
+    client: &Client,
+    target: &EventTarget,
+) -> Result<Option<(Event, Vec<RelayUrl>)>> {
+    let filter = match target {
+        EventTarget::Event(event_id, _) => Filter::new().id(*event_id),
+        EventTarget::Address(coordinate, _) => Filter::new()
+            .kind(coordinate.kind)
+            .author(coordinate.public_key)
+            .identifier(&coordinate.identifier),
+    };
+
+    let mut fetches = tokio::task::JoinSet::new();
+    for (url, relay) in client.relays().await {
+        let filter = filter.clone();
+        fetches.spawn(async move {
+            let events = relay
+                .fetch_events(filter, Duration::from_secs(10), ReqExitPolicy::ExitOnEOSE)
+                .await;
+            (url, events)
+        });
+    }
+
+    let mut found: Vec<(RelayUrl, Event)> = Vec::new();
+    while let Some(joined) = fetches.join_next().await {
+        let Ok((url, Ok(events))) = joined else {
+            continue;
+        };
+        found.extend(events.into_iter().map(|event| (url.clone(), event)));
+    }
+
+    // As in `fetch_addressable_event`, the newest version at an address wins.
+    let Some(event) = found
+        .iter()
+        .map(|(_, event)| event)
+        .max_by_key(|event| event.created_at)
+        .cloned()
+    else {
+        return Ok(None);
+    };
+    let mut sources: Vec<RelayUrl> = found
+        .into_iter()
+        .filter(|(_, candidate)| candidate.id == event.id)
+        .map(|(url, _)| url)
+        .collect();
+    sources.sort();
+    sources.dedup();
+    Ok(Some((event, sources)))
+}
+
+/// The newest version of every addressable event of `kind` by `author`, newest
+/// first. Superseded copies of the same `d` tag are dropped.
+pub async fn fetch_addressable_events(
+    client: &Client,
+    kind: Kind,
+    author: &PublicKey,
+    limit: usize,
+) -> Result<Vec<Event>> {
+    let filter = Filter::new().kind(kind).author(*author).limit(limit);
+    let events = client.fetch_events(filter, Duration::from_secs(10)).await?;
+
+    let mut latest: std::collections::HashMap<String, Event> = std::collections::HashMap::new();
+    for event in events {
+        let identifier = event
+            .tags
+            .iter()
+            .find_map(|tag| match tag.as_slice() {
+                [name, value, ..] if name == "d" => Some(value.clone()),
+                _ => None,
+            })
+            .unwrap_or_default();
+        match latest.get(&identifier) {
+            Some(kept) if kept.created_at >= event.created_at => {}
+            _ => {
+                latest.insert(identifier, event);
+            }
+        }
+    }
+
+    let mut events: Vec<Event> = latest.into_values().collect();
+    events.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+    Ok(events)
+}
+
 pub async fn send_dm(client: &Client, receiver: PublicKey, message: &str) -> Result<()> {
+    if is_dry_run() {
+        // The gift wraps are opaque by design, so showing them proves nothing;
+        // the kind:14 inside them is what would be said.
+        let sender = client.signer().await?.get_public_key().await?;
+        let rumor = EventBuilder::private_msg_rumor(receiver, message).build(sender);
+        println!("Dry run: not sent. This kind:14 would be sealed and gift-wrapped (NIP-17) for the receiver and for you:");
+        println!("{}", serde_json::to_string_pretty(&rumor)?);
+        return Ok(());
+    }
+
     let output = client.send_private_msg(receiver, message, []).await?;
     check_publish_output(&output)?;
     Ok(())
@@ -409,14 +805,17 @@ mod tests {
         assert_eq!(filter.limit, global_timeline_filter(20).limit);
     }
 
-    /// kind:1 only. Other kinds are not part of #10 and must not leak in.
+    /// Notes and reposts only. Other kinds (reactions, zaps, articles) are not
+    /// part of a timeline and must not leak in.
     #[test]
-    fn global_timeline_filter_is_text_notes_only() {
+    fn global_timeline_filter_is_notes_and_reposts_only() {
         let kinds = global_timeline_filter(20)
             .kinds
-            .expect("the global timeline is restricted to one kind");
-        assert_eq!(kinds.len(), 1);
-        assert!(kinds.contains(&Kind::TextNote));
+            .expect("the global timeline is restricted to the timeline kinds");
+        assert_eq!(kinds.len(), 3);
+        for kind in timeline_kinds() {
+            assert!(kinds.contains(&kind), "{:?}", kind);
+        }
     }
 
     #[test]
@@ -426,7 +825,7 @@ mod tests {
         }
     }
 
-    /// kind + limit and nothing else. No since/until/search/ids/tag query: the
+    /// kinds + limit and nothing else. No since/until/search/ids/tag query: the
     /// goal is "the newest N notes, whoever wrote them", and every extra
     /// dimension is a filter option nobody asked for.
     ///
@@ -439,7 +838,118 @@ mod tests {
     fn global_timeline_filter_adds_no_other_constraints() {
         assert_eq!(
             serde_json::to_value(global_timeline_filter(20)).unwrap(),
-            serde_json::json!({ "kinds": [1], "limit": 20 }),
+            serde_json::json!({ "kinds": [1, 6, 16], "limit": 20 }),
+        );
+    }
+
+    fn tag_vecs(event: &Event) -> Vec<Vec<String>> {
+        event
+            .tags
+            .iter()
+            .map(|tag| tag.as_slice().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn a_text_note_is_reposted_as_kind_6() {
+        let author = Keys::generate();
+        let note = EventBuilder::text_note("hi")
+            .sign_with_keys(&author)
+            .unwrap();
+        let repost = repost_builder(&note, Some("wss://relay.example.com"))
+            .unwrap()
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+
+        assert_eq!(repost.kind, Kind::Repost);
+        assert_eq!(
+            tag_vecs(&repost),
+            vec![
+                vec!["e", note.id.to_hex().as_str(), "wss://relay.example.com"],
+                vec![
+                    "p",
+                    author.public_key().to_hex().as_str(),
+                    "wss://relay.example.com"
+                ],
+            ]
+        );
+        assert_eq!(Event::from_json(&repost.content).unwrap(), note);
+    }
+
+    #[test]
+    fn other_kinds_are_generic_reposts_with_a_k_tag() {
+        let author = Keys::generate();
+        let article = EventBuilder::new(Kind::from(30023u16), "# hello")
+            .tags([Tag::identifier("hello")])
+            .sign_with_keys(&author)
+            .unwrap();
+        let repost = repost_builder(&article, None)
+            .unwrap()
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+
+        assert_eq!(repost.kind.as_u16(), GENERIC_REPOST_KIND);
+        let tags = tag_vecs(&repost);
+        assert!(tags.contains(&vec!["k".to_string(), "30023".to_string()]));
+        let coordinate = format!("30023:{}:hello", author.public_key().to_hex());
+        assert!(tags.contains(&vec!["a".to_string(), coordinate, String::new()]));
+    }
+
+    #[test]
+    fn protected_events_are_reposted_without_their_content() {
+        let note = EventBuilder::text_note("members only")
+            .tags([Tag::parse(["-"]).unwrap()])
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+        let repost = repost_builder(&note, None)
+            .unwrap()
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+        assert!(repost.content.is_empty());
+    }
+
+    fn contact_list(keys: &Keys, follows: &[PublicKey]) -> Event {
+        EventBuilder::new(Kind::ContactList, "")
+            .tags(follows.iter().map(|pk| Tag::public_key(*pk)))
+            .sign_with_keys(keys)
+            .unwrap()
+    }
+
+    #[test]
+    fn contact_list_diff_names_added_and_removed_follows() {
+        let me = Keys::generate();
+        let [a, b, c] = [(); 3].map(|_| Keys::generate().public_key());
+        let current = contact_list(&me, &[a, b]);
+        let new = contact_list(&me, &[b, c]);
+
+        assert_eq!(contact_list_diff(Some(&current), &new), (vec![c], vec![a]));
+        assert_eq!(
+            contact_list_diff(None, &new),
+            (vec![b, c], vec![]),
+            "without a current list everything is an addition"
         );
+        assert_eq!(
+            contact_list_diff(Some(&current), &current),
+            (vec![], vec![])
+        );
+    }
+
+    #[test]
+    fn relay_info_lives_at_the_http_form_of_the_relay_url() {
+        assert_eq!(
+            relay_info_url("wss://relay.nostr.band"),
+            "https://relay.nostr.band"
+        );
+        assert_eq!(
+            relay_info_url("ws://localhost:7777"),
+            "http://localhost:7777"
+        );
+    }
+
+    #[test]
+    fn supported_nips_accepts_numbers_and_numeric_strings() {
+        let info = serde_json::json!({"supported_nips": [1, "11", 50, "x"]});
+        assert_eq!(supported_nips(&info), vec![1, 11, 50]);
+        assert!(supported_nips(&serde_json::json!({"name": "r"})).is_empty());
     }
 }
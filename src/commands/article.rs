@@ -0,0 +1,766 @@
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use nostr_sdk::prelude::*;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::client;
+use crate::config::NostaroConfig;
+use crate::keys;
+use crate::utils::resolve_pubkey_with_hints;
+
+/// NIP-23 long-form article.
+pub const ARTICLE_KIND: u16 = 30023;
+/// NIP-23 draft: same shape as an article, a different kind so clients keep it
+/// out of feeds.
+pub const DRAFT_KIND: u16 = 30024;
+
+/// Keys a frontmatter block may carry. Anything else is rejected, for the same
+/// reason `event --file` rejects unknown fields: a `titel:` typo should fail
+/// loudly instead of publishing an article that quietly lost its title.
+const FRONTMATTER_KEYS: [&str; 6] = ["title", "summary", "image", "tags", "published_at", "slug"];
+
+/// A frontmatter value: a scalar, or a list (only `tags` takes one).
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FrontmatterValue {
+    Scalar(String),
+    List(Vec<String>),
+}
+
+/// The metadata block at the top of an article file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Frontmatter {
+    pub title: Option<String>,
+    pub summary: Option<String>,
+    pub image: Option<String>,
+    pub tags: Vec<String>,
+    /// Unix seconds, already normalised from whatever form the file used.
+    pub published_at: Option<u64>,
+    /// The `d` tag. What makes a republish replace the earlier version instead
+    /// of creating a second article.
+    pub slug: Option<String>,
+}
+
+/// An article file split into its metadata and its Markdown body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArticleSource {
+    pub frontmatter: Frontmatter,
+    pub body: String,
+}
+
+/// Split `text` into frontmatter and body.
+///
+/// YAML frontmatter is fenced by `---`, TOML by `+++`, both on the very first
+/// line. A file with no fence is all body. Only the flat subset of YAML that
+/// frontmatter actually uses is understood — `key: value`, `key: [a, b]` and a
+/// `key:` followed by `- item` lines — which keeps a YAML parser out of the
+/// dependency tree.
+pub fn parse_article(text: &str) -> Result<ArticleSource> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut lines = text.lines();
+    let fence = match lines.next().map(str::trim_end) {
+        Some("---") => "---",
+        Some("+++") => "+++",
+        _ => {
+            return Ok(ArticleSource {
+                frontmatter: Frontmatter::default(),
+                body: text.trim().to_string(),
+            })
+        }
+    };
+
+    let mut header = Vec::new();
+    let mut closed = false;
+    for line in lines.by_ref() {
+        if line.trim_end() == fence {
+            closed = true;
+            break;
+        }
+        header.push(line);
+    }
+    if !closed {
+        bail!(
+            "the frontmatter opened with `{}` on the first line is never closed",
+            fence
+        );
+    }
+
+    let values = if fence == "---" {
+        parse_yaml_frontmatter(&header)?
+    } else {
+        parse_toml_frontmatter(&header.join("\n"))?
+    };
+    let body: Vec<&str> = lines.collect();
+
+    Ok(ArticleSource {
+        frontmatter: frontmatter_from_values(values)?,
+        body: body.join("\n").trim().to_string(),
+    })
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote) {
+            return value[1..value.len() - 1].to_string();
+        }
+    }
+    value.to_string()
+}
+
+fn parse_yaml_frontmatter(lines: &[&str]) -> Result<BTreeMap<String, FrontmatterValue>> {
+    let mut values = BTreeMap::new();
+    // The key of a `key:` line with nothing after it, collecting `- item` lines.
+    let mut open_list: Option<String> = None;
+
+    for (index, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        if let Some(item) = trimmed.strip_prefix("- ") {
+            let Some(key) = &open_list else {
+                bail!(
+                    "frontmatter line {}: list item without a key above it: {}",
+                    index + 2,
+                    trimmed
+                );
+            };
+            if let Some(FrontmatterValue::List(items)) = values.get_mut(key) {
+                items.push(unquote(item));
+            }
+            continue;
+        }
+
+        let Some((key, value)) = trimmed.split_once(':') else {
+            bail!(
+                "frontmatter line {}: expected `key: value`, got: {}",
+                index + 2,
+                trimmed
+            );
+        };
+        let key = key.trim().to_string();
+        let value = value.trim();
+
+        if value.is_empty() {
+            values.insert(key.clone(), FrontmatterValue::List(Vec::new()));
+            open_list = Some(key);
+        } else if let Some(inner) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            let items = inner
+                .split(',')
+                .map(unquote)
+                .filter(|item| !item.is_empty())
+                .collect();
+            values.insert(key, FrontmatterValue::List(items));
+            open_list = None;
+        } else {
+            values.insert(key, FrontmatterValue::Scalar(unquote(value)));
+            open_list = None;
+        }
+    }
+
+    Ok(values)
+}
+
+fn parse_toml_frontmatter(text: &str) -> Result<BTreeMap<String, FrontmatterValue>> {
+    let table: toml::Table = toml::from_str(text).context("the TOML frontmatter is not valid")?;
+    let mut values = BTreeMap::new();
+    for (key, value) in table {
+        let value = match value {
+            toml::Value::String(s) => FrontmatterValue::Scalar(s),
+            toml::Value::Integer(i) => FrontmatterValue::Scalar(i.to_string()),
+            toml::Value::Datetime(dt) => FrontmatterValue::Scalar(dt.to_string()),
+            toml::Value::Array(items) => FrontmatterValue::List(
+                items
+                    .into_iter()
+                    .map(|item| match item {
+                        toml::Value::String(s) => Ok(s),
+                        other => Err(anyhow!(
+                            "`{}` must be a list of strings, found {}",
+                            key,
+                            other
+                        )),
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+            other => bail!("`{}` has an unsupported value: {}", key, other),
+        };
+        values.insert(key, value);
+    }
+    Ok(values)
+}
+
+fn frontmatter_from_values(values: BTreeMap<String, FrontmatterValue>) -> Result<Frontmatter> {
+    let mut frontmatter = Frontmatter::default();
+    for (key, value) in values {
+        if !FRONTMATTER_KEYS.contains(&key.as_str()) {
+            bail!(
+                "unknown frontmatter key `{}`; expected one of: {}",
+                key,
+                FRONTMATTER_KEYS.join(", ")
+            );
+        }
+        match (key.as_str(), value) {
+            ("tags", FrontmatterValue::List(items)) => frontmatter.tags = items,
+            // `tags: rust` is a one-element list, not an error.
+            ("tags", FrontmatterValue::Scalar(item)) => frontmatter.tags = vec![item],
+            (_, FrontmatterValue::List(_)) => bail!("`{}` must be a single value, not a list", key),
+            ("title", FrontmatterValue::Scalar(v)) => frontmatter.title = Some(v),
+            ("summary", FrontmatterValue::Scalar(v)) => frontmatter.summary = Some(v),
+            ("image", FrontmatterValue::Scalar(v)) => frontmatter.image = Some(v),
+            ("slug", FrontmatterValue::Scalar(v)) => frontmatter.slug = Some(v),
+            ("published_at", FrontmatterValue::Scalar(v)) => {
+                frontmatter.published_at = Some(parse_published_at(&v)?)
+            }
+            _ => unreachable!("every key in FRONTMATTER_KEYS is handled above"),
+        }
+    }
+    Ok(frontmatter)
+}
+
+/// `published_at` as Unix seconds, RFC 3339 or a bare `YYYY-MM-DD` (midnight UTC).
+pub fn parse_published_at(value: &str) -> Result<u64> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Ok(secs);
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.timestamp().max(0) as u64);
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let midnight = date
+            .and_hms_opt(0, 0, 0)
+            .expect("midnight is always a valid time");
+        return Ok(midnight.and_utc().timestamp().max(0) as u64);
+    }
+    bail!(
+        "published_at must be Unix seconds, RFC 3339 or YYYY-MM-DD, got: {}",
+        value
+    )
+}
+
+/// The `d` tag: the frontmatter `slug`, else the file name without extension.
+pub fn article_identifier(frontmatter: &Frontmatter, path: &Path) -> Result<String> {
+    if let Some(slug) = frontmatter.slug.as_deref().filter(|s| !s.trim().is_empty()) {
+        return Ok(slug.trim().to_string());
+    }
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .filter(|stem| !stem.is_empty())
+        .ok_or_else(|| {
+            anyhow!(
+                "cannot derive a slug from {}; set `slug` in the frontmatter",
+                path.display()
+            )
+        })
+}
+
+/// The NIP-23 tags for an article.
+///
+/// `published_at` is passed separately rather than read from the frontmatter:
+/// on a republish without one it is carried over from the version already on
+/// the relays, so an edit does not move the article's original date.
+pub fn article_tags(
+    frontmatter: &Frontmatter,
+    identifier: &str,
+    published_at: u64,
+) -> Result<Vec<Tag>> {
+    let mut tags = vec![Tag::identifier(identifier)];
+    if let Some(title) = &frontmatter.title {
+        tags.push(Tag::parse(["title", title.as_str()])?);
+    }
+    if let Some(summary) = &frontmatter.summary {
+        tags.push(Tag::parse(["summary", summary.as_str()])?);
+    }
+    if let Some(image) = &frontmatter.image {
+        tags.push(Tag::parse(["image", image.as_str()])?);
+    }
+    tags.push(Tag::parse([
+        "published_at".to_string(),
+        published_at.to_string(),
+    ])?);
+    for hashtag in &frontmatter.tags {
+        tags.push(Tag::hashtag(hashtag.trim_start_matches('#')));
+    }
+    Ok(tags)
+}
+
+/// The value of the first tag named `name`, e.g. an article's `title`.
+pub fn tag_value<'a>(event: &'a Event, name: &str) -> Option<&'a str> {
+    event.tags.iter().find_map(|tag| {
+        let values = tag.as_slice();
+        if values.first().map(String::as_str) == Some(name) {
+            values.get(1).map(String::as_str)
+        } else {
+            None
+        }
+    })
+}
+
+/// `naddr` for an addressable event, carrying up to two of our relays as hints.
+pub fn naddr(kind: Kind, author: PublicKey, identifier: &str, relays: &[String]) -> Result<String> {
+    let coordinate = Coordinate {
+        kind,
+        public_key: author,
+        identifier: identifier.to_string(),
+    };
+    let hints: Vec<String> = relays.iter().take(2).cloned().collect();
+    Ok(Nip19Coordinate::new(coordinate, hints)?.to_bech32()?)
+}
+
+fn naddr_for(event: &Event, relays: &[String]) -> Result<String> {
+    naddr(
+        event.kind,
+        event.pubkey,
+        tag_value(event, "d").unwrap_or_default(),
+        relays,
+    )
+}
+
+fn format_timestamp(secs: u64) -> String {
+    DateTime::<Utc>::from_timestamp(secs as i64, 0)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+pub async fn publish(file: &Path, draft: bool) -> Result<()> {
+    // Parse before touching the network, like `event --file`: a broken file
+    // fails without connecting anywhere.
+    let text = std::fs::read_to_string(file)
+        .with_context(|| format!("failed to read article {}", file.display()))?;
+    let source =
+        parse_article(&text).with_context(|| format!("invalid article {}", file.display()))?;
+    if source.body.is_empty() {
+        bail!(
+            "{} has no article body after the frontmatter",
+            file.display()
+        );
+    }
+    let identifier = article_identifier(&source.frontmatter, file)?;
+    let kind = if draft { DRAFT_KIND } else { ARTICLE_KIND };
+
+    let config = NostaroConfig::load()?;
+    let keys = keys::keys_from_config(&config)?;
+    let nostr_client = client::create_client(&keys, &config).await?;
+
+    let existing = client::fetch_addressable_event(
+        &nostr_client,
+        Kind::from(kind),
+        &keys.public_key(),
+        &identifier,
+    )
+    .await?;
+    let published_at = source
+        .frontmatter
+        .published_at
+        .or_else(|| {
+            existing
+                .as_ref()
+                .and_then(|event| tag_value(event, "published_at"))
+                .and_then(|v| v.parse().ok())
+        })
+        .unwrap_or_else(|| Timestamp::now().as_u64());
+
+    let tags = article_tags(&source.frontmatter, &identifier, published_at)?;
+    match existing {
+        Some(_) => println!("Updating kind:{} \"{}\"...", kind, identifier),
+        None => println!("Publishing kind:{} \"{}\"...", kind, identifier),
+    }
+    let builder = EventBuilder::new(Kind::from(kind), source.body).tags(tags);
+    let output = client::publish(&nostr_client, builder).await?;
+
+    println!("Event ID: {}", output.id().to_hex());
+    println!(
+        "Naddr:    {}",
+        naddr(
+            Kind::from(kind),
+            keys.public_key(),
+            &identifier,
+            &config.active_relays()
+        )?
+    );
+
+    nostr_client.disconnect().await;
+    Ok(())
+}
+
+pub async fn list(author: Option<&str>, limit: usize, drafts: bool) -> Result<()> {
+    let config = NostaroConfig::load()?;
+    let keys = keys::keys_from_config(&config)?;
+    let nostr_client = client::create_client(&keys, &config).await?;
+
+    let author = match author {
+        Some(a) => {
+            let (pubkey, hints) = resolve_pubkey_with_hints(a).await?;
+            client::add_relay_hints(&nostr_client, &hints).await;
+            pubkey
+        }
+        None => keys.public_key(),
+    };
+    let kind = Kind::from(if drafts { DRAFT_KIND } else { ARTICLE_KIND });
+
+    let articles = client::fetch_addressable_events(&nostr_client, kind, &author, limit).await?;
+    if articles.is_empty() {
+        println!("No articles found.");
+        nostr_client.disconnect().await;
+        return Ok(());
+    }
+
+    let relays = config.active_relays();
+    for article in &articles {
+        let title = tag_value(article, "title").unwrap_or("(untitled)");
+        let published = tag_value(article, "published_at")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or_else(|| article.created_at.as_u64());
+        println!("{}", title);
+        println!(
+            "  slug:      {}",
+            tag_value(article, "d").unwrap_or_default()
+        );
+        println!("  published: {}", format_timestamp(published));
+        if published != article.created_at.as_u64() {
+            println!(
+                "  updated:   {}",
+                format_timestamp(article.created_at.as_u64())
+            );
+        }
+        println!("  naddr:     {}", naddr_for(article, &relays)?);
+        println!("{}", "-".repeat(60));
+    }
+    println!("\n{} article(s).", articles.len());
+
+    nostr_client.disconnect().await;
+    Ok(())
+}
+
+pub async fn get(naddr: &str) -> Result<()> {
+    let naddr = naddr.strip_prefix("nostr:").unwrap_or(naddr);
+    let nip19 =
+        Nip19Coordinate::from_bech32(naddr).with_context(|| format!("not an naddr: {}", naddr))?;
+    let coordinate = &nip19.coordinate;
+
+    let config = NostaroConfig::load()?;
+    let keys = keys::keys_from_config(&config)?;
+    let nostr_client = client::create_client(&keys, &config).await?;
+
+    for relay in &nip19.relays {
+        let _ = nostr_client.add_relay(relay).await;
+    }
+    if !nip19.relays.is_empty() {
+        nostr_client.connect().await;
+    }
+
+    let article = client::fetch_addressable_event(
+        &nostr_client,
+        coordinate.kind,
+        &coordinate.public_key,
+        &coordinate.identifier,
+    )
+    .await?
+    .ok_or_else(|| anyhow!("Article not found: {}", naddr))?;
+
+    let published = tag_value(&article, "published_at")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| article.created_at.as_u64());
+
+    if let Some(title) = tag_value(&article, "title") {
+        println!("{}", title);
+        println!("{}", "=".repeat(title.chars().count().max(3)));
+    }
+    println!("Author:    {}", article.pubkey.to_bech32()?);
+    println!("Published: {}", format_timestamp(published));
+    if let Some(summary) = tag_value(&article, "summary") {
+        println!("Summary:   {}", summary);
+    }
+    let hashtags: Vec<String> = article
+        .tags
+        .iter()
+        .filter_map(|tag| match tag.as_slice() {
+            [name, value, ..] if name == "t" => Some(format!("#{}", value)),
+            _ => None,
+        })
+        .collect();
+    if !hashtags.is_empty() {
+        println!("Tags:      {}", hashtags.join(" "));
+    }
+    println!();
+    println!("{}", render_markdown(&article.content));
+
+    nostr_client.disconnect().await;
+    Ok(())
+}
+
+/// Render Markdown as plain terminal text.
+///
+/// Not a Markdown implementation: it handles what articles actually use —
+/// headings, emphasis, links, images, lists, quotes and fenced code — and
+/// passes everything else through unchanged. Headings are underlined, links
+/// keep their URL next to the text (a terminal cannot click a hidden href), and
+/// code is indented and left verbatim.
+pub fn render_markdown(markdown: &str) -> String {
+    let mut out = Vec::new();
+    let mut in_code = false;
+
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            out.push(format!("    {}", line));
+            continue;
+        }
+
+        let level = trimmed.chars().take_while(|&c| c == '#').count();
+        if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+            let text = render_inline(trimmed[level..].trim());
+            let underline = if level == 1 { '=' } else { '-' };
+            let width = text.chars().count().max(3);
+            out.push(text);
+            out.push(underline.to_string().repeat(width));
+            continue;
+        }
+
+        if matches!(trimmed, "---" | "***" | "___") {
+            out.push("-".repeat(40));
+            continue;
+        }
+        if let Some(quote) = trimmed.strip_prefix('>') {
+            out.push(format!("  │ {}", render_inline(quote.trim_start())));
+            continue;
+        }
+        if let Some(item) = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+            .or_else(|| trimmed.strip_prefix("+ "))
+        {
+            let indent = " ".repeat(line.len() - trimmed.len());
+            out.push(format!("{}  • {}", indent, render_inline(item)));
+            continue;
+        }
+
+        out.push(render_inline(line));
+    }
+
+    out.join("\n")
+}
+
+/// Inline Markdown: images and links keep their URL, emphasis and code spans
+/// lose their markers.
+fn render_inline(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let is_image = rest.starts_with("![");
+        if is_image || rest.starts_with('[') {
+            let label_start = if is_image { 2 } else { 1 };
+            if let Some((label, url, consumed)) = split_link(&rest[label_start..]) {
+                if is_image {
+                    out.push_str(&format!("[image: {}] <{}>", label, url));
+                } else if label == url {
+                    out.push_str(&format!("<{}>", url));
+                } else {
+                    out.push_str(&format!("{} <{}>", render_inline(label), url));
+                }
+                rest = &rest[label_start + consumed..];
+                continue;
+            }
+        }
+
+        // `_` only opens emphasis at the start of a word, so snake_case survives.
+        let at_word_start = out.chars().last().is_none_or(|c| !c.is_alphanumeric());
+        let marker = ["**", "__", "~~", "`", "*", "_"]
+            .into_iter()
+            .filter(|m| at_word_start || !m.starts_with('_'))
+            .find(|m| rest.starts_with(m));
+        if let Some(marker) = marker {
+            let after = &rest[marker.len()..];
+            if let Some(end) = after
+                .find(marker)
+                .filter(|&end| end > 0 && !after.starts_with(' '))
+            {
+                let inner = &after[..end];
+                if marker == "`" {
+                    out.push_str(inner);
+                } else {
+                    out.push_str(&render_inline(inner));
+                }
+                rest = &after[end + marker.len()..];
+                continue;
+            }
+        }
+
+        let ch = rest.chars().next().expect("rest is not empty");
+        out.push(ch);
+        rest = &rest[ch.len_utf8()..];
+    }
+
+    out
+}
+
+/// `label](url)` → (label, url, bytes consumed). `None` when it is not a link.
+fn split_link(text: &str) -> Option<(&str, &str, usize)> {
+    let label_end = text.find("](")?;
+    let label = &text[..label_end];
+    if label.contains('\n') {
+        return None;
+    }
+    let url_start = label_end + 2;
+    let url_len = text[url_start..].find(')')?;
+    let url = text[url_start..url_start + url_len].trim();
+    Some((label, url, url_start + url_len + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yaml_frontmatter_is_split_from_the_body() {
+        let source = parse_article(
+            "---\n\
+             title: \"Shipping nostaro 0.4\"\n\
+             summary: What changed\n\
+             image: https://example.com/cover.png\n\
+             tags: [release, rust]\n\
+             published_at: 2024-05-01\n\
+             slug: nostaro-0-4\n\
+             ---\n\
+             \n\
+             # Hello\n\
+             Body text.\n",
+        )
+        .unwrap();
+
+        let fm = &source.frontmatter;
+        assert_eq!(fm.title.as_deref(), Some("Shipping nostaro 0.4"));
+        assert_eq!(fm.summary.as_deref(), Some("What changed"));
+        assert_eq!(fm.image.as_deref(), Some("https://example.com/cover.png"));
+        assert_eq!(fm.tags, vec!["release", "rust"]);
+        assert_eq!(fm.published_at, Some(1714521600));
+        assert_eq!(fm.slug.as_deref(), Some("nostaro-0-4"));
+        assert_eq!(source.body, "# Hello\nBody text.");
+    }
+
+    #[test]
+    fn yaml_block_lists_are_understood() {
+        let source = parse_article("---\ntags:\n  - nostr\n  - 'long form'\n---\nbody").unwrap();
+        assert_eq!(source.frontmatter.tags, vec!["nostr", "long form"]);
+    }
+
+    #[test]
+    fn toml_frontmatter_is_split_from_the_body() {
+        let source = parse_article(
+            "+++\n\
+             title = \"Team blog\"\n\
+             tags = [\"team\"]\n\
+             published_at = 1700000000\n\
+             +++\n\
+             body",
+        )
+        .unwrap();
+        assert_eq!(source.frontmatter.title.as_deref(), Some("Team blog"));
+        assert_eq!(source.frontmatter.tags, vec!["team"]);
+        assert_eq!(source.frontmatter.published_at, Some(1700000000));
+        assert_eq!(source.body, "body");
+    }
+
+    #[test]
+    fn a_file_without_frontmatter_is_all_body() {
+        let source = parse_article("# Just markdown\n\ntext").unwrap();
+        assert_eq!(source.frontmatter, Frontmatter::default());
+        assert_eq!(source.body, "# Just markdown\n\ntext");
+    }
+
+    #[test]
+    fn unknown_frontmatter_keys_are_rejected() {
+        let err = parse_article("---\ntitel: typo\n---\nbody")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("titel"), "{}", err);
+    }
+
+    #[test]
+    fn an_unclosed_frontmatter_is_rejected() {
+        assert!(parse_article("---\ntitle: x\nbody").is_err());
+    }
+
+    #[test]
+    fn published_at_accepts_three_forms() {
+        assert_eq!(parse_published_at("1700000000").unwrap(), 1700000000);
+        assert_eq!(
+            parse_published_at("2024-05-01T00:00:00Z").unwrap(),
+            1714521600
+        );
+        assert_eq!(parse_published_at("2024-05-01").unwrap(), 1714521600);
+        assert!(parse_published_at("yesterday").is_err());
+    }
+
+    #[test]
+    fn the_slug_falls_back_to_the_file_name() {
+        let fm = Frontmatter::default();
+        assert_eq!(
+            article_identifier(&fm, Path::new("posts/hello-world.md")).unwrap(),
+            "hello-world"
+        );
+        let fm = Frontmatter {
+            slug: Some("explicit".to_string()),
+            ..Frontmatter::default()
+        };
+        assert_eq!(
+            article_identifier(&fm, Path::new("posts/hello-world.md")).unwrap(),
+            "explicit"
+        );
+    }
+
+    #[test]
+    fn article_tags_carry_d_title_and_hashtags() {
+        let fm = Frontmatter {
+            title: Some("T".to_string()),
+            tags: vec!["#rust".to_string(), "nostr".to_string()],
+            ..Frontmatter::default()
+        };
+        let tags: Vec<Vec<String>> = article_tags(&fm, "slug", 42)
+            .unwrap()
+            .into_iter()
+            .map(|tag| tag.to_vec())
+            .collect();
+        assert_eq!(
+            tags,
+            vec![
+                vec!["d", "slug"],
+                vec!["title", "T"],
+                vec!["published_at", "42"],
+                vec!["t", "rust"],
+                vec!["t", "nostr"],
+            ]
+        );
+    }
+
+    #[test]
+    fn markdown_renders_as_plain_terminal_text() {
+        let rendered = render_markdown(
+            "# Title\n\
+             Some **bold** and `code` with a [link](https://example.com).\n\
+             - item\n\
+             > quoted\n\
+             ```\n\
+             let x = *1*;\n\
+             ```\n\
+             ![cover](https://example.com/c.png)",
+        );
+        assert_eq!(
+            rendered,
+            "Title\n\
+             =====\n\
+             Some bold and code with a link <https://example.com>.\n\
+             \x20 • item\n\
+             \x20 │ quoted\n\
+             \x20   let x = *1*;\n\
+             [image: cover] <https://example.com/c.png>"
+        );
+    }
+}
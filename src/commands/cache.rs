@@ -17,3 +17,10 @@ pub async fn stats() -> Result<()> {
     println!("  Profiles: {}", profiles);
     Ok(())
 }
+
+pub async fn reindex() -> Result<()> {
+    let db = CacheDb::open()?;
+    let events = db.reindex()?;
+    println!("Search index rebuilt ({} events).", events);
+    Ok(())
+}
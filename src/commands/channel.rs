@@ -22,7 +22,7 @@ pub async fn create(name: &str, about: Option<&str>, picture: Option<&str>) -> R
 
     println!("Creating channel...");
     let event_id = client::create_channel(&nostr_client, &content).await?;
-    println!("Channel created! ID: {}", event_id.to_hex());
+    client::report_published(&format!("Channel created! ID: {}", event_id.to_hex()));
 
     nostr_client.disconnect().await;
     Ok(())
@@ -53,7 +53,7 @@ pub async fn edit(
 
     println!("Updating channel metadata...");
     client::edit_channel(&nostr_client, &channel_id, &content, &relay_url).await?;
-    println!("Channel metadata updated!");
+    client::report_published("Channel metadata updated!");
 
     nostr_client.disconnect().await;
     Ok(())
@@ -137,7 +137,7 @@ pub async fn post(channel_id_str: &str, message: &str) -> Result<()> {
 
     println!("Posting to channel...");
     client::post_channel_message(&nostr_client, &channel_id, message).await?;
-    println!("Message posted successfully!");
+    client::report_published("Message posted successfully!");
 
     nostr_client.disconnect().await;
     Ok(())
@@ -1,8 +1,190 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use nostr_sdk::prelude::*;
 
+use crate::output;
+
+/// The bech32 alphabet (BIP-173).
+const BECH32_CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// The entity without a NIP-21 `nostr:` prefix (in any case).
+fn strip_uri(entity: &str) -> &str {
+    let entity = entity.trim();
+    match entity.get(..6) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("nostr:") => &entity[6..],
+        _ => entity,
+    }
+}
+
+fn relay_list(relays: &[RelayUrl]) -> Vec<String> {
+    relays.iter().map(|r| r.to_string()).collect()
+}
+
+fn bech32_polymod(values: impl Iterator<Item = u8>) -> u32 {
+    const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+    let mut checksum: u32 = 1;
+    for value in values {
+        let top = checksum >> 25;
+        checksum = ((checksum & 0x1ffffff) << 5) ^ u32::from(value);
+        for (i, generator) in GENERATOR.iter().enumerate() {
+            if (top >> i) & 1 == 1 {
+                checksum ^= generator;
+            }
+        }
+    }
+    checksum
+}
+
+/// The human-readable part and payload of a lowercase bech32 string.
+///
+/// Only `lnurl` needs this: it is not a NIP-19 entity, so nostr's decoder does
+/// not know it, and an encoded URL is longer than BIP-173's 90 characters.
+fn bech32_payload(entity: &str) -> Result<(&str, Vec<u8>)> {
+    let (hrp, data) = entity
+        .rsplit_once('1')
+        .ok_or_else(|| anyhow!("no separator"))?;
+    if hrp.is_empty() || data.len() < 6 {
+        bail!("too short");
+    }
+    let values = data
+        .bytes()
+        .map(|c| {
+            BECH32_CHARSET
+                .iter()
+                .position(|&x| x == c)
+                .map(|i| i as u8)
+                .ok_or_else(|| anyhow!("invalid character '{}'", c as char))
+        })
+        .collect::<Result<Vec<u8>>>()?;
+    let expanded_hrp = hrp
+        .bytes()
+        .map(|b| b >> 5)
+        .chain([0])
+        .chain(hrp.bytes().map(|b| b & 31));
+    if bech32_polymod(expanded_hrp.chain(values.iter().copied())) != 1 {
+        bail!("invalid checksum");
+    }
+
+    // Regroup the 5-bit values, minus the 6-value checksum, into bytes.
+    let mut bytes = Vec::new();
+    let (mut acc, mut bits) = (0u32, 0u32);
+    for value in &values[..values.len() - 6] {
+        acc = ((acc << 5) | u32::from(*value)) & 0xfff;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((acc >> bits) as u8);
+        }
+    }
+    if bits >= 5 || acc & ((1 << bits) - 1) != 0 {
+        bail!("invalid padding");
+    }
+    Ok((hrp, bytes))
+}
+
+fn key_security(key: &EncryptedSecretKey) -> &'static str {
+    match key.key_security() {
+        KeySecurity::Weak => "known to have been handled insecurely",
+        KeySecurity::Medium => "not known to have been handled insecurely",
+        KeySecurity::Unknown => "unknown",
+    }
+}
+
+/// The URL inside a bech32 `lnurl` (LUD-01).
+fn decode_lnurl(entity: &str) -> Result<serde_json::Value> {
+    let (hrp, data) = bech32_payload(entity).map_err(|e| anyhow!("invalid lnurl: {}", e))?;
+    if hrp != "lnurl" {
+        bail!("invalid lnurl: prefix {}", hrp);
+    }
+    let url = String::from_utf8(data).map_err(|_| anyhow!("invalid lnurl: not a UTF-8 URL"))?;
+    Ok(serde_json::json!({ "type": "lnurl", "url": url }))
+}
+
+/// Decode any supported entity into the `decode --out-format json` document.
+/// Every document has a `type`; the other fields depend on it.
+///
+/// Pure — it only parses the string.
+pub fn decode(entity: &str) -> Result<serde_json::Value> {
+    let entity = strip_uri(entity);
+    let lower = entity.to_ascii_lowercase();
+    if lower.starts_with("lnurl1") {
+        return decode_lnurl(&lower);
+    }
+
+    Ok(match Nip19::from_bech32(entity)? {
+        Nip19::Pubkey(pubkey) => serde_json::json!({
+            "type": "npub",
+            "hex": pubkey.to_hex(),
+            "npub": pubkey.to_bech32()?,
+        }),
+        Nip19::Secret(secret) => serde_json::json!({
+            "type": "nsec",
+            "hex": secret.to_secret_hex(),
+        }),
+        // What an ncryptsec says about itself without the password (NIP-49).
+        // The key itself stays encrypted; nothing here decrypts it.
+        Nip19::EncryptedSecret(key) => serde_json::json!({
+            "type": "ncryptsec",
+            "version": key.version() as u8,
+            "log_n": key.log_n(),
+            "key_security": key_security(&key),
+        }),
+        Nip19::EventId(event_id) => serde_json::json!({
+            "type": "note",
+            "hex": event_id.to_hex(),
+            "note": event_id.to_bech32()?,
+        }),
+        Nip19::Profile(profile) => serde_json::json!({
+            "type": "nprofile",
+            "pubkey": profile.public_key.to_hex(),
+            "npub": profile.public_key.to_bech32()?,
+            "relays": relay_list(&profile.relays),
+        }),
+        Nip19::Event(event) => serde_json::json!({
+            "type": "nevent",
+            "event_id": event.event_id.to_hex(),
+            "author": event.author.map(|author| author.to_hex()),
+            "kind": event.kind.map(|kind| kind.as_u16()),
+            "relays": relay_list(&event.relays),
+        }),
+        Nip19::Coordinate(coord) => serde_json::json!({
+            "type": "naddr",
+            "kind": coord.coordinate.kind.as_u16(),
+            "pubkey": coord.coordinate.public_key.to_hex(),
+            "identifier": coord.coordinate.identifier,
+            "relays": relay_list(&coord.relays),
+        }),
+    })
+}
+
+fn print_relays(label_width: usize, relays: &[RelayUrl]) {
+    if relays.is_empty() {
+        println!("{:width$}(none)", "Relays:", width = label_width);
+    } else {
+        println!("Relays:");
+        for relay in relays {
+            println!("  - {}", relay);
+        }
+    }
+}
+
 pub fn run(entity: &str) -> Result<()> {
-    let nip19 = Nip19::from_bech32(entity)?;
+    if output::is_json() {
+        let decoded = decode(entity)?;
+        output::write_json(&decoded)?;
+        println!("Decoded {}", decoded["type"].as_str().unwrap_or_default());
+        return Ok(());
+    }
+
+    let bare = strip_uri(entity);
+    let lower = bare.to_ascii_lowercase();
+    if lower.starts_with("lnurl1") {
+        let decoded = decode_lnurl(&lower)?;
+        println!("Type:   lnurl");
+        println!("URL:    {}", decoded["url"].as_str().unwrap_or_default());
+        return Ok(());
+    }
+
+    let nip19 = Nip19::from_bech32(bare)?;
 
     match nip19 {
         Nip19::Pubkey(pubkey) => {
@@ -15,6 +197,12 @@ pub fn run(entity: &str) -> Result<()> {
             println!("Hex:    {}", secret.to_secret_hex());
             println!("Nsec:   exists (hidden for safety)");
         }
+        Nip19::EncryptedSecret(key) => {
+            println!("Type:         ncryptsec (password-encrypted secret key, NIP-49)");
+            println!("Version:      {}", key.version() as u8);
+            println!("log_n:        {}", key.log_n());
+            println!("Key security: {}", key_security(&key));
+        }
         Nip19::EventId(event_id) => {
             println!("Type:   note (event ID)");
             println!("Hex:    {}", event_id.to_hex());
@@ -24,14 +212,7 @@ pub fn run(entity: &str) -> Result<()> {
             println!("Type:   nprofile (profile)");
             println!("Pubkey: {}", profile.public_key.to_hex());
             println!("Npub:   {}", profile.public_key.to_bech32()?);
-            if profile.relays.is_empty() {
-                println!("Relays: (none)");
-            } else {
-                println!("Relays:");
-                for relay in &profile.relays {
-                    println!("  - {}", relay);
-                }
-            }
+            print_relays(8, &profile.relays);
         }
         Nip19::Event(event) => {
             println!("Type:     nevent (event)");
@@ -42,30 +223,80 @@ pub fn run(entity: &str) -> Result<()> {
             if let Some(kind) = event.kind {
                 println!("Kind:     {}", kind.as_u16());
             }
-            if event.relays.is_empty() {
-                println!("Relays:   (none)");
-            } else {
-                println!("Relays:");
-                for relay in &event.relays {
-                    println!("  - {}", relay);
-                }
-            }
+            print_relays(10, &event.relays);
         }
         Nip19::Coordinate(coord) => {
             println!("Type:       naddr (coordinate)");
             println!("Kind:       {}", coord.coordinate.kind.as_u16());
             println!("Pubkey:     {}", coord.coordinate.public_key.to_hex());
             println!("Identifier: {}", coord.coordinate.identifier);
-            if coord.relays.is_empty() {
-                println!("Relays:     (none)");
-            } else {
-                println!("Relays:");
-                for relay in &coord.relays {
-                    println!("  - {}", relay);
-                }
-            }
+            print_relays(12, &coord.relays);
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nostr_uris_decode_like_the_bare_entity() {
+        let keys = Keys::generate();
+        let npub = keys.public_key().to_bech32().unwrap();
+        let bare = decode(&npub).unwrap();
+        assert_eq!(bare["type"], "npub");
+        assert_eq!(bare["hex"], keys.public_key().to_hex());
+        assert_eq!(decode(&format!("nostr:{}", npub)).unwrap(), bare);
+        assert_eq!(decode(&format!("NOSTR:{}", npub)).unwrap(), bare);
+    }
+
+    #[test]
+    fn nevents_carry_their_optional_fields_as_null() {
+        let id = EventId::all_zeros();
+        let nevent = Nip19Event::new(id)
+            .relays([RelayUrl::parse("wss://relay.example.com").unwrap()])
+            .to_bech32()
+            .unwrap();
+        let decoded = decode(&nevent).unwrap();
+        assert_eq!(decoded["type"], "nevent");
+        assert_eq!(decoded["event_id"], id.to_hex());
+        assert!(decoded["author"].is_null());
+        assert!(decoded["kind"].is_null());
+        assert_eq!(decoded["relays"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn lnurls_decode_to_their_url() {
+        // The LUD-01 example.
+        let lnurl = "LNURL1DP68GURN8GHJ7UM9WFMXJCM99E3K7MF0V9CXJ0M385EKVCENXC6R2C35XVUKXEFCV5MKVV34X5EKZD3EV56NYD3HXQURZEPEXEJXXEPNXSCRVWFNV9NXZCN9XQ6XYEFHVGCXXCMYXYMNSERXFQ5FNS";
+        let url = "https://service.com/api?q=3fc3645b439ce8e7f2553a69e5267081d96dcd340693afabe04be7b0ccd178df";
+        let decoded = decode(lnurl).unwrap();
+        assert_eq!(decoded, serde_json::json!({"type": "lnurl", "url": url}));
+
+        let mut corrupted = lnurl.to_string();
+        corrupted.replace_range(20..21, "Q");
+        assert!(decode(&corrupted).is_err());
+    }
+
+    #[test]
+    fn ncryptsec_metadata_is_read_without_the_password() {
+        let mut payload = vec![0u8; EncryptedSecretKey::LEN];
+        payload[0] = 0x02;
+        payload[1] = 16;
+        payload[42] = 0x01;
+        let ncryptsec = EncryptedSecretKey::from_slice(&payload)
+            .unwrap()
+            .to_bech32()
+            .unwrap();
+        let decoded = decode(&ncryptsec).unwrap();
+        assert_eq!(decoded["type"], "ncryptsec");
+        assert_eq!(decoded["version"], 2);
+        assert_eq!(decoded["log_n"], 16);
+        assert_eq!(
+            decoded["key_security"],
+            "not known to have been handled insecurely"
+        );
+    }
+}
@@ -5,14 +5,16 @@ use nostr_sdk::prelude::*;
 use crate::client;
 use crate::config::NostaroConfig;
 use crate::keys;
-use crate::utils::resolve_pubkey;
+use crate::utils::{resolve_pubkey_online, resolve_pubkey_with_hints};
 
 pub async fn send(npub_str: &str, message: &str, use_nip04: bool) -> Result<()> {
     let config = NostaroConfig::load()?;
     let keys = keys::keys_from_config(&config)?;
     let nostr_client = client::create_client(&keys, &config).await?;
 
-    let receiver = resolve_pubkey(npub_str)?;
+    // The receiver's relay hints are where they are likely to look.
+    let (receiver, hints) = resolve_pubkey_with_hints(npub_str).await?;
+    client::add_relay_hints(&nostr_client, &hints).await;
 
     if use_nip04 {
         println!("Sending DM (NIP-04)...");
@@ -23,7 +25,7 @@ pub async fn send(npub_str: &str, message: &str, use_nip04: bool) -> Result<()>
     }
 
     let npub = receiver.to_bech32()?;
-    println!("DM sent to {}!", npub);
+    client::report_published(&format!("DM sent to {}!", npub));
 
     nostr_client.disconnect().await;
     Ok(())
@@ -35,7 +37,7 @@ pub async fn read(npub_filter: Option<&str>) -> Result<()> {
     let nostr_client = client::create_client(&keys, &config).await?;
 
     let filter_pubkey = match npub_filter {
-        Some(pk) => Some(resolve_pubkey(pk)?),
+        Some(pk) => Some(resolve_pubkey_online(pk).await?),
         None => None,
     };
 
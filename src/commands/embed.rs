@@ -0,0 +1,408 @@
+//! What a note points at: the original of a repost (NIP-18) and the inline
+//! `nostr:` references in its text (NIP-21/NIP-27).
+//!
+//! A page of notes is scanned first, and everything it points at that is not
+//! already embedded is fetched in one `ids` read; the authors of what came back
+//! are then named from the cache, topped up with one batched kind:0 read. The
+//! rendering itself is pure, so text and JSON describe the same previews.
+
+use anyhow::Result;
+use nostr_sdk::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use crate::cache::CacheDb;
+use crate::client;
+use crate::post_options;
+
+use super::{thread, timeline};
+
+/// Characters of a referenced note's first line shown in its preview.
+const PREVIEW_CHARS: usize = 60;
+
+/// Whether `event` is a repost: kind:6 of a text note, kind:16 of anything.
+pub(crate) fn is_repost(event: &Event) -> bool {
+    event.kind == Kind::Repost || event.kind.as_u16() == client::GENERIC_REPOST_KIND
+}
+
+/// The id of the event a repost points at: its first `e` tag.
+fn repost_target(event: &Event) -> Option<EventId> {
+    event.tags.iter().find_map(|tag| match tag.as_slice() {
+        [name, value, ..] if name == "e" => EventId::from_hex(value).ok(),
+        _ => None,
+    })
+}
+
+/// The original a repost carries in its content, if it carries one that is
+/// signed and is the event the `e` tag names. Anything else is ignored and the
+/// original is fetched instead: the content of a repost is only a hint.
+fn embedded_original(repost: &Event) -> Option<Event> {
+    let original = Event::from_json(&repost.content).ok()?;
+    if original.verify().is_err() {
+        return None;
+    }
+    match repost_target(repost) {
+        Some(target) if target != original.id => None,
+        _ => Some(original),
+    }
+}
+
+/// A `nostr:` URI in a note's text and what it names.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Reference {
+    /// The URI as written, `nostr:` included.
+    pub uri: String,
+    pub target: Nip19,
+}
+
+/// Every `nostr:` reference in `content`, in order. Secret keys are never
+/// treated as a reference, and anything that does not decode is left alone.
+///
+/// Pure — it only scans the text.
+pub(crate) fn references(content: &str) -> Vec<Reference> {
+    let mut found = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("nostr:") {
+        let after = &rest[start + "nostr:".len()..];
+        let len = after
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(after.len());
+        let entity = &after[..len];
+        match Nip19::from_bech32(entity) {
+            Ok(Nip19::Secret(_)) | Err(_) => {}
+            Ok(target) => found.push(Reference {
+                uri: format!("nostr:{}", entity),
+                target,
+            }),
+        }
+        rest = &after[len..];
+    }
+    found
+}
+
+fn referenced_event(target: &Nip19) -> Option<EventId> {
+    match target {
+        Nip19::EventId(id) => Some(*id),
+        Nip19::Event(event) => Some(event.event_id),
+        _ => None,
+    }
+}
+
+fn referenced_pubkey(target: &Nip19) -> Option<PublicKey> {
+    match target {
+        Nip19::Pubkey(pubkey) => Some(*pubkey),
+        Nip19::Profile(profile) => Some(profile.public_key),
+        Nip19::Coordinate(coord) => Some(coord.coordinate.public_key),
+        _ => None,
+    }
+}
+
+/// The first non-empty line of `event`, behind its content warning if it has
+/// one, cut to [`PREVIEW_CHARS`].
+///
+/// Pure — it only formats the event.
+pub(crate) fn first_line(event: &Event) -> String {
+    let content = post_options::display_content(event);
+    let line = content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default();
+    if line.chars().count() > PREVIEW_CHARS {
+        format!("{}…", line.chars().take(PREVIEW_CHARS).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+/// The cached name for `pubkey`, or its npub cut short.
+fn short_name(pubkey: &PublicKey, cache: Option<&CacheDb>) -> String {
+    thread::cached_name(cache, pubkey).unwrap_or_else(|| {
+        let npub = pubkey.to_bech32().unwrap_or_else(|_| pubkey.to_hex());
+        format!("{}…", &npub[..npub.len().min(14)])
+    })
+}
+
+/// The originals of a page's reposts and the events its notes reference.
+#[derive(Debug, Default)]
+pub(crate) struct Embeds {
+    events: HashMap<EventId, Event>,
+}
+
+impl Embeds {
+    /// What `events` point at that they already carry (embedded originals),
+    /// and the ids that still have to be fetched.
+    ///
+    /// Pure — it only looks at the events.
+    pub(crate) fn plan(events: &[Event]) -> (Self, HashSet<EventId>) {
+        let mut embeds = Self::default();
+        let mut wanted = HashSet::new();
+        for event in events {
+            if is_repost(event) {
+                match embedded_original(event) {
+                    Some(original) => {
+                        for reference in references(&original.content) {
+                            wanted.extend(referenced_event(&reference.target));
+                        }
+                        embeds.events.insert(original.id, original);
+                    }
+                    None => wanted.extend(repost_target(event)),
+                }
+            } else {
+                for reference in references(&event.content) {
+                    wanted.extend(referenced_event(&reference.target));
+                }
+            }
+        }
+        wanted.retain(|id| !embeds.events.contains_key(id));
+        (embeds, wanted)
+    }
+
+    /// Resolve what `events` point at: one `ids` read for everything not
+    /// embedded, then names for the authors involved (one kind:0 read for
+    /// those the cache lacks). A failed read leaves references unresolved
+    /// rather than failing the page.
+    pub(crate) async fn fetch(
+        nostr_client: &Client,
+        events: &[Event],
+        cache: Option<&CacheDb>,
+    ) -> Self {
+        let (mut embeds, wanted) = Self::plan(events);
+        if !wanted.is_empty() {
+            let filter = Filter::new().ids(wanted);
+            if let Ok(found) = nostr_client
+                .fetch_events(filter, Duration::from_secs(10))
+                .await
+            {
+                embeds
+                    .events
+                    .extend(found.into_iter().map(|event| (event.id, event)));
+            }
+        }
+
+        if let Some(cache) = cache {
+            let mut authors: HashSet<PublicKey> =
+                embeds.events.values().map(|event| event.pubkey).collect();
+            for event in events.iter().chain(embeds.events.values()) {
+                for reference in references(&event.content) {
+                    authors.extend(referenced_pubkey(&reference.target));
+                }
+            }
+            if !authors.is_empty() {
+                let _ = timeline::fetch_and_cache_profiles(
+                    nostr_client,
+                    authors.into_iter().collect(),
+                    cache,
+                )
+                .await;
+            }
+        }
+        embeds
+    }
+
+    /// The original of `repost`, embedded or fetched.
+    pub(crate) fn original(&self, repost: &Event) -> Option<&Event> {
+        if !is_repost(repost) {
+            return None;
+        }
+        repost_target(repost)
+            .or_else(|| embedded_original(repost).map(|original| original.id))
+            .and_then(|id| self.events.get(&id))
+    }
+
+    /// The `repost` field of a timeline note: `null` for anything but a
+    /// repost, otherwise the original's id, the original itself (`null` when
+    /// it could be neither read from the repost nor fetched) and its author's
+    /// cached name.
+    ///
+    /// Pure — as [`Embeds::render`].
+    pub(crate) fn repost_json(
+        &self,
+        event: &Event,
+        cache: Option<&CacheDb>,
+    ) -> Result<serde_json::Value> {
+        if !is_repost(event) {
+            return Ok(serde_json::Value::Null);
+        }
+        let original = self.original(event);
+        let id = original
+            .map(|original| original.id)
+            .or_else(|| repost_target(event));
+        Ok(serde_json::json!({
+            "id": id.map(|id| id.to_hex()),
+            "event": original.map(serde_json::to_value).transpose()?,
+            "name": original.and_then(|original| thread::cached_name(cache, &original.pubkey)),
+        }))
+    }
+
+    /// The short form a reference is shown as in text: `[name: first line]`
+    /// for a note, `@name` for a profile, `[kind:N "d" by name]` for an
+    /// address. A note that could not be fetched keeps its URI.
+    fn preview(&self, reference: &Reference, cache: Option<&CacheDb>) -> String {
+        match &reference.target {
+            Nip19::Coordinate(coord) => format!(
+                "[kind:{} \"{}\" by {}]",
+                coord.coordinate.kind.as_u16(),
+                coord.coordinate.identifier,
+                short_name(&coord.coordinate.public_key, cache)
+            ),
+            target => match (referenced_event(target), referenced_pubkey(target)) {
+                (Some(id), _) => match self.events.get(&id) {
+                    Some(event) => {
+                        format!(
+                            "[{}: {}]",
+                            short_name(&event.pubkey, cache),
+                            first_line(event)
+                        )
+                    }
+                    None => reference.uri.clone(),
+                },
+                (None, Some(pubkey)) => format!("@{}", short_name(&pubkey, cache)),
+                (None, None) => reference.uri.clone(),
+            },
+        }
+    }
+
+    /// `content` with every `nostr:` reference replaced by its preview.
+    ///
+    /// Pure — it renders what has been fetched; the cache is only read.
+    pub(crate) fn render(&self, content: &str, cache: Option<&CacheDb>) -> String {
+        let mut rendered = content.to_string();
+        for reference in references(content) {
+            rendered = rendered.replacen(&reference.uri, &self.preview(&reference, cache), 1);
+        }
+        rendered
+    }
+
+    /// The references of `content` for JSON output: what each one names and,
+    /// when it was resolved, the author's name and the preview line.
+    ///
+    /// Pure — as [`Embeds::render`].
+    pub(crate) fn references_json(
+        &self,
+        content: &str,
+        cache: Option<&CacheDb>,
+    ) -> Vec<serde_json::Value> {
+        references(content)
+            .iter()
+            .map(|reference| match &reference.target {
+                Nip19::Coordinate(coord) => serde_json::json!({
+                    "uri": reference.uri,
+                    "type": "address",
+                    "coordinate": format!(
+                        "{}:{}:{}",
+                        coord.coordinate.kind.as_u16(),
+                        coord.coordinate.public_key.to_hex(),
+                        coord.coordinate.identifier
+                    ),
+                    "name": thread::cached_name(cache, &coord.coordinate.public_key),
+                }),
+                target => match (referenced_event(target), referenced_pubkey(target)) {
+                    (Some(id), _) => {
+                        let event = self.events.get(&id);
+                        let name =
+                            event.and_then(|event| thread::cached_name(cache, &event.pubkey));
+                        serde_json::json!({
+                            "uri": reference.uri,
+                            "type": "event",
+                            "id": id.to_hex(),
+                            "author": event.map(|event| event.pubkey.to_hex()),
+                            "name": name,
+                            "preview": event.map(first_line),
+                        })
+                    }
+                    (None, pubkey) => serde_json::json!({
+                        "uri": reference.uri,
+                        "type": "profile",
+                        "pubkey": pubkey.map(|pubkey| pubkey.to_hex()),
+                        "name": pubkey.and_then(|pubkey| thread::cached_name(cache, &pubkey)),
+                    }),
+                },
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(keys: &Keys, content: &str) -> Event {
+        EventBuilder::text_note(content)
+            .sign_with_keys(keys)
+            .unwrap()
+    }
+
+    #[test]
+    fn references_are_found_and_secrets_are_not() {
+        let keys = Keys::generate();
+        let quoted = note(&keys, "quoted");
+        let text = format!(
+            "look nostr:{}, by nostr:{}. not nostr:{} nor nostr:nope",
+            quoted.id.to_bech32().unwrap(),
+            keys.public_key().to_bech32().unwrap(),
+            keys.secret_key().to_bech32().unwrap(),
+        );
+        let found = references(&text);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].target, Nip19::EventId(quoted.id));
+        assert_eq!(found[1].target, Nip19::Pubkey(keys.public_key()));
+        assert!(text.contains(&found[0].uri));
+    }
+
+    #[test]
+    fn reposts_use_their_embedded_original_and_ask_for_the_rest() {
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+        let original = note(&alice, "first line\nsecond line");
+        let missing = note(&alice, "not embedded");
+        let embedded = client::repost_builder(&original, None)
+            .unwrap()
+            .sign_with_keys(&bob)
+            .unwrap();
+        let bare = EventBuilder::new(Kind::Repost, "")
+            .tags([Tag::event(missing.id)])
+            .sign_with_keys(&bob)
+            .unwrap();
+        let quote = note(
+            &bob,
+            &format!("see nostr:{}", original.id.to_bech32().unwrap()),
+        );
+
+        let (embeds, wanted) = Embeds::plan(&[embedded.clone(), bare.clone(), quote.clone()]);
+        assert_eq!(wanted, [missing.id].into_iter().collect());
+        assert_eq!(embeds.original(&embedded), Some(&original));
+        assert_eq!(embeds.original(&bare), None);
+        assert_eq!(embeds.original(&quote), None, "a quote is not a repost");
+
+        let rendered = embeds.render(&quote.content, None);
+        assert!(rendered.starts_with("see ["), "{}", rendered);
+        assert!(rendered.ends_with(": first line]"), "{}", rendered);
+
+        let json = embeds.references_json(&quote.content, None);
+        assert_eq!(json[0]["type"], "event");
+        assert_eq!(json[0]["author"], alice.public_key().to_hex());
+        assert_eq!(json[0]["preview"], "first line");
+    }
+
+    #[test]
+    fn a_forged_embedded_original_is_not_trusted() {
+        let alice = Keys::generate();
+        let original = note(&alice, "real");
+        let other = note(&alice, "something else");
+        let repost = EventBuilder::new(Kind::Repost, other.as_json())
+            .tags([Tag::event(original.id)])
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+        let (embeds, wanted) = Embeds::plan(&[repost.clone()]);
+        assert_eq!(wanted, [original.id].into_iter().collect());
+        assert_eq!(embeds.original(&repost), None);
+    }
+
+    #[test]
+    fn unresolved_notes_keep_their_uri() {
+        let uri = format!("nostr:{}", EventId::all_zeros().to_bech32().unwrap());
+        let text = format!("gone: {}", uri);
+        assert_eq!(Embeds::default().render(&text, None), text);
+    }
+}
@@ -0,0 +1,314 @@
+//! Custom emoji (NIP-30) from our own emoji lists.
+//!
+//! A user's kind:10030 holds emoji inline (`["emoji", shortcode, url]`) and
+//! points at emoji sets (kind:30030) with `a` tags; the user's own kind:30030
+//! sets count as well. `react :shortcode:` resolves against all of them, in
+//! that order, so an inline emoji shadows one of the same name in a set.
+
+use anyhow::Result;
+use nostr_sdk::prelude::*;
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::client;
+use crate::config::NostaroConfig;
+use crate::keys;
+
+/// The user's emoji list (NIP-51 standard list).
+pub const EMOJI_LIST_KIND: u16 = 10030;
+
+/// An emoji set (NIP-51 addressable set).
+pub const EMOJI_SET_KIND: u16 = 30030;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomEmoji {
+    pub shortcode: String,
+    pub url: String,
+}
+
+impl CustomEmoji {
+    /// The NIP-30 `emoji` tag that makes `:shortcode:` render as the image.
+    pub fn tag(&self) -> Result<Tag> {
+        Ok(Tag::parse([
+            "emoji",
+            self.shortcode.as_str(),
+            self.url.as_str(),
+        ])?)
+    }
+}
+
+/// A named group of emoji: the inline part of the kind:10030, or one set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmojiPack {
+    pub name: String,
+    pub emojis: Vec<CustomEmoji>,
+}
+
+/// NIP-30 shortcodes are alphanumeric plus `_` (and `-`, which is common in
+/// the wild).
+fn is_shortcode(code: &str) -> bool {
+    !code.is_empty()
+        && code
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// The shortcode in a `:shortcode:` reaction, or `None` for anything else
+/// (a plain emoji, `+`, or text that merely contains colons).
+pub fn parse_shortcode(reaction: &str) -> Option<&str> {
+    let code = reaction.strip_prefix(':')?.strip_suffix(':')?;
+    is_shortcode(code).then_some(code)
+}
+
+/// The `emoji` tags of an event, in order.
+fn emojis_in(event: &Event) -> Vec<CustomEmoji> {
+    event
+        .tags
+        .iter()
+        .filter_map(|tag| match tag.as_slice() {
+            [name, shortcode, url, ..] if name == "emoji" && is_shortcode(shortcode) => {
+                Some(CustomEmoji {
+                    shortcode: shortcode.clone(),
+                    url: url.clone(),
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The emoji sets a kind:10030 points at, as `(author, identifier)`.
+fn set_references(list: &Event) -> Vec<(PublicKey, String)> {
+    list.tags
+        .iter()
+        .filter_map(|tag| match tag.as_slice() {
+            [name, coordinate, ..] if name == "a" => {
+                let mut parts = coordinate.splitn(3, ':');
+                let kind: u16 = parts.next()?.parse().ok()?;
+                let author = PublicKey::from_hex(parts.next()?).ok()?;
+                let identifier = parts.next()?.to_string();
+                (kind == EMOJI_SET_KIND).then_some((author, identifier))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn set_identifier(set: &Event) -> String {
+    set.tags
+        .iter()
+        .find_map(|tag| match tag.as_slice() {
+            [name, value, ..] if name == "d" => Some(value.clone()),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+/// The packs in resolution order: the list's inline emoji, the sets it
+/// references (in tag order), then our own sets that it does not reference.
+///
+/// Pure — it arranges events that have already been fetched.
+fn assemble_packs(list: Option<&Event>, sets: &[Event], own: &PublicKey) -> Vec<EmojiPack> {
+    let mut packs = Vec::new();
+    let mut used: HashSet<(PublicKey, String)> = HashSet::new();
+
+    let mut ordered: Vec<(PublicKey, String)> = Vec::new();
+    if let Some(list) = list {
+        let inline = emojis_in(list);
+        if !inline.is_empty() {
+            packs.push(EmojiPack {
+                name: "emoji list".to_string(),
+                emojis: inline,
+            });
+        }
+        ordered.extend(set_references(list));
+    }
+    let mut own_sets: Vec<&Event> = sets.iter().filter(|s| s.pubkey == *own).collect();
+    own_sets.sort_by_key(|s| set_identifier(s));
+    ordered.extend(own_sets.iter().map(|s| (s.pubkey, set_identifier(s))));
+
+    for (author, identifier) in ordered {
+        if !used.insert((author, identifier.clone())) {
+            continue;
+        }
+        let newest = sets
+            .iter()
+            .filter(|s| s.pubkey == author && set_identifier(s) == identifier)
+            .max_by_key(|s| s.created_at);
+        if let Some(set) = newest {
+            let title = set
+                .tags
+                .iter()
+                .find_map(|tag| match tag.as_slice() {
+                    [name, value, ..] if name == "title" && !value.is_empty() => {
+                        Some(value.clone())
+                    }
+                    _ => None,
+                })
+                .unwrap_or_else(|| identifier.clone());
+            packs.push(EmojiPack {
+                name: title,
+                emojis: emojis_in(set),
+            });
+        }
+    }
+
+    packs
+}
+
+/// The first emoji called `shortcode`, in pack order.
+pub fn resolve<'a>(packs: &'a [EmojiPack], shortcode: &str) -> Option<&'a CustomEmoji> {
+    packs
+        .iter()
+        .flat_map(|pack| pack.emojis.iter())
+        .find(|emoji| emoji.shortcode == shortcode)
+}
+
+/// Fetch `pubkey`'s emoji list, the sets it references and `pubkey`'s own sets.
+pub async fn fetch_packs(nostr_client: &Client, pubkey: &PublicKey) -> Result<Vec<EmojiPack>> {
+    let list_filter = Filter::new()
+        .kind(Kind::from(EMOJI_LIST_KIND))
+        .author(*pubkey)
+        .limit(1);
+    let list = nostr_client
+        .fetch_events(list_filter, Duration::from_secs(10))
+        .await?
+        .into_iter()
+        .max_by_key(|e| e.created_at);
+
+    let mut sets =
+        client::fetch_addressable_events(nostr_client, Kind::from(EMOJI_SET_KIND), pubkey, 100)
+            .await?;
+
+    let references: Vec<(PublicKey, String)> = list
+        .as_ref()
+        .map(set_references)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(author, _)| author != pubkey)
+        .collect();
+    if !references.is_empty() {
+        // One read for every referenced set: all authors and identifiers in a
+        // single filter, matched up in `assemble_packs`.
+        let filter = Filter::new()
+            .kind(Kind::from(EMOJI_SET_KIND))
+            .authors(references.iter().map(|(author, _)| *author))
+            .identifiers(references.iter().map(|(_, identifier)| identifier.clone()));
+        sets.extend(
+            nostr_client
+                .fetch_events(filter, Duration::from_secs(10))
+                .await?,
+        );
+    }
+
+    Ok(assemble_packs(list.as_ref(), &sets, pubkey))
+}
+
+/// `emoji list`: every pack we can react with, and what is in it.
+pub async fn list() -> Result<()> {
+    let config = NostaroConfig::load()?;
+    let keys = keys::keys_from_config(&config)?;
+    let nostr_client = client::create_client(&keys, &config).await?;
+
+    let packs = fetch_packs(&nostr_client, &keys.public_key()).await?;
+
+    if packs.is_empty() {
+        println!("No custom emoji found (no kind:10030 emoji list or kind:30030 emoji sets).");
+    }
+    for pack in &packs {
+        println!("{} ({} emoji)", pack.name, pack.emojis.len());
+        for emoji in &pack.emojis {
+            println!("  :{}:  {}", emoji.shortcode, emoji.url);
+        }
+    }
+
+    nostr_client.disconnect().await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emoji_tag(code: &str, url: &str) -> Tag {
+        Tag::parse(["emoji", code, url]).unwrap()
+    }
+
+    fn set(keys: &Keys, identifier: &str, tags: Vec<Tag>) -> Event {
+        let mut all = vec![Tag::identifier(identifier)];
+        all.extend(tags);
+        EventBuilder::new(Kind::from(EMOJI_SET_KIND), "")
+            .tags(all)
+            .sign_with_keys(keys)
+            .unwrap()
+    }
+
+    #[test]
+    fn shortcodes_need_both_colons_and_a_plain_name() {
+        assert_eq!(parse_shortcode(":blobcat:"), Some("blobcat"));
+        assert_eq!(parse_shortcode(":party_parrot-2:"), Some("party_parrot-2"));
+        assert_eq!(parse_shortcode("🤙"), None);
+        assert_eq!(parse_shortcode("+"), None);
+        assert_eq!(parse_shortcode("::"), None);
+        assert_eq!(parse_shortcode(":not this:"), None);
+        assert_eq!(parse_shortcode("blobcat:"), None);
+    }
+
+    #[test]
+    fn inline_emoji_shadow_sets_and_sets_follow_the_list_order() {
+        let me = Keys::generate();
+        let friend = Keys::generate();
+        let friends_set = set(
+            &friend,
+            "cats",
+            vec![
+                Tag::parse(["title", "Cats"]).unwrap(),
+                emoji_tag("blobcat", "https://example.com/set.png"),
+                emoji_tag("nyan", "https://example.com/nyan.png"),
+            ],
+        );
+        let my_set = set(
+            &me,
+            "mine",
+            vec![emoji_tag("wave", "https://example.com/wave.png")],
+        );
+        let list = EventBuilder::new(Kind::from(EMOJI_LIST_KIND), "")
+            .tags([
+                emoji_tag("blobcat", "https://example.com/inline.png"),
+                Tag::parse([
+                    "a",
+                    format!("30030:{}:cats", friend.public_key().to_hex()).as_str(),
+                ])
+                .unwrap(),
+            ])
+            .sign_with_keys(&me)
+            .unwrap();
+
+        let packs = assemble_packs(Some(&list), &[my_set, friends_set], &me.public_key());
+        let names: Vec<&str> = packs.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["emoji list", "Cats", "mine"]);
+
+        assert_eq!(
+            resolve(&packs, "blobcat").unwrap().url,
+            "https://example.com/inline.png"
+        );
+        assert_eq!(
+            resolve(&packs, "nyan").unwrap().url,
+            "https://example.com/nyan.png"
+        );
+        assert!(resolve(&packs, "wave").is_some());
+        assert!(resolve(&packs, "missing").is_none());
+    }
+
+    #[test]
+    fn the_emoji_tag_is_shortcode_then_url() {
+        let emoji = CustomEmoji {
+            shortcode: "blobcat".to_string(),
+            url: "https://example.com/blobcat.png".to_string(),
+        };
+        assert_eq!(
+            emoji.tag().unwrap().to_vec(),
+            vec!["emoji", "blobcat", "https://example.com/blobcat.png"]
+        );
+    }
+}
@@ -0,0 +1,313 @@
+//! `encode`: the counterpart of `decode`, building NIP-19 entities from hex
+//! (or from other entities) plus the relay hints, author, kind and identifier
+//! the TLV forms carry.
+
+use anyhow::{anyhow, bail, Result};
+use nostr_sdk::prelude::*;
+
+use crate::output;
+use crate::utils::{parse_event_reference, resolve_pubkey};
+
+/// The entity `encode` builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum EntityType {
+    /// Public key
+    Npub,
+    /// Event id
+    Note,
+    /// Public key with relay hints
+    Nprofile,
+    /// Event id with relay hints, author and kind
+    Nevent,
+    /// Addressable event: kind, author and identifier, with relay hints
+    Naddr,
+}
+
+/// The optional TLV fields of `encode`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EncodeOptions {
+    pub relays: Vec<String>,
+    pub author: Option<String>,
+    pub kind: Option<u16>,
+    pub identifier: Option<String>,
+}
+
+fn entity_name(entity: EntityType) -> &'static str {
+    match entity {
+        EntityType::Npub => "npub",
+        EntityType::Note => "note",
+        EntityType::Nprofile => "nprofile",
+        EntityType::Nevent => "nevent",
+        EntityType::Naddr => "naddr",
+    }
+}
+
+/// Refuse options the entity has no room for, rather than silently dropping
+/// them — an npub with a `--relay` is not the nprofile the caller wanted.
+fn reject_unused(entity: EntityType, options: &EncodeOptions) -> Result<()> {
+    let name = entity_name(entity);
+    let takes = |flag: &str| match entity {
+        EntityType::Npub | EntityType::Note => false,
+        EntityType::Nprofile => flag == "--relay",
+        EntityType::Nevent => flag != "--identifier",
+        EntityType::Naddr => true,
+    };
+    let given = [
+        ("--relay", !options.relays.is_empty()),
+        ("--author", options.author.is_some()),
+        ("--kind", options.kind.is_some()),
+        ("--identifier", options.identifier.is_some()),
+    ];
+    for (flag, present) in given {
+        if present && !takes(flag) {
+            bail!("{} does not apply to {}", flag, name);
+        }
+    }
+    Ok(())
+}
+
+fn parse_relays(relays: &[String]) -> Result<Vec<RelayUrl>> {
+    relays
+        .iter()
+        .map(|relay| {
+            RelayUrl::parse(relay).map_err(|e| anyhow!("invalid relay URL {}: {}", relay, e))
+        })
+        .collect()
+}
+
+/// The relay hints an entity already carries, followed by the `--relay` ones
+/// it does not have yet.
+fn merge_relays(mut hints: Vec<RelayUrl>, relays: &[String]) -> Result<Vec<RelayUrl>> {
+    for relay in parse_relays(relays)? {
+        if !hints.contains(&relay) {
+            hints.push(relay);
+        }
+    }
+    Ok(hints)
+}
+
+/// The value as the TLV entity of the same type when it is one (with or
+/// without a `nostr:` prefix), so re-encoding it keeps what it carries.
+fn reencoded<T: FromBech32>(value: &str) -> Option<T> {
+    let value = value.trim();
+    T::from_bech32(value.strip_prefix("nostr:").unwrap_or(value)).ok()
+}
+
+/// The address an `naddr` points at: from `kind:pubkey:identifier` in
+/// `value`, or from `--kind`, `--author` and `--identifier`.
+fn coordinate(value: Option<&str>, options: &EncodeOptions) -> Result<Coordinate> {
+    if let Some(value) = value {
+        if options.kind.is_some() || options.author.is_some() || options.identifier.is_some() {
+            bail!(
+                "give naddr either a kind:pubkey:identifier value \
+                 or --kind/--author/--identifier, not both"
+            );
+        }
+        let mut parts = value.splitn(3, ':');
+        let (Some(kind), Some(author), Some(identifier)) =
+            (parts.next(), parts.next(), parts.next())
+        else {
+            bail!("expected kind:pubkey:identifier, got \"{}\"", value);
+        };
+        let kind: u16 = kind
+            .parse()
+            .map_err(|_| anyhow!("invalid kind \"{}\" in {}", kind, value))?;
+        return Ok(Coordinate {
+            kind: Kind::from(kind),
+            public_key: resolve_pubkey(author)?,
+            identifier: identifier.to_string(),
+        });
+    }
+
+    let kind = options
+        .kind
+        .ok_or_else(|| anyhow!("naddr needs --kind (or a kind:pubkey:identifier value)"))?;
+    let author = options
+        .author
+        .as_deref()
+        .ok_or_else(|| anyhow!("naddr needs --author (or a kind:pubkey:identifier value)"))?;
+    Ok(Coordinate {
+        kind: Kind::from(kind),
+        public_key: resolve_pubkey(author)?,
+        identifier: options.identifier.clone().unwrap_or_default(),
+    })
+}
+
+/// Build the bech32 entity. Pubkeys may be given as hex, npub or nprofile,
+/// event ids as hex, note or nevent — so `encode nevent note1... --relay ...`
+/// adds relay hints to a note id. Re-encoding an nprofile or nevent keeps the
+/// relay hints (and the nevent's author and kind) it already has; the flags
+/// add to them, or replace the author and kind.
+///
+/// Pure — it only formats what it is given.
+pub fn encode(entity: EntityType, value: Option<&str>, options: &EncodeOptions) -> Result<String> {
+    reject_unused(entity, options)?;
+    let required = || value.ok_or_else(|| anyhow!("encode needs a value to encode"));
+
+    Ok(match entity {
+        EntityType::Npub => resolve_pubkey(required()?)?.to_bech32()?,
+        EntityType::Note => parse_event_reference(required()?)?.0.to_bech32()?,
+        EntityType::Nprofile => {
+            let value = required()?;
+            let mut profile = match reencoded::<Nip19Profile>(value) {
+                Some(profile) => profile,
+                None => Nip19Profile {
+                    public_key: resolve_pubkey(value)?,
+                    relays: Vec::new(),
+                },
+            };
+            profile.relays = merge_relays(profile.relays, &options.relays)?;
+            profile.to_bech32()?
+        }
+        EntityType::Nevent => {
+            let value = required()?;
+            let mut nevent = match reencoded::<Nip19Event>(value) {
+                Some(nevent) => nevent,
+                None => Nip19Event::new(parse_event_reference(value)?.0),
+            };
+            nevent.relays = merge_relays(nevent.relays, &options.relays)?;
+            if let Some(author) = options.author.as_deref() {
+                nevent = nevent.author(resolve_pubkey(author)?);
+            }
+            if let Some(kind) = options.kind {
+                nevent = nevent.kind(Kind::from(kind));
+            }
+            nevent.to_bech32()?
+        }
+        EntityType::Naddr => {
+            parse_relays(&options.relays)?;
+            Nip19Coordinate::new(coordinate(value, options)?, options.relays.clone())?
+                .to_bech32()?
+        }
+    })
+}
+
+pub fn run(entity: EntityType, value: Option<&str>, options: &EncodeOptions) -> Result<()> {
+    let encoded = encode(entity, value, options)?;
+    if output::is_json() {
+        output::write_json(&serde_json::json!({
+            "type": entity_name(entity),
+            "bech32": encoded,
+            "uri": format!("nostr:{}", encoded),
+            "decoded": super::decode::decode(&encoded)?,
+        }))?;
+    }
+    println!("{}", encoded);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::decode::decode;
+
+    #[test]
+    fn nevents_carry_relays_author_and_kind() {
+        let keys = Keys::generate();
+        let note = EventBuilder::text_note("hi").sign_with_keys(&keys).unwrap();
+        let options = EncodeOptions {
+            relays: vec!["wss://relay.example.com".to_string()],
+            author: Some(keys.public_key().to_bech32().unwrap()),
+            kind: Some(1),
+            identifier: None,
+        };
+        let nevent = encode(
+            EntityType::Nevent,
+            Some(&note.id.to_bech32().unwrap()),
+            &options,
+        )
+        .unwrap();
+
+        let decoded = decode(&nevent).unwrap();
+        assert_eq!(decoded["event_id"], note.id.to_hex());
+        assert_eq!(decoded["author"], keys.public_key().to_hex());
+        assert_eq!(decoded["kind"], 1);
+        assert_eq!(decoded["relays"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn reencoding_keeps_the_hints_the_value_carries() {
+        let keys = Keys::generate();
+        let id = EventId::all_zeros();
+        let first = RelayUrl::parse("wss://a.example.com").unwrap();
+        let nevent = Nip19Event::new(id)
+            .author(keys.public_key())
+            .kind(Kind::TextNote)
+            .relays([first.clone()])
+            .to_bech32()
+            .unwrap();
+        let options = EncodeOptions {
+            relays: vec!["wss://b.example.com".to_string(), first.to_string()],
+            ..EncodeOptions::default()
+        };
+
+        let reencoded = encode(EntityType::Nevent, Some(&nevent), &options).unwrap();
+        let decoded = decode(&reencoded).unwrap();
+        assert_eq!(decoded["author"], keys.public_key().to_hex());
+        assert_eq!(decoded["kind"], 1);
+        assert_eq!(
+            decoded["relays"],
+            serde_json::json!(["wss://a.example.com", "wss://b.example.com"])
+        );
+
+        let nprofile = Nip19Profile::new(keys.public_key(), [first])
+            .unwrap()
+            .to_bech32()
+            .unwrap();
+        let reencoded = encode(
+            EntityType::Nprofile,
+            Some(&format!("nostr:{}", nprofile)),
+            &EncodeOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(reencoded, nprofile);
+    }
+
+    #[test]
+    fn naddrs_come_from_flags_or_a_coordinate() {
+        let keys = Keys::generate();
+        let from_flags = encode(
+            EntityType::Naddr,
+            None,
+            &EncodeOptions {
+                author: Some(keys.public_key().to_hex()),
+                kind: Some(30023),
+                identifier: Some("hello".to_string()),
+                ..EncodeOptions::default()
+            },
+        )
+        .unwrap();
+        let from_value = encode(
+            EntityType::Naddr,
+            Some(&format!("30023:{}:hello", keys.public_key().to_hex())),
+            &EncodeOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(from_flags, from_value);
+
+        let decoded = decode(&from_flags).unwrap();
+        assert_eq!(decoded["kind"], 30023);
+        assert_eq!(decoded["identifier"], "hello");
+
+        assert!(encode(EntityType::Naddr, None, &EncodeOptions::default()).is_err());
+    }
+
+    #[test]
+    fn options_an_entity_cannot_hold_are_refused() {
+        let keys = Keys::generate();
+        let hex = keys.public_key().to_hex();
+        let with_relay = EncodeOptions {
+            relays: vec!["wss://relay.example.com".to_string()],
+            ..EncodeOptions::default()
+        };
+        let err = encode(EntityType::Npub, Some(&hex), &with_relay).unwrap_err();
+        assert!(err.to_string().contains("--relay does not apply to npub"));
+        assert!(encode(EntityType::Nprofile, Some(&hex), &with_relay).is_ok());
+
+        let bad_relay = EncodeOptions {
+            relays: vec!["not a url".to_string()],
+            ..EncodeOptions::default()
+        };
+        assert!(encode(EntityType::Nprofile, Some(&hex), &bad_relay).is_err());
+    }
+}
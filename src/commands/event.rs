@@ -1,11 +1,18 @@
 use anyhow::{anyhow, bail, Context, Result};
 use nostr_sdk::prelude::*;
 use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
 use std::path::Path;
+use std::time::Duration;
 
 use crate::client;
 use crate::config::NostaroConfig;
 use crate::keys;
+use crate::outln;
+use crate::output;
+use crate::post_options::PostOptions;
 
 /// Fields that describe a *signed* event. They cannot be honoured here (nostaro
 /// signs with the configured key), so a file containing them is rejected
@@ -38,7 +45,7 @@ fn is_hex_32_bytes(value: &str) -> bool {
 /// Nothing else is second-guessed here: the shape of a tag is the NIPs' business
 /// (a name-only tag such as NIP-70's `["-"]` is perfectly valid), and nostaro
 /// must not refuse tags it happens not to know.
-fn validate_tag(index: usize, values: &[String]) -> Result<()> {
+pub(crate) fn validate_tag(index: usize, values: &[String]) -> Result<()> {
     let Some(name) = values.first() else {
         bail!(
             "tags[{}] is empty; every tag needs at least a name element, e.g. [\"p\", \"<hex>\"]",
@@ -183,10 +190,11 @@ pub async fn run(
     tags: Vec<String>,
     content: Option<String>,
     file: Option<&Path>,
+    options: &PostOptions,
 ) -> Result<()> {
     // Resolve the event before touching the config, the key or the network, so
     // that a malformed file fails fast and without connecting anywhere.
-    let (kind, content, parsed_tags) = match file {
+    let (kind, content, mut parsed_tags) = match file {
         Some(path) => {
             let spec = load_event_spec(path)?;
             let parsed_tags = spec
@@ -199,6 +207,9 @@ pub async fn run(
             (kind, content.unwrap_or_default(), parse_tag_flags(&tags)?)
         }
     };
+    // The shared post options ride on top of whatever the file or the flags
+    // described; they never replace a tag the caller wrote.
+    parsed_tags.extend(options.tags(Timestamp::now())?);
 
     let config = NostaroConfig::load()?;
     let keys = keys::keys_from_config(&config)?;
@@ -208,8 +219,348 @@ pub async fn run(
     println!("Publishing kind:{} event ({} tag(s))...", kind, tag_count);
     let builder = build_event(kind, content, parsed_tags);
     let output = client::publish(&nostr_client, builder).await?;
-    println!("Event published! ID: {}", output.id().to_hex());
+    client::report_published(&format!("Event published! ID: {}", output.id().to_hex()));
 
     nostr_client.disconnect().await;
     Ok(())
 }
+
+/// How many invalid lines `--batch` lists before it stops counting them out.
+const MAX_REPORTED_BATCH_ERRORS: usize = 20;
+
+/// One event of a `--batch` file: its position among the events (`index`,
+/// from 0), the line it came from (from 1), the SHA-256 of that line's text
+/// (what `--resume` matches on) and what it describes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchEntry {
+    pub index: usize,
+    pub line: usize,
+    pub digest: String,
+    pub spec: EventSpec,
+}
+
+/// Hex SHA-256 of a batch line, surrounding whitespace ignored.
+fn line_digest(raw: &str) -> String {
+    Sha256::digest(raw.trim().as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Parse and validate every line of a `--batch` JSONL document.
+///
+/// Blank lines are skipped. Every other line goes through the same
+/// `parse_event_spec` / tag validation as `--file`, and **all** of them are
+/// checked before anything is returned: a batch with a broken line 4,000 fails
+/// before line 1 is published, with every broken line listed, instead of
+/// leaving a half-published migration behind.
+pub fn parse_batch(text: &str) -> Result<Vec<BatchEntry>> {
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+
+    for (offset, raw) in text.lines().enumerate() {
+        if raw.trim().is_empty() {
+            continue;
+        }
+        let line = offset + 1;
+        let checked = parse_event_spec(raw).and_then(|spec| spec.parsed_tags().map(|_| spec));
+        match checked {
+            Ok(spec) => entries.push(BatchEntry {
+                index: entries.len() + errors.len(),
+                line,
+                digest: line_digest(raw),
+                spec,
+            }),
+            Err(err) => errors.push(format!("line {}: {:#}", line, err)),
+        }
+    }
+
+    if !errors.is_empty() {
+        let shown = errors.len().min(MAX_REPORTED_BATCH_ERRORS);
+        let mut message = format!(
+            "{} of {} line(s) in the batch are invalid; nothing was published:\n  {}",
+            errors.len(),
+            entries.len() + errors.len(),
+            errors[..shown].join("\n  ")
+        );
+        if errors.len() > shown {
+            message.push_str(&format!("\n  ... and {} more", errors.len() - shown));
+        }
+        bail!(message);
+    }
+    if entries.is_empty() {
+        bail!("the batch contains no events (one JSON object per line is expected)");
+    }
+
+    Ok(entries)
+}
+
+/// The successful results of an earlier run, by the digest of the line each
+/// came from, in the order that run wrote them. Failed and unparseable lines,
+/// dry-run records (nothing was published) and records without a digest are
+/// ignored — those events are simply published again.
+pub fn parse_resume(text: &str) -> HashMap<String, Vec<serde_json::Value>> {
+    let mut done: HashMap<String, Vec<serde_json::Value>> = HashMap::new();
+    for record in text
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter(|record| record["ok"] == true && record["dry_run"] != true)
+    {
+        if let Some(digest) = record["digest"].as_str() {
+            done.entry(digest.to_string()).or_default().push(record);
+        }
+    }
+    done
+}
+
+/// Which entries of this batch an earlier run already published, by `index`.
+///
+/// Results are matched on the text of the line, not its position: a batch
+/// file edited between runs (a line inserted, removed or changed) must not
+/// have one event's result applied to another. The nth copy of a repeated
+/// line takes the nth result recorded for it. A matched record is renumbered
+/// to where its line is now.
+///
+/// Pure — it pairs up two lists that are already parsed.
+pub fn match_resume(
+    entries: &[BatchEntry],
+    mut done: HashMap<String, Vec<serde_json::Value>>,
+) -> HashMap<usize, serde_json::Value> {
+    let mut matched = HashMap::new();
+    for entry in entries {
+        let Some(records) = done.get_mut(&entry.digest) else {
+            continue;
+        };
+        if records.is_empty() {
+            continue;
+        }
+        let mut record = records.remove(0);
+        record["index"] = entry.index.into();
+        record["line"] = entry.line.into();
+        matched.insert(entry.index, record);
+    }
+    matched
+}
+
+/// One line of `--batch` output.
+///
+/// `ok` means at least one relay accepted the event — the same rule a single
+/// `event` publish uses.
+///
+/// Pure — it formats an outcome that has already happened.
+pub fn batch_record(
+    entry: &BatchEntry,
+    id: Option<EventId>,
+    outcome: std::result::Result<&Output<EventId>, String>,
+) -> serde_json::Value {
+    let (accepted, rejected, error) = match outcome {
+        Ok(output) => {
+            let mut accepted: Vec<String> = output.success.iter().map(|r| r.to_string()).collect();
+            accepted.sort();
+            let rejected: BTreeMap<String, String> = output
+                .failed
+                .iter()
+                .map(|(relay, reason)| (relay.to_string(), reason.to_string()))
+                .collect();
+            let error = accepted
+                .is_empty()
+                .then(|| "no relay accepted the event".to_string());
+            (accepted, rejected, error)
+        }
+        Err(error) => (Vec::new(), BTreeMap::new(), Some(error)),
+    };
+
+    serde_json::json!({
+        "index": entry.index,
+        "line": entry.line,
+        "digest": entry.digest,
+        "ok": error.is_none(),
+        "id": id.map(|id| id.to_hex()),
+        "accepted": accepted,
+        "rejected": rejected,
+        "error": error,
+    })
+}
+
+/// One line of `--batch --dry-run` output: the signed event where the relays
+/// would have gone, so the output shows exactly what would be sent. It carries
+/// no `digest`, so a later `--resume` can never take it for a published event.
+///
+/// Pure — it formats an event that was only signed.
+pub fn dry_run_record(entry: &BatchEntry, event: &Event) -> serde_json::Value {
+    serde_json::json!({
+        "index": entry.index,
+        "line": entry.line,
+        "ok": true,
+        "id": event.id.to_hex(),
+        "dry_run": true,
+        "event": event,
+    })
+}
+
+/// Knobs for `event --batch`.
+#[derive(Debug, Clone, Default)]
+pub struct BatchOptions {
+    /// Events in flight at once (at least 1).
+    pub concurrency: usize,
+    /// Upper bound on events started per second.
+    pub rate: Option<f64>,
+    /// JSONL written by an earlier run; lines it records as `ok` are skipped.
+    pub resume: Option<std::path::PathBuf>,
+}
+
+pub(crate) fn read_source(source: &Path) -> Result<String> {
+    if source == Path::new("-") {
+        let mut text = String::new();
+        std::io::stdin()
+            .read_to_string(&mut text)
+            .context("failed to read the batch from stdin")?;
+        return Ok(text);
+    }
+    std::fs::read_to_string(source)
+        .with_context(|| format!("failed to read batch file {}", source.display()))
+}
+
+/// Publish every event of a JSONL batch, one result line per event.
+///
+/// Results are the body (stdout, or `--out`), in input order whatever the
+/// concurrency; progress goes to stderr so the body stays pure JSONL. A rerun
+/// with `--resume <previous results>` re-emits the recorded successes and
+/// publishes only the rest, so the new results are again complete.
+pub async fn run_batch(source: &Path, options: &PostOptions, batch: &BatchOptions) -> Result<()> {
+    // Everything that can be checked offline is checked before connecting.
+    let entries = parse_batch(&read_source(source)?)?;
+    let option_tags = options.tags(Timestamp::now())?;
+    let done = match &batch.resume {
+        Some(path) => match_resume(
+            &entries,
+            parse_resume(
+                &std::fs::read_to_string(path)
+                    .with_context(|| format!("failed to read resume file {}", path.display()))?,
+            ),
+        ),
+        None => HashMap::new(),
+    };
+    if let Some(rate) = batch.rate {
+        if !rate.is_finite() || rate <= 0.0 {
+            bail!("--rate must be a number of events per second greater than zero");
+        }
+    }
+    let concurrency = batch.concurrency.max(1);
+
+    let config = NostaroConfig::load()?;
+    let keys = keys::keys_from_config(&config)?;
+    let nostr_client = client::create_client(&keys, &config).await?;
+
+    let pending = entries
+        .iter()
+        .filter(|e| !done.contains_key(&e.index))
+        .count();
+    eprintln!(
+        "Publishing {} event(s) ({} already done), concurrency {}...",
+        pending,
+        entries.len() - pending,
+        concurrency
+    );
+
+    let mut ticker = batch.rate.map(|rate| {
+        // `interval` panics on a zero period, which a huge rate rounds down to.
+        let period = Duration::from_secs_f64(1.0 / rate).max(Duration::from_micros(1));
+        let mut ticker = tokio::time::interval(period);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        ticker
+    });
+
+    output::open_body()?;
+    let mut in_flight = tokio::task::JoinSet::new();
+    let mut finished: BTreeMap<usize, serde_json::Value> = BTreeMap::new();
+    let mut next_to_emit = 0;
+    let mut failures = 0;
+
+    // Emit every result that is ready, in input order.
+    let mut flush = |finished: &mut BTreeMap<usize, serde_json::Value>,
+                     next_to_emit: &mut usize|
+     -> Result<()> {
+        while let Some(record) = finished.remove(next_to_emit) {
+            if record["ok"] != true {
+                failures += 1;
+            }
+            outln!("{}", serde_json::to_string(&record)?)?;
+            *next_to_emit += 1;
+        }
+        Ok(())
+    };
+
+    for entry in &entries {
+        if let Some(previous) = done.get(&entry.index) {
+            let mut record = previous.clone();
+            record["resumed"] = serde_json::Value::Bool(true);
+            finished.insert(entry.index, record);
+            flush(&mut finished, &mut next_to_emit)?;
+            continue;
+        }
+
+        while in_flight.len() >= concurrency {
+            if let Some(joined) = in_flight.join_next().await {
+                let (index, record): (usize, serde_json::Value) = joined?;
+                finished.insert(index, record);
+            }
+            flush(&mut finished, &mut next_to_emit)?;
+        }
+
+        if let Some(ticker) = ticker.as_mut() {
+            ticker.tick().await;
+        }
+
+        let mut tags = entry.spec.parsed_tags()?;
+        tags.extend(option_tags.iter().cloned());
+        let signed =
+            build_event(entry.spec.kind, entry.spec.content.clone(), tags).sign_with_keys(&keys);
+
+        let nostr_client = nostr_client.clone();
+        let entry = entry.clone();
+        in_flight.spawn(async move {
+            let record = match signed {
+                Ok(event) if client::is_dry_run() => dry_run_record(&entry, &event),
+                Ok(event) => {
+                    let sent = client::send_signed(&nostr_client, &event).await;
+                    match &sent {
+                        Ok(output) => batch_record(&entry, Some(event.id), Ok(output)),
+                        Err(err) => batch_record(&entry, Some(event.id), Err(format!("{:#}", err))),
+                    }
+                }
+                Err(err) => batch_record(&entry, None, Err(format!("failed to sign: {}", err))),
+            };
+            (entry.index, record)
+        });
+    }
+
+    while let Some(joined) = in_flight.join_next().await {
+        let (index, record) = joined?;
+        finished.insert(index, record);
+        flush(&mut finished, &mut next_to_emit)?;
+    }
+
+    nostr_client.disconnect().await;
+
+    if client::is_dry_run() {
+        eprintln!(
+            "Dry run: {} event(s) signed, nothing was published.",
+            entries.len() - failures
+        );
+    } else {
+        eprintln!(
+            "{} of {} event(s) published.",
+            entries.len() - failures,
+            entries.len()
+        );
+    }
+    if failures > 0 {
+        bail!(
+            "{} event(s) were not accepted by any relay; rerun with --resume <this run's results> \
+             to publish only those",
+            failures
+        );
+    }
+    Ok(())
+}
@@ -1,12 +1,20 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use nostr_sdk::prelude::*;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 
+use crate::cache::CacheDb;
 use crate::client;
 use crate::config::NostaroConfig;
 use crate::keys;
 use crate::outln;
 use crate::output;
-use crate::utils::resolve_pubkey;
+use crate::utils::{self, resolve_pubkey_online, resolve_pubkey_with_hints, EventTarget};
+
+use super::list::{self, ListItems};
+use super::profile::confirm;
+use super::snapshot::{find_version, snapshot_event, versions};
+use super::timeline::format_time;
 
 /// One entry of a following/followers listing: the npub and the hex pubkey
 /// (what a kind:3 `p` tag needs).
@@ -74,49 +82,607 @@ fn emit(entries: &[Entry]) -> Result<()> {
     Ok(())
 }
 
-pub async fn follow(pubkey_str: &str) -> Result<()> {
+/// The newest of our kind:3 as the relays returned it now and as recorded
+/// before — what a new list is checked against. A relay that answers with a
+/// stale or empty list does not get to define what "previous" means.
+fn newest_known(fetched: Option<&Event>, recorded: &[Event]) -> Option<Event> {
+    fetched
+        .into_iter()
+        .chain(recorded.iter())
+        .max_by_key(|event| event.created_at)
+        .cloned()
+}
+
+/// Refuse a new contact list that drops more than `limit` of `previous`.
+///
+/// One dropped follow is always allowed, so unfollowing from a short list
+/// works whatever the limit; a limit of 1 allows dropping everything.
+///
+/// Pure — it compares two events that are already in hand.
+pub fn check_drop(previous: &Event, new: &Event, limit: f64) -> Result<()> {
+    let before = client::contact_pubkeys(previous).len();
+    let (_, removed) = client::contact_list_diff(Some(previous), new);
+    let allowed = ((before as f64 * limit).floor() as usize).max(1);
+    if removed.len() > allowed {
+        bail!(
+            "refusing to publish: the new follow list drops {} of the {} follows in your \
+             latest known list (from {}), more than contact_drop_limit ({}) allows.\n\
+             If a relay returned a stale or empty list, see `nostaro follow history` and \
+             `nostaro follow restore <version>`; to publish anyway, pass --force.",
+            removed.len(),
+            before,
+            format_time(previous.created_at),
+            limit
+        );
+    }
+    Ok(())
+}
+
+/// Our kind:3 as the relays have it now, and the newest version nostaro knows
+/// of. Both the fetched list and every earlier one stay recorded.
+async fn own_contact_list(
+    nostr_client: &Client,
+    keys: &Keys,
+    cache: Option<&CacheDb>,
+) -> Result<(Option<Event>, Option<Event>)> {
+    let current = client::fetch_contact_list(nostr_client, &keys.public_key()).await?;
+    let recorded = match cache {
+        Some(cache) => {
+            if let Some(event) = &current {
+                snapshot_event(cache, event);
+            }
+            versions(cache, &keys.public_key(), Kind::ContactList)?
+        }
+        None => Vec::new(),
+    };
+    let newest = newest_known(current.as_ref(), &recorded);
+    Ok((current, newest))
+}
+
+/// Check a signed kind:3 against `previous` unless the guard is off, publish
+/// it and record it.
+async fn publish_contacts(
+    nostr_client: &Client,
+    event: &Event,
+    previous: Option<&Event>,
+    limit: f64,
+    cache: Option<&CacheDb>,
+) -> Result<()> {
+    if let Some(previous) = previous {
+        check_drop(previous, event, limit)?;
+    }
+    client::publish_event(nostr_client, event).await?;
+    if let (Some(cache), false) = (cache, client::is_dry_run()) {
+        snapshot_event(cache, event);
+    }
+    Ok(())
+}
+
+/// Whether `tag` is a `p` tag for `pubkey`.
+fn is_follow_of(tag: &Tag, pubkey: &PublicKey) -> bool {
+    match tag.as_slice() {
+        [name, hex, ..] => name == "p" && hex.eq_ignore_ascii_case(&pubkey.to_hex()),
+        _ => false,
+    }
+}
+
+/// Follow `pubkey` in a kind:3 tag list, editing it in place.
+///
+/// A new follow is appended as NIP-02 lays it out, `["p", <hex>, <relay>,
+/// <petname>]`, with the trailing fields left off when empty. An existing
+/// follow keeps its tag as it is and only gets the relay or petname that was
+/// given; every other tag is untouched. Returns whether anything changed.
+///
+/// Pure — it edits the list it is given.
+pub fn add_follow(
+    tags: &mut Vec<Tag>,
+    pubkey: &PublicKey,
+    relay: Option<&str>,
+    petname: Option<&str>,
+) -> Result<bool> {
+    let set = |fields: &mut Vec<String>| {
+        for (index, value) in [(2, relay), (3, petname)] {
+            if let Some(value) = value {
+                if fields.len() <= index {
+                    fields.resize(index + 1, String::new());
+                }
+                fields[index] = value.to_string();
+            }
+        }
+    };
+
+    if let Some(index) = tags.iter().position(|tag| is_follow_of(tag, pubkey)) {
+        let mut fields = tags[index].as_slice().to_vec();
+        set(&mut fields);
+        if fields == tags[index].as_slice() {
+            return Ok(false);
+        }
+        tags[index] = Tag::parse(fields)?;
+        return Ok(true);
+    }
+
+    let mut fields = vec!["p".to_string(), pubkey.to_hex()];
+    set(&mut fields);
+    while fields.len() > 2 && fields.last().is_some_and(|field| field.is_empty()) {
+        fields.pop();
+    }
+    tags.push(Tag::parse(fields)?);
+    Ok(true)
+}
+
+/// Drop every `p` tag for `pubkey`, leaving the rest of the list as it was.
+/// Returns whether it was followed.
+///
+/// Pure — it edits the list it is given.
+pub fn remove_follow(tags: &mut Vec<Tag>, pubkey: &PublicKey) -> bool {
+    let before = tags.len();
+    tags.retain(|tag| !is_follow_of(tag, pubkey));
+    tags.len() != before
+}
+
+/// The drop limit for this run: the configured one, or none with `--force`.
+fn drop_limit(config: &NostaroConfig, force: bool) -> f64 {
+    if force {
+        1.0
+    } else {
+        config.contact_drop_limit()
+    }
+}
+
+pub async fn follow(
+    pubkey_str: &str,
+    relay: Option<&str>,
+    petname: Option<&str>,
+    force: bool,
+) -> Result<()> {
+    if let Some(relay) = relay {
+        RelayUrl::parse(relay).with_context(|| format!("invalid relay URL {}", relay))?;
+    }
+
     let config = NostaroConfig::load()?;
     let keys = keys::keys_from_config(&config)?;
     let nostr_client = client::create_client(&keys, &config).await?;
+    let cache = CacheDb::open().ok();
 
-    let pubkey = resolve_pubkey(pubkey_str)?;
+    let pubkey = resolve_pubkey_online(pubkey_str).await?;
 
-    let mut contacts = client::fetch_contacts(&nostr_client, &keys.public_key()).await?;
+    let (current, newest) = own_contact_list(&nostr_client, &keys, cache.as_ref()).await?;
+    let mut tags: Vec<Tag> = current
+        .as_ref()
+        .map(|event| event.tags.iter().cloned().collect())
+        .unwrap_or_default();
+    let already = tags.iter().any(|tag| is_follow_of(tag, &pubkey));
 
-    if contacts.contains(&pubkey) {
+    if !add_follow(&mut tags, &pubkey, relay, petname)? {
         println!("Already following {}", pubkey.to_bech32()?);
         nostr_client.disconnect().await;
         return Ok(());
     }
 
-    contacts.push(pubkey);
-
-    client::publish_contact_list(&nostr_client, &contacts).await?;
-    println!("Now following {}", pubkey.to_bech32()?);
+    let event = nostr_client
+        .sign_event_builder(client::contact_list(current.as_ref(), tags))
+        .await?;
+    publish_contacts(
+        &nostr_client,
+        &event,
+        newest.as_ref(),
+        drop_limit(&config, force),
+        cache.as_ref(),
+    )
+    .await?;
+    if already {
+        client::report_published(&format!("Updated {}", pubkey.to_bech32()?));
+    } else {
+        client::report_published(&format!("Now following {}", pubkey.to_bech32()?));
+    }
 
     nostr_client.disconnect().await;
     Ok(())
 }
 
-pub async fn unfollow(pubkey_str: &str) -> Result<()> {
+pub async fn unfollow(pubkey_str: &str, force: bool) -> Result<()> {
     let config = NostaroConfig::load()?;
     let keys = keys::keys_from_config(&config)?;
     let nostr_client = client::create_client(&keys, &config).await?;
+    let cache = CacheDb::open().ok();
 
-    let pubkey = resolve_pubkey(pubkey_str)?;
+    let pubkey = resolve_pubkey_online(pubkey_str).await?;
 
-    let mut contacts = client::fetch_contacts(&nostr_client, &keys.public_key()).await?;
+    let (current, newest) = own_contact_list(&nostr_client, &keys, cache.as_ref()).await?;
+    let mut tags: Vec<Tag> = current
+        .as_ref()
+        .map(|event| event.tags.iter().cloned().collect())
+        .unwrap_or_default();
 
-    if !contacts.contains(&pubkey) {
+    if !remove_follow(&mut tags, &pubkey) {
         println!("Not following {}", pubkey.to_bech32()?);
         nostr_client.disconnect().await;
         return Ok(());
     }
 
-    contacts.retain(|&p| p != pubkey);
+    let event = nostr_client
+        .sign_event_builder(client::contact_list(current.as_ref(), tags))
+        .await?;
+    publish_contacts(
+        &nostr_client,
+        &event,
+        newest.as_ref(),
+        drop_limit(&config, force),
+        cache.as_ref(),
+    )
+    .await?;
+    client::report_published(&format!("Unfollowed {}", pubkey.to_bech32()?));
+
+    nostr_client.disconnect().await;
+    Ok(())
+}
+
+/// Where a bulk `follow` takes the users to add from.
+pub enum FollowSource {
+    /// A file of pubkeys, one per line (`-` for stdin)
+    File(PathBuf),
+    /// Another account, whose kind:3 follows are copied
+    CopyFrom(String),
+    /// A NIP-51 set, by naddr
+    FromList(String),
+}
+
+/// The entries of a `--file` list: one npub, hex, nprofile or NIP-05 address
+/// per line; blank lines and `#` comments are skipped, duplicates are dropped.
+///
+/// Pure — it splits the text it is given; resolving is the caller's.
+pub fn file_entries(text: &str) -> Result<Vec<String>> {
+    let mut seen = HashSet::new();
+    let entries: Vec<String> = text
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty() && seen.insert(line.to_string()))
+        .map(str::to_string)
+        .collect();
+    if entries.is_empty() {
+        bail!("the file names nobody");
+    }
+    Ok(entries)
+}
+
+/// Resolve the entries of a `--file` list, keeping the first relay hint an
+/// nprofile or NIP-05 address gives.
+async fn read_file(path: &Path) -> Result<Vec<(PublicKey, Option<String>)>> {
+    let mut users = Vec::new();
+    for entry in file_entries(&super::event::read_source(path)?)? {
+        let (pubkey, hints) = resolve_pubkey_with_hints(&entry)
+            .await
+            .with_context(|| format!("{}: not a pubkey", entry))?;
+        users.push((pubkey, hints.into_iter().next()));
+    }
+    Ok(users)
+}
+
+/// The follows of `input`'s kind:3, with the relay hints it stores. Their
+/// petnames are theirs and are not copied.
+async fn read_follows_of(
+    nostr_client: &Client,
+    input: &str,
+) -> Result<Vec<(PublicKey, Option<String>)>> {
+    let (pubkey, hints) = resolve_pubkey_with_hints(input).await?;
+    client::add_relay_hints(nostr_client, &hints).await;
+    let Some(event) = client::fetch_contact_list(nostr_client, &pubkey).await? else {
+        bail!("no follow list (kind:3) found for {}", pubkey.to_bech32()?);
+    };
+    Ok(event
+        .tags
+        .iter()
+        .filter_map(|tag| match tag.as_slice() {
+            [name, hex, rest @ ..] if name == "p" => {
+                let relay = rest.first().filter(|relay| !relay.is_empty()).cloned();
+                PublicKey::from_hex(hex).ok().map(|pubkey| (pubkey, relay))
+            }
+            _ => None,
+        })
+        .collect())
+}
+
+/// The members of a NIP-51 set. Private members are only readable in our own
+/// sets; someone else's set gives its public members.
+async fn read_set(
+    nostr_client: &Client,
+    keys: &Keys,
+    naddr: &str,
+) -> Result<Vec<(PublicKey, Option<String>)>> {
+    let target = utils::parse_event_target(naddr)?;
+    if !matches!(target, EventTarget::Address(..)) {
+        bail!("--from-list takes the naddr of a NIP-51 set, got {}", naddr);
+    }
+    client::add_relay_hints(nostr_client, target.relay_hints()).await;
+    let Some(event) = client::fetch_event_target(nostr_client, &target).await? else {
+        bail!("list not found: {}", naddr);
+    };
+    let items = if event.pubkey == keys.public_key() {
+        list::list_items(keys, &event)?
+    } else {
+        ListItems {
+            public: event.tags.iter().cloned().collect(),
+            private: Vec::new(),
+        }
+    };
+    Ok(items
+        .members()
+        .into_iter()
+        .map(|(pubkey, _)| (pubkey, None))
+        .collect())
+}
+
+/// Apply one edit to our kind:3 as a single update: show how many follows it
+/// adds and drops, ask, then publish it through the drop guard.
+async fn bulk_update(
+    nostr_client: &Client,
+    keys: &Keys,
+    config: &NostaroConfig,
+    edit: impl FnOnce(&mut Vec<Tag>) -> Result<()>,
+    force: bool,
+    yes: bool,
+) -> Result<()> {
+    let cache = CacheDb::open().ok();
+    let (current, newest) = own_contact_list(nostr_client, keys, cache.as_ref()).await?;
+    let mut tags: Vec<Tag> = current
+        .as_ref()
+        .map(|event| event.tags.iter().cloned().collect())
+        .unwrap_or_default();
+    edit(&mut tags)?;
+
+    let event = nostr_client
+        .sign_event_builder(client::contact_list(current.as_ref(), tags))
+        .await?;
+    let (added, removed) = client::contact_list_diff(current.as_ref(), &event);
+    if added.is_empty() && removed.is_empty() {
+        println!("Nothing to change: your follow list already matches.");
+        return Ok(());
+    }
+    println!(
+        "Follow list: {} -> {} follow(s), {} added, {} removed",
+        current
+            .as_ref()
+            .map(client::contact_pubkeys)
+            .unwrap_or_default()
+            .len(),
+        client::contact_pubkeys(&event).len(),
+        added.len(),
+        removed.len()
+    );
+
+    // Refuse before asking, not after a yes that was never going to count.
+    if let Some(previous) = &newest {
+        check_drop(previous, &event, drop_limit(config, force))?;
+    }
+    if !yes && !client::is_dry_run() && !confirm("Publish this follow list?")? {
+        println!("Not published.");
+        return Ok(());
+    }
+
+    publish_contacts(
+        nostr_client,
+        &event,
+        newest.as_ref(),
+        drop_limit(config, force),
+        cache.as_ref(),
+    )
+    .await?;
+    client::report_published(&format!(
+        "Follow list updated: {} added, {} removed.",
+        added.len(),
+        removed.len()
+    ));
+    Ok(())
+}
+
+/// `follow --file`, `--copy-from` or `--from-list`: follow everyone the
+/// source names in one kind:3 update, instead of one republish per user.
+/// Existing follows, and our own pubkey, are left as they are.
+pub async fn follow_many(source: &FollowSource, force: bool, yes: bool) -> Result<()> {
+    let config = NostaroConfig::load()?;
+    let keys = keys::keys_from_config(&config)?;
+    let nostr_client = client::create_client(&keys, &config).await?;
+
+    let users = match source {
+        FollowSource::File(path) => read_file(path).await?,
+        FollowSource::CopyFrom(input) => read_follows_of(&nostr_client, input).await?,
+        FollowSource::FromList(naddr) => read_set(&nostr_client, &keys, naddr).await?,
+    };
+    println!("{} user(s) to follow.", users.len());
+
+    let own = keys.public_key();
+    let result = bulk_update(
+        &nostr_client,
+        &keys,
+        &config,
+        |tags| {
+            for (pubkey, relay) in &users {
+                let followed = tags.iter().any(|tag| is_follow_of(tag, pubkey));
+                if *pubkey != own && !followed {
+                    add_follow(tags, pubkey, relay.as_deref(), None)?;
+                }
+            }
+            Ok(())
+        },
+        force,
+        yes,
+    )
+    .await;
+
+    nostr_client.disconnect().await;
+    result
+}
+
+/// `unfollow --file`: drop everyone the file names in one kind:3 update.
+pub async fn unfollow_many(path: &Path, force: bool, yes: bool) -> Result<()> {
+    let users = read_file(path).await?;
+    let config = NostaroConfig::load()?;
+    let keys = keys::keys_from_config(&config)?;
+    let nostr_client = client::create_client(&keys, &config).await?;
+    println!("{} user(s) to unfollow.", users.len());
+
+    let result = bulk_update(
+        &nostr_client,
+        &keys,
+        &config,
+        |tags| {
+            for (pubkey, _) in &users {
+                remove_follow(tags, pubkey);
+            }
+            Ok(())
+        },
+        force,
+        yes,
+    )
+    .await;
+
+    nostr_client.disconnect().await;
+    result
+}
+
+/// Print the follows `to` adds to and drops from `from`.
+fn print_changes(from: Option<&Event>, to: &Event) -> Result<()> {
+    let (added, removed) = client::contact_list_diff(from, to);
+    println!("  +{} -{}", added.len(), removed.len());
+    for pubkey in &added {
+        println!("  + {}", pubkey.to_bech32()?);
+    }
+    for pubkey in &removed {
+        println!("  - {}", pubkey.to_bech32()?);
+    }
+    Ok(())
+}
+
+/// Record the kind:3 the relays have now, if they can be reached, and load
+/// every recorded version.
+async fn load_versions(keys: &Keys, config: &NostaroConfig, cache: &CacheDb) -> Result<Vec<Event>> {
+    let nostr_client = client::create_client(keys, config).await?;
+    match client::fetch_contact_list(&nostr_client, &keys.public_key()).await {
+        Ok(Some(event)) => snapshot_event(cache, &event),
+        Ok(None) => {}
+        Err(e) => eprintln!("Warning: could not fetch the current follow list: {}", e),
+    }
+    nostr_client.disconnect().await;
+    versions(cache, &keys.public_key(), Kind::ContactList)
+}
+
+/// `follow history`: every version of our kind:3 nostaro has seen or
+/// published, with its size and what changed since the one before.
+pub async fn history() -> Result<()> {
+    let config = NostaroConfig::load()?;
+    let keys = keys::keys_from_config(&config)?;
+    let cache = CacheDb::open()?;
+    let versions = load_versions(&keys, &config, &cache).await?;
+
+    if versions.is_empty() {
+        println!("No follow list versions recorded yet.");
+        return Ok(());
+    }
+
+    println!(
+        "Follow list history ({} version(s), oldest first):\n",
+        versions.len()
+    );
+    let mut previous: Option<&Event> = None;
+    for (index, event) in versions.iter().enumerate() {
+        let (added, removed) = client::contact_list_diff(previous, event);
+        let latest = if index + 1 == versions.len() {
+            " (latest)"
+        } else {
+            ""
+        };
+        println!(
+            "v{}  {}  {}  {} follow(s)  +{} -{}{}",
+            index + 1,
+            format_time(event.created_at),
+            event.id.to_bech32()?,
+            client::contact_pubkeys(event).len(),
+            added.len(),
+            removed.len(),
+            latest
+        );
+        previous = Some(event);
+    }
+    println!("\nSee `nostaro follow diff <from> [<to>]` for who was added and dropped.");
+    Ok(())
+}
 
-    client::publish_contact_list(&nostr_client, &contacts).await?;
-    println!("Unfollowed {}", pubkey.to_bech32()?);
+/// `follow diff [<from>] [<to>]`: who was added and dropped between two
+/// recorded versions. `to` defaults to the latest and `from` to the one
+/// before it, so with no arguments this is the last change.
+pub async fn diff(from: Option<&str>, to: Option<&str>) -> Result<()> {
+    let config = NostaroConfig::load()?;
+    let keys = keys::keys_from_config(&config)?;
+    let cache = CacheDb::open()?;
+    let versions = load_versions(&keys, &config, &cache).await?;
+
+    let (to_number, to_event) = match to {
+        Some(version) => find_version(&versions, version)?,
+        None => match versions.last() {
+            Some(event) => (versions.len(), event),
+            None => bail!("no follow list versions recorded yet"),
+        },
+    };
+    let (from_number, from_event) = match from {
+        Some(version) => find_version(&versions, version)?,
+        None if versions.len() >= 2 => (versions.len() - 1, &versions[versions.len() - 2]),
+        None => bail!("only one follow list version is recorded; there is nothing to compare"),
+    };
+
+    println!(
+        "v{} ({}) -> v{} ({}):",
+        from_number,
+        format_time(from_event.created_at),
+        to_number,
+        format_time(to_event.created_at)
+    );
+    print_changes(Some(from_event), to_event)
+}
+
+/// `follow restore <version>`: republish a recorded follow list as a new
+/// kind:3, tags and content as they were, after showing what it changes.
+///
+/// The drop guard does not apply — restoring a smaller list is the point —
+/// so the changes are always shown and confirmed instead.
+pub async fn restore(version: &str, yes: bool) -> Result<()> {
+    let config = NostaroConfig::load()?;
+    let keys = keys::keys_from_config(&config)?;
+    let cache = CacheDb::open()?;
+    let nostr_client = client::create_client(&keys, &config).await?;
+
+    let current = client::fetch_contact_list(&nostr_client, &keys.public_key())
+        .await
+        .context("could not fetch your current follow list (kind:3) to compare with")?;
+    if let Some(event) = &current {
+        snapshot_event(&cache, event);
+    }
+
+    let versions = versions(&cache, &keys.public_key(), Kind::ContactList)?;
+    let (number, chosen) = find_version(&versions, version)?;
+
+    if current.as_ref().map(|event| event.id) == Some(chosen.id) {
+        println!("Version {} is already the current follow list.", number);
+        nostr_client.disconnect().await;
+        return Ok(());
+    }
+    println!(
+        "Restoring version {} ({}, {} follow(s)):",
+        number,
+        format_time(chosen.created_at),
+        client::contact_pubkeys(chosen).len()
+    );
+    print_changes(current.as_ref(), chosen)?;
+
+    if !yes && !client::is_dry_run() && !confirm("Publish this follow list?")? {
+        println!("Not published.");
+        nostr_client.disconnect().await;
+        return Ok(());
+    }
+
+    let builder = client::contact_list(Some(chosen), chosen.tags.iter().cloned().collect());
+    let event = nostr_client.sign_event_builder(builder).await?;
+    publish_contacts(&nostr_client, &event, None, 1.0, Some(&cache)).await?;
+    client::report_published(&format!("Follow list restored to version {}.", number));
 
     nostr_client.disconnect().await;
     Ok(())
@@ -128,11 +694,24 @@ pub async fn following(npub_str: Option<&str>) -> Result<()> {
     let nostr_client = client::create_client(&keys, &config).await?;
 
     let target_pubkey = match npub_str {
-        Some(s) => resolve_pubkey(s)?,
+        Some(s) => {
+            let (pubkey, hints) = resolve_pubkey_with_hints(s).await?;
+            client::add_relay_hints(&nostr_client, &hints).await;
+            pubkey
+        }
         None => keys.public_key(),
     };
 
-    let contacts = client::fetch_contacts(&nostr_client, &target_pubkey).await?;
+    let contact_list = client::fetch_contact_list(&nostr_client, &target_pubkey).await?;
+    if let (Some(event), true) = (&contact_list, target_pubkey == keys.public_key()) {
+        if let Ok(cache) = CacheDb::open() {
+            snapshot_event(&cache, event);
+        }
+    }
+    let contacts = contact_list
+        .as_ref()
+        .map(client::contact_pubkeys)
+        .unwrap_or_default();
 
     if contacts.is_empty() {
         if npub_str.is_some() {
@@ -160,7 +739,11 @@ pub async fn followers(npub_str: Option<&str>) -> Result<()> {
     let nostr_client = client::create_client(&keys, &config).await?;
 
     let target_pubkey = match npub_str {
-        Some(s) => resolve_pubkey(s)?,
+        Some(s) => {
+            let (pubkey, hints) = resolve_pubkey_with_hints(s).await?;
+            client::add_relay_hints(&nostr_client, &hints).await;
+            pubkey
+        }
         None => keys.public_key(),
     };
 
@@ -193,6 +776,147 @@ mod tests {
         (0..3).map(|_| Keys::generate().public_key()).collect()
     }
 
+    fn contact_list_at(keys: &Keys, follows: &[PublicKey], at: u64) -> Event {
+        let tags = follows
+            .iter()
+            .map(|pubkey| Tag::public_key(*pubkey))
+            .collect();
+        client::contact_list(None, tags)
+            .custom_created_at(Timestamp::from(at))
+            .sign_with_keys(keys)
+            .unwrap()
+    }
+
+    #[test]
+    fn the_newest_known_list_wins_over_a_stale_fetch() {
+        let keys = Keys::generate();
+        let follows = sample_pubkeys();
+        let recorded = vec![
+            contact_list_at(&keys, &follows[..1], 100),
+            contact_list_at(&keys, &follows, 300),
+        ];
+        let stale = contact_list_at(&keys, &[], 200);
+
+        assert_eq!(
+            newest_known(Some(&stale), &recorded).unwrap().id,
+            recorded[1].id
+        );
+        assert_eq!(newest_known(None, &recorded).unwrap().id, recorded[1].id);
+        let fresh = contact_list_at(&keys, &follows[..2], 400);
+        assert_eq!(newest_known(Some(&fresh), &recorded).unwrap().id, fresh.id);
+        assert!(newest_known(None, &[]).is_none());
+    }
+
+    #[test]
+    fn dropping_more_than_the_limit_is_refused() {
+        let keys = Keys::generate();
+        let follows: Vec<PublicKey> = (0..20).map(|_| Keys::generate().public_key()).collect();
+        let previous = contact_list_at(&keys, &follows, 100);
+
+        // Two of twenty is within 10%; three is not.
+        let two_fewer = contact_list_at(&keys, &follows[2..], 200);
+        assert!(check_drop(&previous, &two_fewer, 0.1).is_ok());
+        let three_fewer = contact_list_at(&keys, &follows[3..], 200);
+        let error = check_drop(&previous, &three_fewer, 0.1).unwrap_err();
+        assert!(error.to_string().contains("drops 3 of the 20"), "{}", error);
+
+        // An empty list from a relay hiccup is the case this exists for.
+        let empty = contact_list_at(&keys, &follows[..0], 200);
+        assert!(check_drop(&previous, &empty, 0.1).is_err());
+        assert!(check_drop(&previous, &empty, 1.0).is_ok());
+
+        // Growing the list, or dropping a single follow, always passes.
+        let more = contact_list_at(&keys, &[follows.clone(), sample_pubkeys()].concat(), 200);
+        assert!(check_drop(&previous, &more, 0.0).is_ok());
+        let short = contact_list_at(&keys, &follows[..2], 100);
+        let one_left = contact_list_at(&keys, &follows[..1], 200);
+        assert!(check_drop(&short, &one_left, 0.1).is_ok());
+    }
+
+    fn strings(fields: &[&str]) -> Vec<String> {
+        fields.iter().map(|field| field.to_string()).collect()
+    }
+
+    fn tag(fields: &[&str]) -> Tag {
+        Tag::parse(strings(fields)).unwrap()
+    }
+
+    fn fields(tags: &[Tag]) -> Vec<Vec<String>> {
+        tags.iter().map(|tag| tag.as_slice().to_vec()).collect()
+    }
+
+    #[test]
+    fn following_edits_the_tag_list_in_place() {
+        let [alice, bob, carol] = [(); 3].map(|_| Keys::generate().public_key());
+        let mut tags = vec![
+            tag(&["p", &alice.to_hex(), "wss://a.example", "alice"]),
+            tag(&["p", &bob.to_hex(), "", "bob"]),
+        ];
+        let before = fields(&tags);
+
+        assert!(!add_follow(&mut tags, &alice, None, None).unwrap());
+        assert!(!add_follow(&mut tags, &bob, None, Some("bob")).unwrap());
+        assert_eq!(fields(&tags), before, "nothing to change, nothing changed");
+
+        assert!(add_follow(&mut tags, &carol, None, Some("carol")).unwrap());
+        assert_eq!(fields(&tags)[..2], before[..]);
+        assert_eq!(
+            fields(&tags)[2],
+            strings(&["p", &carol.to_hex(), "", "carol"])
+        );
+
+        assert!(add_follow(&mut tags, &bob, Some("wss://b.example"), None).unwrap());
+        assert_eq!(
+            fields(&tags)[1],
+            strings(&["p", &bob.to_hex(), "wss://b.example", "bob"]),
+            "the petname survives a new relay"
+        );
+
+        let dave = Keys::generate().public_key();
+        assert!(add_follow(&mut tags, &dave, None, None).unwrap());
+        assert_eq!(fields(&tags)[3], vec!["p".to_string(), dave.to_hex()]);
+    }
+
+    #[test]
+    fn unfollowing_leaves_the_other_tags_alone() {
+        let [alice, bob] = [(); 2].map(|_| Keys::generate().public_key());
+        let mut tags = vec![
+            tag(&["p", &alice.to_hex(), "wss://a.example", "alice"]),
+            tag(&["p", &bob.to_hex()]),
+            tag(&["p", &bob.to_hex(), "", "bob again"]),
+        ];
+
+        assert!(remove_follow(&mut tags, &bob));
+        assert_eq!(
+            fields(&tags),
+            vec![strings(&["p", &alice.to_hex(), "wss://a.example", "alice"])]
+        );
+        assert!(!remove_follow(&mut tags, &bob));
+    }
+
+    #[test]
+    fn a_rewritten_list_keeps_its_content() {
+        let keys = Keys::generate();
+        let relays = r#"{"wss://a.example":{"read":true,"write":true}}"#;
+        let current = EventBuilder::new(Kind::ContactList, relays)
+            .sign_with_keys(&keys)
+            .unwrap();
+        let edited = client::contact_list(Some(&current), Vec::new())
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(edited.content, relays);
+    }
+
+    #[test]
+    fn a_follow_file_skips_comments_blanks_and_repeats() {
+        let text = "# bots\nnpub1a\n\n  bob@example.com  # Bob\nnpub1a\nabcdef\n";
+        assert_eq!(
+            file_entries(text).unwrap(),
+            vec!["npub1a", "bob@example.com", "abcdef"]
+        );
+        assert!(file_entries("# nobody\n\n").is_err());
+    }
+
     #[test]
     fn describe_yields_npub_and_hex_only() {
         let pubkeys = sample_pubkeys();
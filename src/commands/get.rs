@@ -1,42 +1,234 @@
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
 use nostr_sdk::prelude::*;
-use std::time::Duration;
+use std::collections::{BTreeMap, HashSet};
 
+use crate::cache::CacheDb;
 use crate::client;
 use crate::config::NostaroConfig;
 use crate::keys;
+use crate::outln;
+use crate::output;
+use crate::utils::{parse_event_target, EventTarget};
+
+use super::{thread, timeline};
+
+/// What the network says about one event: who reacted how, who reposted,
+/// quoted or replied to it, and what it was zapped.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Summary {
+    /// Reaction counts by emoji (`+` for an empty or `+` reaction), most
+    /// frequent first.
+    pub reactions: Vec<(String, usize)>,
+    pub reposts: usize,
+    pub quotes: usize,
+    /// Replies whose NIP-10 parent is the event itself, not the whole thread.
+    pub replies: usize,
+    pub zaps: usize,
+    pub zap_msats: u64,
+}
+
+impl Summary {
+    pub fn reaction_total(&self) -> usize {
+        self.reactions.iter().map(|(_, count)| count).sum()
+    }
+}
+
+/// Whether `related` is about `target`: its last `e` tag (NIP-25/NIP-18/NIP-57),
+/// or, for an addressable target, its `a` tag.
+fn is_about(related: &Event, target: &Event, coordinate: Option<&str>) -> bool {
+    if thread::target_id(related) == Some(target.id) {
+        return true;
+    }
+    coordinate.is_some_and(|coordinate| {
+        related.tags.iter().any(|tag| match tag.as_slice() {
+            [name, value, ..] => name == "a" && value == coordinate,
+            _ => false,
+        })
+    })
+}
+
+/// The `kind:pubkey:d` address of an addressable event.
+fn coordinate_of(event: &Event) -> Option<String> {
+    (30000..40000).contains(&event.kind.as_u16()).then(|| {
+        format!(
+            "{}:{}:{}",
+            event.kind.as_u16(),
+            event.pubkey.to_hex(),
+            super::article::tag_value(event, "d").unwrap_or_default()
+        )
+    })
+}
+
+/// Count what `related` says about `target`. Duplicates count once, and
+/// events that only mention the target in passing are ignored.
+///
+/// Pure — it counts events that have already been fetched.
+pub fn summarize(target: &Event, related: &[Event]) -> Summary {
+    let coordinate = coordinate_of(target);
+    let coordinate = coordinate.as_deref();
+    let mut seen: HashSet<EventId> = HashSet::new();
+    let mut reactions: BTreeMap<String, usize> = BTreeMap::new();
+    let mut summary = Summary::default();
+
+    for event in related {
+        if event.id == target.id || !seen.insert(event.id) {
+            continue;
+        }
+        let kind = event.kind.as_u16();
+        if event.kind == Kind::Reaction && is_about(event, target, coordinate) {
+            *reactions
+                .entry(timeline::reaction_emoji(event))
+                .or_default() += 1;
+        } else if (event.kind == Kind::Repost || kind == client::GENERIC_REPOST_KIND)
+            && is_about(event, target, coordinate)
+        {
+            summary.reposts += 1;
+        } else if kind == 9735 && is_about(event, target, coordinate) {
+            summary.zaps += 1;
+            summary.zap_msats += thread::zap_msats(event).unwrap_or(0);
+        } else if event.kind == Kind::TextNote {
+            let quotes = event.tags.iter().any(|tag| match tag.as_slice() {
+                [name, value, ..] => {
+                    name == "q"
+                        && (*value == target.id.to_hex() || Some(value.as_str()) == coordinate)
+                }
+                _ => false,
+            });
+            if quotes {
+                summary.quotes += 1;
+            } else if thread::parent_id(event) == Some(target.id) {
+                summary.replies += 1;
+            }
+        }
+    }
+
+    summary.reactions = reactions.into_iter().collect();
+    // Stable: ties keep the emoji order of the BTreeMap.
+    summary.reactions.sort_by_key(|reaction| std::cmp::Reverse(reaction.1));
+    summary
+}
+
+/// Everything that points at `target`: reactions, reposts, zap receipts and
+/// notes (replies and quotes), by `e`, by `q`, and for an addressable event
+/// by `a` — paged, so a popular note is not cut at one relay page.
+async fn fetch_related(nostr_client: &Client, target: &Event) -> Result<Vec<Event>> {
+    let kinds = [
+        Kind::TextNote,
+        Kind::Reaction,
+        Kind::Repost,
+        Kind::from(client::GENERIC_REPOST_KIND),
+        Kind::from(9735u16),
+    ];
+    let mut filters = vec![
+        Filter::new().kinds(kinds).event(target.id),
+        Filter::new()
+            .kind(Kind::TextNote)
+            .custom_tag(SingleLetterTag::lowercase(Alphabet::Q), target.id.to_hex()),
+    ];
+    if let Some(coordinate) = coordinate_of(target) {
+        filters.push(
+            Filter::new()
+                .kinds(kinds)
+                .custom_tag(SingleLetterTag::lowercase(Alphabet::A), coordinate),
+        );
+    }
+
+    let mut related = Vec::new();
+    for filter in filters {
+        related.extend(thread::fetch_all_pages(nostr_client, filter).await?);
+    }
+    Ok(related)
+}
+
+/// The `--out-format json` document:
+/// `{event, author: {npub, name}, reactions: {total, by_emoji}, reposts,
+/// quotes, replies, zaps, zap_sats}`.
+///
+/// Pure — it renders what has already been fetched; the cache is only read.
+fn to_json(event: &Event, summary: &Summary, cache: Option<&CacheDb>) -> Result<serde_json::Value> {
+    let by_emoji: serde_json::Map<String, serde_json::Value> = summary
+        .reactions
+        .iter()
+        .map(|(emoji, count)| (emoji.clone(), serde_json::json!(count)))
+        .collect();
+    Ok(serde_json::json!({
+        "event": event,
+        "author": {
+            "npub": event.pubkey.to_bech32()?,
+            "name": thread::cached_name(cache, &event.pubkey),
+        },
+        "reactions": {
+            "total": summary.reaction_total(),
+            "by_emoji": by_emoji,
+        },
+        "reposts": summary.reposts,
+        "quotes": summary.quotes,
+        "replies": summary.replies,
+        "zaps": summary.zaps,
+        "zap_sats": summary.zap_msats / 1000,
+    }))
+}
+
+/// `get <id|note|nevent|naddr>`: one event and what the network did with it.
+/// `raw` prints the signed event exactly as received and nothing else.
+pub async fn run(event_id_str: &str, raw: bool) -> Result<()> {
+    let target = parse_event_target(event_id_str)?;
 
-pub async fn run(event_id_str: &str) -> Result<()> {
     let config = NostaroConfig::load()?;
     let keys = keys::keys_from_config(&config)?;
     let nostr_client = client::create_client(&keys, &config).await?;
 
-    // Parse event ID: hex, note1 bech32, or nevent1 bech32
-    let (event_id, relay_hints) = if event_id_str.starts_with("nevent1") {
-        let nip19_event = Nip19Event::from_bech32(event_id_str)?;
-        (nip19_event.event_id, nip19_event.relays)
-    } else {
-        let id = EventId::parse(event_id_str).or_else(|_| EventId::from_bech32(event_id_str))?;
-        (id, vec![])
-    };
-
     // Add relay hints if present
-    for relay in &relay_hints {
+    let relay_hints = target.relay_hints();
+    for relay in relay_hints {
         let _ = nostr_client.add_relay(relay).await;
     }
     if !relay_hints.is_empty() {
         nostr_client.connect().await;
     }
 
-    // Fetch the event
-    let event = client::fetch_event_by_id(&nostr_client, &event_id)
+    // Fetch the event (the newest version, for an naddr)
+    let event = client::fetch_event_target(&nostr_client, &target)
         .await?
         .ok_or_else(|| anyhow!("Event not found: {}", event_id_str))?;
 
+    if raw {
+        if output::is_json() {
+            output::write_json(&serde_json::to_value(&event)?)?;
+        } else {
+            output::open_body()?;
+            outln!("{}", event.as_json())?;
+        }
+        nostr_client.disconnect().await;
+        return Ok(());
+    }
+
+    let related = fetch_related(&nostr_client, &event).await?;
+    let summary = summarize(&event, &related);
+
+    let cache = CacheDb::open().ok();
+    if let Some(cache) = cache.as_ref() {
+        let _ = timeline::fetch_and_cache_profiles(&nostr_client, vec![event.pubkey], cache).await;
+    }
+
+    if output::is_json() {
+        output::write_json(&to_json(&event, &summary, cache.as_ref())?)?;
+        println!("Event {}", event.id.to_hex());
+        nostr_client.disconnect().await;
+        return Ok(());
+    }
+
     // Display event details
     println!("Event ID:    {}", event.id.to_hex());
-    println!("Author:      {}", event.pubkey.to_bech32()?);
+    if let EventTarget::Address(..) = target {
+        println!("Address:     {}", coordinate_of(&event).unwrap_or_default());
+    }
+    let npub = event.pubkey.to_bech32()?;
+    match thread::cached_name(cache.as_ref(), &event.pubkey) {
+        Some(name) => println!("Author:      {} ({})", name, npub),
+        None => println!("Author:      {}", npub),
+    }
 
     let timestamp = event.created_at.as_u64() as i64;
     let datetime = DateTime::<Utc>::from_timestamp(timestamp, 0)
@@ -47,17 +239,122 @@ pub async fn run(event_id_str: &str) -> Result<()> {
     println!("Kind:        {}", event.kind.as_u16());
     println!("Content:     {}", event.content);
 
-    // Fetch reactions
-    let reaction_filter = Filter::new()
-        .kind(Kind::Reaction)
-        .event(event_id)
-        .limit(100);
-    let reactions = nostr_client
-        .fetch_events(reaction_filter, Duration::from_secs(10))
-        .await?;
-    let reaction_count = reactions.into_iter().count();
-    println!("Reactions:   {}", reaction_count);
+    let breakdown: Vec<String> = summary
+        .reactions
+        .iter()
+        .map(|(emoji, count)| format!("{} x{}", emoji, count))
+        .collect();
+    if breakdown.is_empty() {
+        println!("Reactions:   0");
+    } else {
+        println!(
+            "Reactions:   {} ({})",
+            summary.reaction_total(),
+            breakdown.join(", ")
+        );
+    }
+    println!("Replies:     {}", summary.replies);
+    println!("Reposts:     {}", summary.reposts);
+    println!("Quotes:      {}", summary.quotes);
+    println!(
+        "Zaps:        {} ({} sats)",
+        summary.zaps,
+        thread::format_sats(summary.zap_msats)
+    );
 
     nostr_client.disconnect().await;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(keys: &Keys, content: &str, tags: Vec<Tag>) -> Event {
+        EventBuilder::text_note(content)
+            .tags(tags)
+            .sign_with_keys(keys)
+            .unwrap()
+    }
+
+    #[test]
+    fn everything_pointing_at_the_note_is_counted_once() {
+        let author = Keys::generate();
+        let fan = Keys::generate();
+        let target = note(&author, "gm", vec![]);
+        let e = |id: EventId| Tag::event(id);
+
+        let reaction = |content: &str| {
+            EventBuilder::new(Kind::Reaction, content)
+                .tags([e(target.id), Tag::public_key(author.public_key())])
+                .sign_with_keys(&Keys::generate())
+                .unwrap()
+        };
+        let plus = reaction("+");
+        let reply = note(
+            &fan,
+            "gm!",
+            vec![Tag::parse(["e", target.id.to_hex().as_str(), "", "root"]).unwrap()],
+        );
+        let reply_to_reply = note(
+            &fan,
+            "again",
+            vec![
+                Tag::parse(["e", target.id.to_hex().as_str(), "", "root"]).unwrap(),
+                Tag::parse(["e", reply.id.to_hex().as_str(), "", "reply"]).unwrap(),
+            ],
+        );
+        let quote = note(
+            &fan,
+            "look",
+            vec![Tag::parse(["q", target.id.to_hex().as_str()]).unwrap()],
+        );
+        let repost = client::repost_builder(&target, None)
+            .unwrap()
+            .sign_with_keys(&fan)
+            .unwrap();
+
+        let related = vec![
+            plus.clone(),
+            plus,
+            reaction(""),
+            reaction("🤙"),
+            reply,
+            reply_to_reply,
+            quote,
+            repost,
+        ];
+        let summary = summarize(&target, &related);
+
+        assert_eq!(
+            summary.reactions,
+            vec![("+".to_string(), 2), ("🤙".to_string(), 1)]
+        );
+        assert_eq!(summary.reaction_total(), 3);
+        assert_eq!(summary.replies, 1, "only direct replies count");
+        assert_eq!(summary.quotes, 1);
+        assert_eq!(summary.reposts, 1);
+        assert_eq!(summary.zaps, 0);
+    }
+
+    #[test]
+    fn reactions_to_an_article_count_by_address() {
+        let author = Keys::generate();
+        let article = EventBuilder::new(Kind::from(30023u16), "# hi")
+            .tags([Tag::identifier("hello")])
+            .sign_with_keys(&author)
+            .unwrap();
+        let coordinate = format!("30023:{}:hello", author.public_key().to_hex());
+        // Reacted to an earlier version: the e tag is stale, the a tag is not.
+        let reaction = EventBuilder::new(Kind::Reaction, "🔥")
+            .tags([
+                Tag::event(EventId::all_zeros()),
+                Tag::parse(["a", coordinate.as_str()]).unwrap(),
+            ])
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+
+        let summary = summarize(&article, &[reaction]);
+        assert_eq!(summary.reactions, vec![("🔥".to_string(), 1)]);
+    }
+}
@@ -0,0 +1,475 @@
+//! NIP-51 follow sets (kind:30000): named groups of people, kept apart from
+//! the kind:3 follow list, that `timeline --list` can read.
+//!
+//! A list has public items (its tags) and private ones: a JSON array of tags,
+//! encrypted to ourselves (NIP-44; NIP-04 when reading older lists) in the
+//! event content. Both halves are members; only we can see the private one.
+
+use anyhow::{anyhow, bail, Context, Result};
+use nostr_sdk::prelude::*;
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::cache::CacheDb;
+use crate::client;
+use crate::config::NostaroConfig;
+use crate::keys;
+use crate::utils::{resolve_pubkey, resolve_pubkeys};
+
+use super::{article, thread, timeline};
+
+/// A follow set (NIP-51 addressable set).
+pub const FOLLOW_SET_KIND: u16 = 30000;
+
+/// The items of a NIP-51 list: its public tags (without the `d` tag, which
+/// is the list's name) and the private tags from its encrypted content.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ListItems {
+    pub public: Vec<Tag>,
+    pub private: Vec<Tag>,
+}
+
+/// Decrypt the private half of a list we wrote. NIP-51 moved from NIP-04 to
+/// NIP-44; a NIP-04 payload is recognised by its `?iv=` suffix.
+pub(crate) fn decrypt_private(keys: &Keys, event: &Event) -> Result<Vec<Tag>> {
+    if event.content.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let json = if event.content.contains("?iv=") {
+        nip04::decrypt(keys.secret_key(), &keys.public_key(), &event.content)?
+    } else {
+        nip44::decrypt(keys.secret_key(), &keys.public_key(), &event.content)?
+    };
+    let items: Vec<Vec<String>> =
+        serde_json::from_str(&json).context("the private items are not a JSON array of tags")?;
+    items
+        .into_iter()
+        .map(|item| Tag::parse(item).map_err(Into::into))
+        .collect()
+}
+
+/// Encrypt private items to ourselves (NIP-44). No private items is an empty
+/// content, not an encrypted empty array.
+pub(crate) fn encrypt_private(keys: &Keys, private: &[Tag]) -> Result<String> {
+    if private.is_empty() {
+        return Ok(String::new());
+    }
+    let items: Vec<Vec<String>> = private.iter().map(|tag| tag.clone().to_vec()).collect();
+    Ok(nip44::encrypt(
+        keys.secret_key(),
+        &keys.public_key(),
+        serde_json::to_string(&items)?,
+        nip44::Version::V2,
+    )?)
+}
+
+/// Split a list event into its items. The `d` tag is left out; it comes back
+/// from [`list_builder`].
+pub(crate) fn list_items(keys: &Keys, event: &Event) -> Result<ListItems> {
+    Ok(ListItems {
+        public: event
+            .tags
+            .iter()
+            .filter(|tag| !matches!(tag.as_slice(), [name, ..] if name == "d"))
+            .cloned()
+            .collect(),
+        private: decrypt_private(keys, event)?,
+    })
+}
+
+fn pubkeys_in(tags: &[Tag]) -> Vec<PublicKey> {
+    tags.iter()
+        .filter_map(|tag| match tag.as_slice() {
+            [name, value, ..] if name == "p" => PublicKey::from_hex(value).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+impl ListItems {
+    /// Every member once, public ones first, with whether it is private.
+    pub fn members(&self) -> Vec<(PublicKey, bool)> {
+        let mut seen = HashSet::new();
+        pubkeys_in(&self.public)
+            .into_iter()
+            .map(|pk| (pk, false))
+            .chain(pubkeys_in(&self.private).into_iter().map(|pk| (pk, true)))
+            .filter(|(pk, _)| seen.insert(*pk))
+            .collect()
+    }
+
+    /// Add `pubkeys` to the public or private half, skipping anyone already in
+    /// either. Returns who was added.
+    ///
+    /// Pure — it edits the items in memory.
+    pub fn add(&mut self, pubkeys: &[PublicKey], private: bool) -> Vec<PublicKey> {
+        let tags: Vec<Tag> = pubkeys.iter().map(|pk| Tag::public_key(*pk)).collect();
+        pubkeys_in(&self.add_tags(&tags, private))
+    }
+
+    /// Remove `pubkeys` from both halves. Returns who was removed.
+    ///
+    /// Pure — it edits the items in memory.
+    pub fn remove(&mut self, pubkeys: &[PublicKey]) -> Vec<PublicKey> {
+        let tags: Vec<Tag> = pubkeys.iter().map(|pk| Tag::public_key(*pk)).collect();
+        pubkeys_in(&self.remove_tags(&tags))
+    }
+
+    /// Add items to the public or private half, skipping any whose name and
+    /// value are already in either. Returns what was added.
+    ///
+    /// Pure — it edits the items in memory.
+    pub fn add_tags(&mut self, tags: &[Tag], private: bool) -> Vec<Tag> {
+        let mut present: HashSet<(String, String)> = self
+            .public
+            .iter()
+            .chain(&self.private)
+            .filter_map(item_key)
+            .collect();
+        let added: Vec<Tag> = tags
+            .iter()
+            .filter(|tag| item_key(tag).is_some_and(|key| present.insert(key)))
+            .cloned()
+            .collect();
+        let half = if private {
+            &mut self.private
+        } else {
+            &mut self.public
+        };
+        half.extend(added.iter().cloned());
+        added
+    }
+
+    /// Remove the items matching `tags` by name and value from both halves.
+    /// Returns what was removed, once each.
+    ///
+    /// Pure — it edits the items in memory.
+    pub fn remove_tags(&mut self, tags: &[Tag]) -> Vec<Tag> {
+        let drop: HashSet<(String, String)> = tags.iter().filter_map(item_key).collect();
+        let mut seen = HashSet::new();
+        let mut removed = Vec::new();
+        for half in [&mut self.public, &mut self.private] {
+            half.retain(|tag| match item_key(tag) {
+                Some(key) if drop.contains(&key) => {
+                    if seen.insert(key) {
+                        removed.push(tag.clone());
+                    }
+                    false
+                }
+                _ => true,
+            });
+        }
+        removed
+    }
+}
+
+/// What makes two items the same: their name and first value. A relay hint
+/// or petname after the value does not make a second entry.
+fn item_key(tag: &Tag) -> Option<(String, String)> {
+    match tag.as_slice() {
+        [name, value, ..] => Some((name.clone(), value.clone())),
+        _ => None,
+    }
+}
+
+/// The event for list `name` holding `items`.
+pub(crate) fn list_builder(
+    keys: &Keys,
+    kind: u16,
+    name: Option<&str>,
+    items: &ListItems,
+) -> Result<EventBuilder> {
+    let mut tags = Vec::with_capacity(items.public.len() + 1);
+    if let Some(name) = name {
+        tags.push(Tag::identifier(name));
+    }
+    tags.extend(items.public.iter().cloned());
+    Ok(EventBuilder::new(Kind::from(kind), encrypt_private(keys, &items.private)?).tags(tags))
+}
+
+/// Our newest follow set called `name`, if any.
+async fn fetch_list(
+    nostr_client: &Client,
+    author: &PublicKey,
+    name: &str,
+) -> Result<Option<Event>> {
+    let filter = Filter::new()
+        .kind(Kind::from(FOLLOW_SET_KIND))
+        .author(*author)
+        .identifier(name);
+    let events = nostr_client
+        .fetch_events(filter, Duration::from_secs(10))
+        .await?;
+    Ok(events.into_iter().max_by_key(|e| e.created_at))
+}
+
+async fn fetch_existing(nostr_client: &Client, keys: &Keys, name: &str) -> Result<Event> {
+    fetch_list(nostr_client, &keys.public_key(), name)
+        .await?
+        .ok_or_else(|| {
+            anyhow!(
+                "no list named \"{}\"; create it with `nostaro list create {}`",
+                name,
+                name
+            )
+        })
+}
+
+/// The members of our list `name`, public and private: the author set of
+/// `timeline --list`.
+pub async fn fetch_members(
+    nostr_client: &Client,
+    keys: &Keys,
+    name: &str,
+) -> Result<Vec<PublicKey>> {
+    let event = fetch_existing(nostr_client, keys, name).await?;
+    Ok(list_items(keys, &event)?
+        .members()
+        .into_iter()
+        .map(|(pk, _)| pk)
+        .collect())
+}
+
+/// `list create <name>`: an empty follow set, optionally with a title.
+pub async fn create(name: &str, title: Option<&str>) -> Result<()> {
+    let config = NostaroConfig::load()?;
+    let keys = keys::keys_from_config(&config)?;
+    let nostr_client = client::create_client(&keys, &config).await?;
+
+    if fetch_list(&nostr_client, &keys.public_key(), name)
+        .await?
+        .is_some()
+    {
+        nostr_client.disconnect().await;
+        bail!("a list named \"{}\" already exists", name);
+    }
+
+    let mut items = ListItems::default();
+    if let Some(title) = title {
+        items.public.push(Tag::parse(["title", title])?);
+    }
+    let builder = list_builder(&keys, FOLLOW_SET_KIND, Some(name), &items)?;
+    client::publish(&nostr_client, builder).await?;
+    client::report_published(&format!("List \"{}\" created.", name));
+
+    nostr_client.disconnect().await;
+    Ok(())
+}
+
+/// `list add <name> <pubkey>...`: add people, publicly or `--private`ly.
+pub async fn add(name: &str, pubkeys: &[String], private: bool) -> Result<()> {
+    let pubkeys = resolve_pubkeys(pubkeys).await?;
+    let config = NostaroConfig::load()?;
+    let keys = keys::keys_from_config(&config)?;
+    let nostr_client = client::create_client(&keys, &config).await?;
+
+    let event = fetch_existing(&nostr_client, &keys, name).await?;
+    let mut items = list_items(&keys, &event)?;
+    let added = items.add(&pubkeys, private);
+    if added.is_empty() {
+        println!("Everyone given is already in \"{}\".", name);
+        nostr_client.disconnect().await;
+        return Ok(());
+    }
+
+    let builder = list_builder(&keys, FOLLOW_SET_KIND, Some(name), &items)?;
+    client::publish(&nostr_client, builder).await?;
+    let half = if private { " (private)" } else { "" };
+    client::report_published(&format!("Added {} to \"{}\"{}.", added.len(), name, half));
+
+    nostr_client.disconnect().await;
+    Ok(())
+}
+
+/// `list remove <name> <pubkey>...`: remove people from either half.
+pub async fn remove(name: &str, pubkeys: &[String]) -> Result<()> {
+    let pubkeys = resolve_pubkeys(pubkeys).await?;
+    let config = NostaroConfig::load()?;
+    let keys = keys::keys_from_config(&config)?;
+    let nostr_client = client::create_client(&keys, &config).await?;
+
+    let event = fetch_existing(&nostr_client, &keys, name).await?;
+    let mut items = list_items(&keys, &event)?;
+    let removed = items.remove(&pubkeys);
+    if removed.is_empty() {
+        println!("Nobody given is in \"{}\".", name);
+        nostr_client.disconnect().await;
+        return Ok(());
+    }
+
+    let builder = list_builder(&keys, FOLLOW_SET_KIND, Some(name), &items)?;
+    client::publish(&nostr_client, builder).await?;
+    client::report_published(&format!("Removed {} from \"{}\".", removed.len(), name));
+
+    nostr_client.disconnect().await;
+    Ok(())
+}
+
+/// `list show [name]`: every follow set we have, or the members of one.
+pub async fn show(name: Option<&str>) -> Result<()> {
+    let config = NostaroConfig::load()?;
+    let keys = keys::keys_from_config(&config)?;
+    let nostr_client = client::create_client(&keys, &config).await?;
+
+    let Some(name) = name else {
+        let lists = client::fetch_addressable_events(
+            &nostr_client,
+            Kind::from(FOLLOW_SET_KIND),
+            &keys.public_key(),
+            100,
+        )
+        .await?;
+        if lists.is_empty() {
+            println!("No lists yet. Create one with `nostaro list create <name>`.");
+        }
+        for list in &lists {
+            let name = article::tag_value(list, "d").unwrap_or_default();
+            let members = list_items(&keys, list)?.members();
+            let private = members.iter().filter(|(_, private)| *private).count();
+            let title = article::tag_value(list, "title")
+                .map(|title| format!(" — {}", title))
+                .unwrap_or_default();
+            println!(
+                "{}{} ({} member(s), {} private)",
+                name,
+                title,
+                members.len(),
+                private
+            );
+        }
+        nostr_client.disconnect().await;
+        return Ok(());
+    };
+
+    let event = fetch_existing(&nostr_client, &keys, name).await?;
+    let members = list_items(&keys, &event)?.members();
+
+    let cache = CacheDb::open().ok();
+    if let Some(cache) = cache.as_ref() {
+        let pubkeys = members.iter().map(|(pk, _)| *pk).collect();
+        let _ = timeline::fetch_and_cache_profiles(&nostr_client, pubkeys, cache).await;
+    }
+
+    match article::tag_value(&event, "title") {
+        Some(title) => println!("{} — {} ({} member(s))", name, title, members.len()),
+        None => println!("{} ({} member(s))", name, members.len()),
+    }
+    for (pubkey, private) in &members {
+        let npub = pubkey.to_bech32()?;
+        let label = if *private { "  [private]" } else { "" };
+        match thread::cached_name(cache.as_ref(), pubkey) {
+            Some(name) => println!("  {} ({}){}", name, npub, label),
+            None => println!("  {}{}", npub, label),
+        }
+    }
+
+    nostr_client.disconnect().await;
+    Ok(())
+}
+
+/// Read an `--authors-file`: one pubkey (npub, hex or nprofile) per line;
+/// blank lines and `#` comments are skipped, duplicates are dropped.
+///
+/// Pure — it parses the text it is given.
+pub fn parse_authors(text: &str) -> Result<Vec<PublicKey>> {
+    let mut seen = HashSet::new();
+    let mut authors = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let pubkey =
+            resolve_pubkey(line).with_context(|| format!("line {}: not a pubkey", index + 1))?;
+        if seen.insert(pubkey) {
+            authors.push(pubkey);
+        }
+    }
+    if authors.is_empty() {
+        bail!("the authors file names nobody");
+    }
+    Ok(authors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn private_items_round_trip_and_count_as_members() {
+        let keys = Keys::generate();
+        let [a, b] = [(); 2].map(|_| Keys::generate().public_key());
+        let mut items = ListItems::default();
+        assert_eq!(items.add(&[a], false), vec![a]);
+        assert_eq!(items.add(&[a, b], true), vec![b], "a is already public");
+
+        let event = list_builder(&keys, FOLLOW_SET_KIND, Some("core"), &items)
+            .unwrap()
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert!(
+            !event.content.contains(&b.to_hex()),
+            "private items are encrypted"
+        );
+        assert_eq!(article::tag_value(&event, "d"), Some("core"));
+
+        let read = list_items(&keys, &event).unwrap();
+        assert_eq!(read, items);
+        assert_eq!(read.members(), vec![(a, false), (b, true)]);
+    }
+
+    #[test]
+    fn removal_reaches_both_halves_and_keeps_other_tags() {
+        let [a, b, c] = [(); 3].map(|_| Keys::generate().public_key());
+        let mut items = ListItems {
+            public: vec![
+                Tag::parse(["title", "Core devs"]).unwrap(),
+                Tag::public_key(a),
+            ],
+            private: vec![Tag::public_key(b)],
+        };
+        let mut removed = items.remove(&[a, b, c]);
+        removed.sort();
+        let mut expected = vec![a, b];
+        expected.sort();
+        assert_eq!(removed, expected, "c was never a member");
+        assert_eq!(
+            items.public,
+            vec![Tag::parse(["title", "Core devs"]).unwrap()]
+        );
+        assert!(items.private.is_empty());
+    }
+
+    #[test]
+    fn legacy_nip04_private_items_are_still_read() {
+        let keys = Keys::generate();
+        let member = Keys::generate().public_key();
+        let json = serde_json::to_string(&[["p", &member.to_hex()]]).unwrap();
+        let content = nip04::encrypt(keys.secret_key(), &keys.public_key(), json).unwrap();
+        let event = EventBuilder::new(Kind::from(FOLLOW_SET_KIND), content)
+            .tags([Tag::identifier("old")])
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(
+            list_items(&keys, &event).unwrap().members(),
+            vec![(member, true)]
+        );
+    }
+
+    #[test]
+    fn authors_files_skip_comments_and_duplicates() {
+        let a = Keys::generate().public_key();
+        let b = Keys::generate().public_key();
+        let text = format!(
+            "# customers\n{}\n\n{}  # bob\n{}\n",
+            a.to_bech32().unwrap(),
+            b.to_hex(),
+            a.to_hex()
+        );
+        assert_eq!(parse_authors(&text).unwrap(), vec![a, b]);
+
+        let err = parse_authors("npub1nope\n").unwrap_err();
+        assert!(format!("{:#}", err).contains("line 1"));
+        assert!(parse_authors("# nobody\n").is_err());
+    }
+}
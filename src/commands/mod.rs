@@ -1,11 +1,18 @@
+pub mod article;
 pub mod cache;
 pub mod channel;
 pub mod decode;
 pub mod dm;
+pub mod emoji;
+pub mod embed;
+pub mod encode;
 pub mod event;
 pub mod follow;
 pub mod get;
 pub mod init;
+pub mod list;
+pub mod mute;
+pub mod notifications;
 pub mod post;
 pub mod profile;
 pub mod pubkey;
@@ -14,8 +21,11 @@ pub mod relay;
 pub mod reply;
 pub mod repost;
 pub mod search;
+pub mod snapshot;
+pub mod thread;
 pub mod timeline;
 pub mod upload;
 pub mod vanity;
+pub mod verify;
 pub mod watch;
 pub mod zap;
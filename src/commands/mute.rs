@@ -0,0 +1,440 @@
+//! NIP-51 mute list (kind:10000): people, hashtags, words and threads we do
+//! not want to see.
+//!
+//! The list is edited with `mute add/remove` and read by every command that
+//! shows other people's notes — timeline, search, notifications, thread and
+//! watch — through the one predicate, [`Mutes::is_muted`], so muting means
+//! the same thing everywhere. Items can be public (tags) or private (the
+//! encrypted content, see [`super::list`]).
+
+use anyhow::{anyhow, Result};
+use nostr_sdk::prelude::*;
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::cache::CacheDb;
+use crate::client;
+use crate::config::NostaroConfig;
+use crate::keys;
+use crate::utils::{parse_event_reference, resolve_pubkey, resolve_pubkey_online};
+
+use super::list::{self, ListItems};
+use super::thread;
+
+/// The mute list (NIP-51 standard list, replaceable).
+pub const MUTE_LIST_KIND: u16 = 10000;
+
+/// State key for the last mute list fetched, so a run that cannot reach a
+/// relay (or `search --local`) still hides what was muted.
+const CACHED_MUTE_LIST_KEY: &str = "mute.list";
+
+/// What `mute add/remove` is given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum MuteTarget {
+    /// A person (npub, hex, nprofile, or NIP-05 address): everything they write
+    Pubkey,
+    /// A hashtag (`#` optional): notes tagged with it
+    Hashtag,
+    /// A word or phrase: notes containing it, case-insensitively
+    Word,
+    /// A thread (note ID): the note and every reply to it
+    Thread,
+}
+
+/// The list item for `value`. Hashtags and words are stored lowercased, which
+/// is how they are compared.
+///
+/// Pure — it only parses the value.
+pub fn mute_tag(target: MuteTarget, value: &str) -> Result<Tag> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(anyhow!("nothing to mute"));
+    }
+    Ok(match target {
+        MuteTarget::Pubkey => Tag::public_key(resolve_pubkey(value)?),
+        MuteTarget::Hashtag => Tag::hashtag(value.trim_start_matches('#').to_lowercase()),
+        MuteTarget::Word => Tag::parse(["word", value.to_lowercase().as_str()])?,
+        MuteTarget::Thread => Tag::event(parse_event_reference(value)?.0),
+    })
+}
+
+/// The mute list, ready to test events against. Public and private items are
+/// treated alike.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Mutes {
+    pubkeys: HashSet<PublicKey>,
+    hashtags: HashSet<String>,
+    words: Vec<String>,
+    threads: HashSet<EventId>,
+}
+
+impl Mutes {
+    pub fn from_items(items: &ListItems) -> Self {
+        let mut mutes = Self::default();
+        for tag in items.public.iter().chain(&items.private) {
+            match tag.as_slice() {
+                [name, value, ..] if name == "p" => {
+                    if let Ok(pubkey) = PublicKey::from_hex(value) {
+                        mutes.pubkeys.insert(pubkey);
+                    }
+                }
+                [name, value, ..] if name == "t" => {
+                    mutes.hashtags.insert(value.to_lowercase());
+                }
+                [name, value, ..] if name == "word" && !value.is_empty() => {
+                    mutes.words.push(value.to_lowercase());
+                }
+                [name, value, ..] if name == "e" => {
+                    if let Ok(id) = EventId::from_hex(value) {
+                        mutes.threads.insert(id);
+                    }
+                }
+                _ => {}
+            }
+        }
+        mutes
+    }
+
+    /// Whether `event` should be hidden: written by a muted person (for a zap
+    /// receipt, sent by one — the receipt itself is signed by the wallet),
+    /// tagged with a muted hashtag, containing a muted word, or part of a
+    /// muted thread (the thread's note itself, or anything referencing it).
+    ///
+    /// Pure — the one test every command applies.
+    pub fn is_muted(&self, event: &Event) -> bool {
+        if self.pubkeys.contains(&event.pubkey) {
+            return true;
+        }
+        let tagged = event.tags.iter().any(|tag| match tag.as_slice() {
+            [name, value, ..] if name == "t" => self.hashtags.contains(&value.to_lowercase()),
+            [name, value, ..] if name == "e" => {
+                EventId::from_hex(value).is_ok_and(|id| self.threads.contains(&id))
+            }
+            [name, value, ..] if name == "P" && event.kind == Kind::ZapReceipt => {
+                PublicKey::from_hex(value).is_ok_and(|pk| self.pubkeys.contains(&pk))
+            }
+            _ => false,
+        });
+        if tagged || self.threads.contains(&event.id) {
+            return true;
+        }
+        if self.words.is_empty() {
+            return false;
+        }
+        let content = event.content.to_lowercase();
+        self.words.iter().any(|word| content.contains(word))
+    }
+}
+
+/// Our newest mute list, if any.
+async fn fetch_mute_list(nostr_client: &Client, author: &PublicKey) -> Result<Option<Event>> {
+    let filter = Filter::new()
+        .kind(Kind::from(MUTE_LIST_KIND))
+        .author(*author);
+    let events = nostr_client
+        .fetch_events(filter, Duration::from_secs(10))
+        .await?;
+    Ok(events.into_iter().max_by_key(|e| e.created_at))
+}
+
+fn cached_mute_list(cache: Option<&CacheDb>) -> Option<Event> {
+    let json = cache?.get_state(CACHED_MUTE_LIST_KEY).ok()??;
+    Event::from_json(json).ok()
+}
+
+/// The mute list for filtering. Never fails: a relay that cannot be reached
+/// falls back to the last list fetched, and a list that cannot be read hides
+/// nothing — with a warning on stderr either way, so an unreadable mute list
+/// does not stop anyone reading their timeline.
+pub async fn load(nostr_client: &Client, keys: &Keys) -> Mutes {
+    let cache = CacheDb::open().ok();
+    let event = match fetch_mute_list(nostr_client, &keys.public_key()).await {
+        Ok(Some(event)) => {
+            if let Some(cache) = cache.as_ref() {
+                let _ = cache.set_state(CACHED_MUTE_LIST_KEY, &event.as_json());
+            }
+            Some(event)
+        }
+        Ok(None) => None,
+        Err(err) => {
+            eprintln!(
+                "Could not fetch your mute list ({}); using the cached one.",
+                err
+            );
+            cached_mute_list(cache.as_ref())
+        }
+    };
+    match event {
+        Some(event) => mutes_from(Some(keys), &event),
+        None => Mutes::default(),
+    }
+}
+
+/// The list's items as mutes. The private part needs our keys; without them,
+/// or when it cannot be decrypted, only the public items apply.
+fn mutes_from(keys: Option<&Keys>, event: &Event) -> Mutes {
+    let public_only = || {
+        Mutes::from_items(&ListItems {
+            public: event.tags.iter().cloned().collect(),
+            private: Vec::new(),
+        })
+    };
+    let Some(keys) = keys else {
+        return public_only();
+    };
+    match list::list_items(keys, event) {
+        Ok(items) => Mutes::from_items(&items),
+        Err(err) => {
+            eprintln!(
+                "Could not read the private part of your mute list ({}).",
+                err
+            );
+            public_only()
+        }
+    }
+}
+
+/// The last mute list fetched, without touching the network, for reads that
+/// stay offline (`search --local`). Never fails, like [`load`]; a cached list
+/// that belongs to another account hides nothing.
+pub fn load_cached(config: &NostaroConfig) -> Mutes {
+    let cache = CacheDb::open().ok();
+    let Some(event) = cached_mute_list(cache.as_ref()) else {
+        return Mutes::default();
+    };
+    let keys = keys::keys_from_config(config).ok();
+    if keys
+        .as_ref()
+        .is_some_and(|keys| keys.public_key() != event.pubkey)
+    {
+        return Mutes::default();
+    }
+    mutes_from(keys.as_ref(), &event)
+}
+
+/// [`load`] for commands that have no client of their own at that point
+/// (`search`): connects with the configured relays just for the list. Falls
+/// back to [`load_cached`] when there are no keys or no client to ask with.
+pub async fn load_with_config(config: &NostaroConfig) -> Mutes {
+    let Ok(keys) = keys::keys_from_config(config) else {
+        return load_cached(config);
+    };
+    let nostr_client = match client::create_client(&keys, config).await {
+        Ok(nostr_client) => nostr_client,
+        Err(err) => {
+            eprintln!(
+                "Could not fetch your mute list ({}); using the cached one.",
+                err
+            );
+            return load_cached(config);
+        }
+    };
+    let mutes = load(&nostr_client, &keys).await;
+    nostr_client.disconnect().await;
+    mutes
+}
+
+/// The current list's items, for editing. Unlike [`load`] this fails if the
+/// private part cannot be decrypted: republishing without it would unmute
+/// everything in it.
+async fn fetch_items(nostr_client: &Client, keys: &Keys) -> Result<ListItems> {
+    match fetch_mute_list(nostr_client, &keys.public_key()).await? {
+        Some(event) => list::list_items(keys, &event),
+        None => Ok(ListItems::default()),
+    }
+}
+
+fn describe(tag: &Tag, cache: Option<&CacheDb>) -> String {
+    match tag.as_slice() {
+        [name, value, ..] if name == "p" => match PublicKey::from_hex(value) {
+            Ok(pubkey) => {
+                let npub = pubkey.to_bech32().unwrap_or_else(|_| value.clone());
+                match thread::cached_name(cache, &pubkey) {
+                    Some(name) => format!("pubkey  {} ({})", name, npub),
+                    None => format!("pubkey  {}", npub),
+                }
+            }
+            Err(_) => format!("pubkey  {}", value),
+        },
+        [name, value, ..] if name == "t" => format!("hashtag #{}", value),
+        [name, value, ..] if name == "word" => format!("word    \"{}\"", value),
+        [name, value, ..] if name == "e" => {
+            let note = EventId::from_hex(value)
+                .ok()
+                .and_then(|id| id.to_bech32().ok())
+                .unwrap_or_else(|| value.clone());
+            format!("thread  {}", note)
+        }
+        other => other.join(" "),
+    }
+}
+
+/// [`mute_tag`] for every value, after looking up the ones that are NIP-05
+/// addresses when people are being muted.
+async fn mute_tags(target: MuteTarget, values: &[String]) -> Result<Vec<Tag>> {
+    let mut tags = Vec::with_capacity(values.len());
+    for value in values {
+        tags.push(match target {
+            MuteTarget::Pubkey => Tag::public_key(resolve_pubkey_online(value).await?),
+            _ => mute_tag(target, value)?,
+        });
+    }
+    Ok(tags)
+}
+
+/// `mute add <type> <value>...`: mute publicly or `--private`ly.
+pub async fn add(target: MuteTarget, values: &[String], private: bool) -> Result<()> {
+    let tags = mute_tags(target, values).await?;
+    let config = NostaroConfig::load()?;
+    let keys = keys::keys_from_config(&config)?;
+    let nostr_client = client::create_client(&keys, &config).await?;
+
+    let mut items = fetch_items(&nostr_client, &keys).await?;
+    let added = items.add_tags(&tags, private);
+    if added.is_empty() {
+        println!("Already muted.");
+        nostr_client.disconnect().await;
+        return Ok(());
+    }
+
+    let builder = list::list_builder(&keys, MUTE_LIST_KIND, None, &items)?;
+    client::publish(&nostr_client, builder).await?;
+    let half = if private { " (private)" } else { "" };
+    client::report_published(&format!("Muted {} item(s){}.", added.len(), half));
+
+    nostr_client.disconnect().await;
+    Ok(())
+}
+
+/// `mute remove <type> <value>...`: unmute, whichever half the item is in.
+pub async fn remove(target: MuteTarget, values: &[String]) -> Result<()> {
+    let tags = mute_tags(target, values).await?;
+    let config = NostaroConfig::load()?;
+    let keys = keys::keys_from_config(&config)?;
+    let nostr_client = client::create_client(&keys, &config).await?;
+
+    let mut items = fetch_items(&nostr_client, &keys).await?;
+    let removed = items.remove_tags(&tags);
+    if removed.is_empty() {
+        println!("Not muted.");
+        nostr_client.disconnect().await;
+        return Ok(());
+    }
+
+    let builder = list::list_builder(&keys, MUTE_LIST_KIND, None, &items)?;
+    client::publish(&nostr_client, builder).await?;
+    client::report_published(&format!("Unmuted {} item(s).", removed.len()));
+
+    nostr_client.disconnect().await;
+    Ok(())
+}
+
+/// `mute list`: everything muted, private items marked.
+pub async fn list() -> Result<()> {
+    let config = NostaroConfig::load()?;
+    let keys = keys::keys_from_config(&config)?;
+    let nostr_client = client::create_client(&keys, &config).await?;
+
+    let items = fetch_items(&nostr_client, &keys).await?;
+    let cache = CacheDb::open().ok();
+    if let Some(cache) = cache.as_ref() {
+        let pubkeys: Vec<PublicKey> = items.members().into_iter().map(|(pk, _)| pk).collect();
+        if !pubkeys.is_empty() {
+            let _ = super::timeline::fetch_and_cache_profiles(&nostr_client, pubkeys, cache).await;
+        }
+    }
+
+    if items.public.is_empty() && items.private.is_empty() {
+        println!("Nothing is muted.");
+    }
+    for tag in &items.public {
+        println!("{}", describe(tag, cache.as_ref()));
+    }
+    for tag in &items.private {
+        println!("{}  [private]", describe(tag, cache.as_ref()));
+    }
+
+    nostr_client.disconnect().await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mutes(tags: Vec<Tag>) -> Mutes {
+        Mutes::from_items(&ListItems {
+            public: tags,
+            private: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn each_kind_of_item_hides_what_it_names() {
+        let spammer = Keys::generate();
+        let friend = Keys::generate();
+        let root = EventBuilder::text_note("flame war")
+            .sign_with_keys(&friend)
+            .unwrap();
+        let mutes = mutes(vec![
+            mute_tag(
+                MuteTarget::Pubkey,
+                &spammer.public_key().to_bech32().unwrap(),
+            )
+            .unwrap(),
+            mute_tag(MuteTarget::Hashtag, "#Airdrop").unwrap(),
+            mute_tag(MuteTarget::Word, "Buy Now").unwrap(),
+            mute_tag(MuteTarget::Thread, &root.id.to_bech32().unwrap()).unwrap(),
+        ]);
+
+        let note = |keys: &Keys, builder: EventBuilder| builder.sign_with_keys(keys).unwrap();
+        assert!(mutes.is_muted(&note(&spammer, EventBuilder::text_note("gm"))));
+        assert!(mutes.is_muted(&note(
+            &friend,
+            EventBuilder::text_note("free").tags([Tag::hashtag("AIRDROP")])
+        )));
+        assert!(mutes.is_muted(&note(&friend, EventBuilder::text_note("BUY NOW!!"))));
+        assert!(mutes.is_muted(&root));
+        assert!(mutes.is_muted(&note(
+            &friend,
+            EventBuilder::text_note("+1").tags([Tag::event(root.id)])
+        )));
+
+        assert!(!mutes.is_muted(&note(&friend, EventBuilder::text_note("gm"))));
+        assert!(!Mutes::default().is_muted(&root));
+    }
+
+    #[test]
+    fn zap_receipts_are_muted_by_their_sender() {
+        let spammer = Keys::generate();
+        let wallet = Keys::generate();
+        let receipt = EventBuilder::new(Kind::ZapReceipt, "")
+            .tags([Tag::parse(["P", spammer.public_key().to_hex().as_str()]).unwrap()])
+            .sign_with_keys(&wallet)
+            .unwrap();
+        let mutes = mutes(vec![Tag::public_key(spammer.public_key())]);
+        assert!(mutes.is_muted(&receipt));
+    }
+
+    #[test]
+    fn private_items_mute_like_public_ones() {
+        let keys = Keys::generate();
+        let mut items = ListItems::default();
+        items.add_tags(&[mute_tag(MuteTarget::Word, "spoiler").unwrap()], true);
+        let event = list::list_builder(&keys, MUTE_LIST_KIND, None, &items)
+            .unwrap()
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert!(event.tags.is_empty(), "the word is not public");
+
+        let mutes = Mutes::from_items(&list::list_items(&keys, &event).unwrap());
+        let note = EventBuilder::text_note("huge SPOILER ahead")
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+        assert!(mutes.is_muted(&note));
+        assert!(mutes_from(Some(&keys), &event).is_muted(&note));
+
+        // Offline without keys (`search --local`), only the public part applies.
+        assert!(!mutes_from(None, &event).is_muted(&note));
+    }
+}
@@ -0,0 +1,557 @@
+//! `notifications`: what happened to us since we last looked.
+//!
+//! One read of everything that tags our pubkey — mentions, replies, quotes,
+//! reactions, reposts and zap receipts — grouped so that five reactions to a
+//! note are one line, not five. The time of each run is kept in the cache as
+//! a read marker, so the next run (say, an agent's cron job) shows only what
+//! is new; `--since` looks further back without losing the marker's place.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use nostr_sdk::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use crate::cache::CacheDb;
+use crate::client;
+use crate::config::NostaroConfig;
+use crate::keys;
+use crate::outln;
+use crate::output;
+use crate::post_options;
+use crate::utils::parse_since;
+
+use super::mute;
+use super::thread;
+use super::timeline;
+
+/// How far back the very first run looks, before there is a read marker.
+const FIRST_RUN_WINDOW: u64 = 24 * 60 * 60;
+
+/// What a notification is about. Serialized as the `type` field of the JSON.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Category {
+    Reply,
+    Quote,
+    Mention,
+    Reaction,
+    Repost,
+    Zap,
+}
+
+impl Category {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Category::Reply => "reply",
+            Category::Quote => "quote",
+            Category::Mention => "mention",
+            Category::Reaction => "reaction",
+            Category::Repost => "repost",
+            Category::Zap => "zap",
+        }
+    }
+
+    /// Reactions, reposts and zaps say nothing of their own, so they are
+    /// counted per note; replies, quotes and mentions are shown one by one.
+    fn is_grouped(self) -> bool {
+        matches!(self, Category::Reaction | Category::Repost | Category::Zap)
+    }
+}
+
+/// The event that quotes another names it in a `q` tag (NIP-18).
+fn quoted_id(event: &Event) -> Option<EventId> {
+    event.tags.iter().find_map(|tag| match tag.as_slice() {
+        [name, value, ..] if name == "q" => EventId::from_hex(value).ok(),
+        _ => None,
+    })
+}
+
+/// Who sent a zap: the pubkey of the zap request inside the receipt's
+/// `description` (NIP-57) — the receipt itself is signed by the LNURL server.
+fn zap_sender(receipt: &Event) -> Option<PublicKey> {
+    let description = receipt.tags.iter().find_map(|tag| match tag.as_slice() {
+        [name, value, ..] if name == "description" => Some(value.clone()),
+        _ => None,
+    })?;
+    let request: serde_json::Value = serde_json::from_str(&description).ok()?;
+    PublicKey::from_hex(request["pubkey"].as_str()?).ok()
+}
+
+/// Sort one event that tags `me` into a category, with the note it is about
+/// and who is behind it. `None` for our own events and for kinds that are not
+/// notifications.
+///
+/// Pure — it reads tags of an event that has already been fetched.
+pub fn classify(event: &Event, me: &PublicKey) -> Option<(Category, Option<EventId>, PublicKey)> {
+    let kind = event.kind.as_u16();
+    let (category, target, actor) = if kind == 9735 {
+        let sender = zap_sender(event)?;
+        (Category::Zap, thread::target_id(event), sender)
+    } else if event.kind == Kind::Reaction {
+        (Category::Reaction, thread::target_id(event), event.pubkey)
+    } else if event.kind == Kind::Repost || kind == client::GENERIC_REPOST_KIND {
+        (Category::Repost, thread::target_id(event), event.pubkey)
+    } else if event.kind == Kind::TextNote {
+        if let Some(quoted) = quoted_id(event) {
+            (Category::Quote, Some(quoted), event.pubkey)
+        } else if let Some(parent) = thread::parent_id(event) {
+            (Category::Reply, Some(parent), event.pubkey)
+        } else {
+            (Category::Mention, None, event.pubkey)
+        }
+    } else {
+        return None;
+    };
+
+    (actor != *me).then_some((category, target, actor))
+}
+
+/// Notifications of one category about one note: all the reactions to it, or
+/// a single reply.
+#[derive(Debug, Clone)]
+pub struct Group {
+    pub category: Category,
+    pub target: Option<EventId>,
+    pub events: Vec<Event>,
+    /// Distinct actors, in order of appearance.
+    pub actors: Vec<PublicKey>,
+    pub zap_msats: u64,
+    pub latest: Timestamp,
+}
+
+/// Classify and group `events`, newest group first. Reactions, reposts and
+/// zaps are grouped by the note they are about; everything else stays one
+/// group per event. Duplicates (the same event from two relays) count once.
+///
+/// Pure — it arranges events that have already been fetched.
+pub fn group(events: &[Event], me: &PublicKey) -> Vec<Group> {
+    let mut seen: HashSet<EventId> = HashSet::new();
+    let mut sorted: Vec<&Event> = events.iter().filter(|e| seen.insert(e.id)).collect();
+    sorted.sort_by_key(|e| e.created_at);
+
+    let mut groups: Vec<Group> = Vec::new();
+    let mut index: HashMap<(Category, Option<EventId>), usize> = HashMap::new();
+    for event in sorted {
+        let Some((category, target, actor)) = classify(event, me) else {
+            continue;
+        };
+
+        let slot = if category.is_grouped() {
+            index.get(&(category, target)).copied()
+        } else {
+            None
+        };
+        let slot = match slot {
+            Some(slot) => slot,
+            None => {
+                groups.push(Group {
+                    category,
+                    target,
+                    events: Vec::new(),
+                    actors: Vec::new(),
+                    zap_msats: 0,
+                    latest: event.created_at,
+                });
+                if category.is_grouped() {
+                    index.insert((category, target), groups.len() - 1);
+                }
+                groups.len() - 1
+            }
+        };
+
+        let group = &mut groups[slot];
+        if !group.actors.contains(&actor) {
+            group.actors.push(actor);
+        }
+        if category == Category::Zap {
+            group.zap_msats += thread::zap_msats(event).unwrap_or(0);
+        }
+        group.latest = group.latest.max(event.created_at);
+        group.events.push(event.clone());
+    }
+
+    groups.sort_by_key(|group| std::cmp::Reverse(group.latest));
+    groups
+}
+
+/// The cache key of the read marker: one per account, so switching keys does
+/// not mark someone else's notifications read.
+fn marker_key(me: &PublicKey) -> String {
+    format!("notifications.last_read.{}", me.to_hex())
+}
+
+fn read_marker(cache: Option<&CacheDb>, me: &PublicKey) -> Option<Timestamp> {
+    cache?
+        .get_state(&marker_key(me))
+        .ok()
+        .flatten()?
+        .parse::<u64>()
+        .ok()
+        .map(Timestamp::from)
+}
+
+fn actor_label(cache: Option<&CacheDb>, pubkey: &PublicKey) -> Result<String> {
+    let npub = pubkey.to_bech32()?;
+    Ok(match thread::cached_name(cache, pubkey) {
+        Some(name) => format!("{} ({})", name, npub),
+        None => npub,
+    })
+}
+
+/// One line of a note, short enough to follow a label.
+fn snippet(text: &str) -> String {
+    let line = text.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+    let mut out: String = line.chars().take(60).collect();
+    if line.chars().count() > 60 {
+        out.push('…');
+    }
+    out
+}
+
+fn format_time(at: Timestamp) -> String {
+    DateTime::<Utc>::from_timestamp(at.as_u64() as i64, 0)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// The `--out-format json` document:
+/// `{since, until, count, groups: [{type, target, count, actors, zap_sats, latest, events}]}`.
+///
+/// Pure — it renders what has already been fetched; the cache is only read.
+fn to_json(
+    groups: &[Group],
+    since: Timestamp,
+    until: Timestamp,
+    cache: Option<&CacheDb>,
+) -> Result<serde_json::Value> {
+    let groups = groups
+        .iter()
+        .map(|group| {
+            let actors = group
+                .actors
+                .iter()
+                .map(|pk| {
+                    Ok(serde_json::json!({
+                        "npub": pk.to_bech32()?,
+                        "name": thread::cached_name(cache, pk),
+                    }))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(serde_json::json!({
+                "type": group.category.as_str(),
+                "target": group.target.map(|id| id.to_hex()),
+                "count": group.events.len(),
+                "actors": actors,
+                "zap_sats": group.zap_msats / 1000,
+                "latest": group.latest.as_u64(),
+                "events": group.events,
+            }))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(serde_json::json!({
+        "since": since.as_u64(),
+        "until": until.as_u64(),
+        "count": groups.len(),
+        "groups": groups,
+    }))
+}
+
+fn render_text(
+    groups: &[Group],
+    targets: &HashMap<EventId, Event>,
+    cache: Option<&CacheDb>,
+) -> Result<()> {
+    for group in groups {
+        let about = match group.target {
+            Some(id) => match targets.get(&id) {
+                Some(note) => format!("\"{}\" ({})", snippet(&note.content), id.to_bech32()?),
+                None => id.to_bech32()?,
+            },
+            None => String::new(),
+        };
+
+        if group.category.is_grouped() {
+            let mut names = group
+                .actors
+                .iter()
+                .take(3)
+                .map(|pk| actor_label(cache, pk))
+                .collect::<Result<Vec<_>>>()?;
+            if group.actors.len() > 3 {
+                names.push(format!("{} more", group.actors.len() - 3));
+            }
+            let what = match group.category {
+                Category::Reaction => {
+                    let mut emojis: Vec<&str> = Vec::new();
+                    for event in &group.events {
+                        if !emojis.contains(&event.content.as_str()) {
+                            emojis.push(event.content.as_str());
+                        }
+                    }
+                    format!("{} reaction(s) {}", group.events.len(), emojis.join(" "))
+                }
+                Category::Repost => format!("{} repost(s)", group.events.len()),
+                _ => format!(
+                    "{} zap(s), {} sats",
+                    group.events.len(),
+                    thread::format_sats(group.zap_msats)
+                ),
+            };
+            outln!("{}  {} on {}", format_time(group.latest), what, about)?;
+            outln!("  from {}", names.join(", "))?;
+        } else {
+            let event = &group.events[0];
+            let heading = match group.category {
+                Category::Reply => format!("replied to {}", about),
+                Category::Quote => format!("quoted {}", about),
+                _ => "mentioned you".to_string(),
+            };
+            outln!(
+                "{}  {} {}",
+                format_time(group.latest),
+                actor_label(cache, &event.pubkey)?,
+                heading
+            )?;
+            for line in post_options::display_content(event).lines() {
+                outln!("  {}", line)?;
+            }
+            outln!("  id: {}", event.id.to_bech32()?)?;
+        }
+        outln!()?;
+    }
+    Ok(())
+}
+
+/// `notifications [--since]`: everything that tagged us since the read
+/// marker (or `since`), grouped. Unless `no_mark`, the marker then moves to
+/// the start of this run.
+pub async fn run(since: Option<&str>, no_mark: bool) -> Result<()> {
+    let now = Timestamp::now();
+    let cache = CacheDb::open().ok();
+
+    let config = NostaroConfig::load()?;
+    let keys = keys::keys_from_config(&config)?;
+    let me = keys.public_key();
+
+    let since = match since {
+        Some(since) => parse_since(since, now)?,
+        None => read_marker(cache.as_ref(), &me)
+            .unwrap_or_else(|| Timestamp::from(now.as_u64().saturating_sub(FIRST_RUN_WINDOW))),
+    };
+
+    let nostr_client = client::create_client(&keys, &config).await?;
+    println!("Fetching notifications since {}...\n", format_time(since));
+
+    let filter = Filter::new()
+        .pubkey(me)
+        .kinds([
+            Kind::TextNote,
+            Kind::Reaction,
+            Kind::Repost,
+            Kind::from(client::GENERIC_REPOST_KIND),
+            Kind::from(9735u16),
+        ])
+        .since(since);
+    let mutes = mute::load(&nostr_client, &keys).await;
+    let events: Vec<Event> = thread::fetch_all_pages(&nostr_client, filter)
+        .await?
+        .into_iter()
+        .filter(|event| event.created_at >= since && !post_options::is_expired(event, now))
+        .filter(|event| !mutes.is_muted(event))
+        .collect();
+
+    let groups = group(&events, &me);
+
+    // Names for everyone involved and the notes they are about, one read each.
+    if let Some(cache) = cache.as_ref() {
+        let actors: HashSet<PublicKey> = groups
+            .iter()
+            .flat_map(|g| g.actors.iter().copied())
+            .collect();
+        let _ =
+            timeline::fetch_and_cache_profiles(&nostr_client, actors.into_iter().collect(), cache)
+                .await;
+    }
+    let target_ids: HashSet<EventId> = groups.iter().filter_map(|g| g.target).collect();
+    let targets: HashMap<EventId, Event> = if target_ids.is_empty() {
+        HashMap::new()
+    } else {
+        nostr_client
+            .fetch_events(Filter::new().ids(target_ids), Duration::from_secs(10))
+            .await?
+            .into_iter()
+            .map(|event| (event.id, event))
+            .collect()
+    };
+
+    if output::is_json() {
+        output::write_json(&to_json(&groups, since, now, cache.as_ref())?)?;
+    } else {
+        output::open_body()?;
+        render_text(&groups, &targets, cache.as_ref())?;
+    }
+
+    if groups.is_empty() {
+        println!("No new notifications.");
+    } else {
+        println!(
+            "{} notification(s) from {} event(s).",
+            groups.len(),
+            events.len()
+        );
+    }
+
+    if !no_mark {
+        if let Some(cache) = cache.as_ref() {
+            cache.set_state(&marker_key(&me), &now.as_u64().to_string())?;
+        }
+    }
+
+    nostr_client.disconnect().await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(builder: EventBuilder, keys: &Keys, secs: u64) -> Event {
+        builder
+            .custom_created_at(Timestamp::from(secs))
+            .sign_with_keys(keys)
+            .unwrap()
+    }
+
+    #[test]
+    fn events_are_sorted_into_categories() {
+        let me = Keys::generate();
+        let friend = Keys::generate();
+        let mine = at(EventBuilder::text_note("gm"), &me, 100);
+
+        let reply = at(
+            EventBuilder::text_note("gm!").tags([
+                Tag::parse(["e", mine.id.to_hex().as_str(), "", "root"]).unwrap(),
+                Tag::public_key(me.public_key()),
+            ]),
+            &friend,
+            110,
+        );
+        let quote = at(
+            EventBuilder::text_note("look").tags([
+                Tag::parse(["q", mine.id.to_hex().as_str()]).unwrap(),
+                Tag::public_key(me.public_key()),
+            ]),
+            &friend,
+            120,
+        );
+        let mention = at(
+            EventBuilder::text_note("hi").tags([Tag::public_key(me.public_key())]),
+            &friend,
+            130,
+        );
+        let own = at(
+            EventBuilder::text_note("note to self").tags([Tag::public_key(me.public_key())]),
+            &me,
+            140,
+        );
+
+        let pk = me.public_key();
+        assert_eq!(
+            classify(&reply, &pk),
+            Some((Category::Reply, Some(mine.id), friend.public_key()))
+        );
+        assert_eq!(
+            classify(&quote, &pk),
+            Some((Category::Quote, Some(mine.id), friend.public_key()))
+        );
+        assert_eq!(
+            classify(&mention, &pk),
+            Some((Category::Mention, None, friend.public_key()))
+        );
+        assert_eq!(classify(&own, &pk), None);
+    }
+
+    #[test]
+    fn reactions_to_a_note_are_one_group_and_replies_stay_apart() {
+        let me = Keys::generate();
+        let mine = at(EventBuilder::text_note("gm"), &me, 100);
+        let react = |keys: &Keys, content: &str, secs: u64| {
+            at(
+                EventBuilder::new(Kind::Reaction, content)
+                    .tags([Tag::event(mine.id), Tag::public_key(me.public_key())]),
+                keys,
+                secs,
+            )
+        };
+        let alice = Keys::generate();
+        let bob = Keys::generate();
+        let reply = |keys: &Keys, secs: u64| {
+            at(
+                EventBuilder::text_note("gm").tags([
+                    Tag::parse(["e", mine.id.to_hex().as_str(), "", "root"]).unwrap(),
+                    Tag::public_key(me.public_key()),
+                ]),
+                keys,
+                secs,
+            )
+        };
+
+        let first = react(&alice, "+", 110);
+        let events = vec![
+            first.clone(),
+            react(&bob, "🤙", 120),
+            react(&alice, "⚡", 130),
+            first,
+            reply(&alice, 140),
+            reply(&bob, 150),
+        ];
+        let groups = group(&events, &me.public_key());
+
+        let summary: Vec<(Category, usize, usize)> = groups
+            .iter()
+            .map(|g| (g.category, g.events.len(), g.actors.len()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Category::Reply, 1, 1),
+                (Category::Reply, 1, 1),
+                (Category::Reaction, 3, 2),
+            ]
+        );
+        assert_eq!(groups[2].latest, Timestamp::from(130));
+    }
+
+    #[test]
+    fn zaps_are_credited_to_the_sender_in_the_zap_request() {
+        let me = Keys::generate();
+        let sender = Keys::generate();
+        let server = Keys::generate();
+        let note = at(EventBuilder::text_note("gm"), &me, 100);
+        let request = at(
+            EventBuilder::new(Kind::ZapRequest, "").tags([
+                Tag::public_key(me.public_key()),
+                Tag::event(note.id),
+                Tag::parse(["amount", "21000"]).unwrap(),
+            ]),
+            &sender,
+            110,
+        );
+        let receipt = at(
+            EventBuilder::new(Kind::from(9735u16), "").tags([
+                Tag::public_key(me.public_key()),
+                Tag::event(note.id),
+                Tag::parse(["description", request.as_json().as_str()]).unwrap(),
+            ]),
+            &server,
+            111,
+        );
+
+        let groups = group(&[receipt], &me.public_key());
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].category, Category::Zap);
+        assert_eq!(groups[0].actors, vec![sender.public_key()]);
+        assert_eq!(groups[0].zap_msats, 21000);
+    }
+}
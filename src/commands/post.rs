@@ -4,8 +4,12 @@ use nostr_sdk::prelude::*;
 use crate::client;
 use crate::config::NostaroConfig;
 use crate::keys;
+use crate::post_options::PostOptions;
+
+pub async fn run(message: &str, quote: Option<&str>, options: &PostOptions) -> Result<()> {
+    // Built before connecting, so a bad --lang or --expires-in fails fast.
+    let option_tags = options.tags(Timestamp::now())?;
 
-pub async fn run(message: &str, quote: Option<&str>) -> Result<()> {
     let config = NostaroConfig::load()?;
     let keys = keys::keys_from_config(&config)?;
     let nostr_client = client::create_client(&keys, &config).await?;
@@ -38,6 +42,8 @@ pub async fn run(message: &str, quote: Option<&str>) -> Result<()> {
         content.push_str(&format!("\n\nnostr:{}", quote_str));
     }
 
+    extra_tags.extend(option_tags);
+
     println!("Publishing note...");
     if extra_tags.is_empty() {
         client::post_note(&nostr_client, &content).await?;
@@ -47,7 +53,7 @@ pub async fn run(message: &str, quote: Option<&str>) -> Result<()> {
         let output = client::publish(&nostr_client, builder).await?;
         println!("Event ID: {}", output.id().to_bech32()?);
     }
-    println!("Note published successfully!");
+    client::report_published("Note published successfully!");
 
     nostr_client.disconnect().await;
     Ok(())
@@ -1,11 +1,18 @@
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use nostr_sdk::prelude::*;
+use serde_json::{Map, Value};
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
 
 use crate::cache::CacheDb;
 use crate::client;
 use crate::config::NostaroConfig;
 use crate::keys;
-use crate::utils::resolve_pubkey;
+use crate::nip05;
+use crate::utils::resolve_pubkey_with_hints;
+
+use super::snapshot::{find_version, snapshot_event, versions};
+use super::timeline::format_time;
 
 pub async fn show(pubkey_str: Option<&str>) -> Result<()> {
     let config = NostaroConfig::load()?;
@@ -13,14 +20,27 @@ pub async fn show(pubkey_str: Option<&str>) -> Result<()> {
     let nostr_client = client::create_client(&own_keys, &config).await?;
 
     let pubkey = match pubkey_str {
-        Some(pk) => resolve_pubkey(pk)?,
+        Some(pk) => {
+            let (pubkey, hints) = resolve_pubkey_with_hints(pk).await?;
+            client::add_relay_hints(&nostr_client, &hints).await;
+            pubkey
+        }
         None => own_keys.public_key(),
     };
 
     let npub = pubkey.to_bech32()?;
     println!("Fetching profile for {}...\n", npub);
 
-    let metadata = client::fetch_profile(&nostr_client, &pubkey).await?;
+    let event = client::fetch_profile_event(&nostr_client, &pubkey).await?;
+    let metadata = event
+        .as_ref()
+        .and_then(|event| Metadata::from_json(&event.content).ok());
+    let cache = CacheDb::open().ok();
+    if let (Some(cache), Some(event)) = (cache.as_ref(), event.as_ref()) {
+        if event.pubkey == own_keys.public_key() {
+            snapshot_event(cache, event);
+        }
+    }
 
     if let Some(ref metadata) = metadata {
         if let Some(ref name) = metadata.name {
@@ -44,12 +64,20 @@ pub async fn show(pubkey_str: Option<&str>) -> Result<()> {
         if let Some(ref lud16) = metadata.lud16 {
             println!("Lud16:        {}", lud16);
         }
-        if let Some(ref nip05) = metadata.nip05 {
-            println!("NIP-05:       {}", nip05);
+        let claimed = metadata.nip05.as_deref().filter(|nip05| !nip05.is_empty());
+        if let Some(nip05) = claimed {
+            // The claim alone proves nothing: only the domain can vouch for it.
+            let status = match nip05::verify(cache.as_ref(), &pubkey, nip05).await {
+                Some(check) if check.verified => "✔ verified".to_string(),
+                Some(_) => "✘ NOT verified: the domain does not list this pubkey under that name"
+                    .to_string(),
+                None => "? could not be checked: the domain did not answer".to_string(),
+            };
+            println!("NIP-05:       {} ({})", nip05, status);
         }
 
         // Cache the profile
-        if let Ok(cache) = CacheDb::open() {
+        if let Some(cache) = cache.as_ref() {
             let _ = cache.store_profile(
                 &pubkey.to_hex(),
                 metadata.name.as_deref(),
@@ -57,6 +85,9 @@ pub async fn show(pubkey_str: Option<&str>) -> Result<()> {
                 metadata.about.as_deref(),
                 metadata.picture.as_deref(),
             );
+            if claimed.is_none() {
+                let _ = cache.store_nip05_claim(&pubkey.to_hex(), None);
+            }
         }
     } else {
         println!("No profile metadata found.");
@@ -70,74 +101,411 @@ pub async fn show(pubkey_str: Option<&str>) -> Result<()> {
     Ok(())
 }
 
-#[allow(clippy::too_many_arguments)]
-pub async fn set(
-    name: Option<&str>,
-    display_name: Option<&str>,
-    about: Option<&str>,
-    picture: Option<&str>,
-    lud16: Option<&str>,
-    lud06: Option<&str>,
-    nip05: Option<&str>,
-    banner: Option<&str>,
-    website: Option<&str>,
-) -> Result<()> {
-    if name.is_none()
-        && display_name.is_none()
-        && about.is_none()
-        && picture.is_none()
-        && lud16.is_none()
-        && lud06.is_none()
-        && nip05.is_none()
-        && banner.is_none()
-        && website.is_none()
-    {
-        bail!("At least one field must be specified (--name, --display-name, --about, --picture, --lud16, --lud06, --nip05, --banner, --website)");
+/// Metadata fields whose value must be a URL.
+const URL_FIELDS: [&str; 3] = ["picture", "banner", "website"];
+
+/// What `profile set` was asked to change.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ProfileChanges {
+    /// `--name`, `--about`, ...: the kind:0 key and its new string value.
+    pub named: Vec<(&'static str, String)>,
+    /// `--field key=value`, as typed.
+    pub fields: Vec<String>,
+    /// `--unset <field>`.
+    pub unset: Vec<String>,
+    /// `--from-file`: a whole kind:0 content object (`-` for stdin).
+    pub from_file: Option<PathBuf>,
+}
+
+impl ProfileChanges {
+    pub fn is_empty(&self) -> bool {
+        self.named.is_empty()
+            && self.fields.is_empty()
+            && self.unset.is_empty()
+            && self.from_file.is_none()
+    }
+}
+
+/// Parse `--field key=value`. The value is read as JSON when it is JSON
+/// (`bot=true`, `age=3`, `tags=["a"]`), as a plain string otherwise; quote it
+/// (`key="true"`) to force a string.
+///
+/// Pure — it only parses its argument.
+pub fn parse_field(spec: &str) -> Result<(String, Value)> {
+    let Some((key, value)) = spec.split_once('=') else {
+        bail!("--field expects key=value, got \"{}\"", spec);
+    };
+    let key = key.trim();
+    if key.is_empty() {
+        bail!("--field expects key=value, got \"{}\"", spec);
+    }
+    let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+    Ok((key.to_string(), value))
+}
+
+/// A kind:0 content as a JSON object. Anything else is refused: it is not a
+/// profile we could edit without losing what is in it.
+///
+/// Pure — it only parses its argument.
+pub fn parse_profile(content: &str) -> Result<Map<String, Value>> {
+    match serde_json::from_str(content) {
+        Ok(Value::Object(fields)) => Ok(fields),
+        Ok(_) => bail!("a profile must be a JSON object"),
+        Err(e) => bail!("a profile must be a JSON object: {}", e),
+    }
+}
+
+/// The new profile: `base` (the file, when there is one, or else the current
+/// profile), then every field set, then every field unset. Keys nostaro does
+/// not know, such as `bot` or `pronouns`, are kept as they are.
+///
+/// Pure — it edits copies of what it is given.
+pub fn apply(
+    base: &Map<String, Value>,
+    set: &[(String, Value)],
+    unset: &[String],
+) -> Result<Map<String, Value>> {
+    if let Some((key, _)) = set.iter().find(|(key, _)| unset.contains(key)) {
+        bail!("\"{}\" is both set and unset", key);
+    }
+    let mut profile = base.clone();
+    for (key, value) in set {
+        if URL_FIELDS.contains(&key.as_str()) {
+            if let Some(url) = value.as_str().filter(|url| !url.is_empty()) {
+                Url::parse(url).with_context(|| format!("{} is not a URL: {}", key, url))?;
+            }
+        }
+        profile.insert(key.clone(), value.clone());
+    }
+    for key in unset {
+        profile.remove(key);
+    }
+    Ok(profile)
+}
+
+/// One field that differs between two versions of a profile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldChange {
+    Added(String, Value),
+    Removed(String, Value),
+    Changed(String, Value, Value),
+}
+
+impl std::fmt::Display for FieldChange {
+    /// One line, values as JSON so that strings with line breaks stay on it.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldChange::Added(key, value) => write!(f, "+ {}: {}", key, value),
+            FieldChange::Removed(key, value) => write!(f, "- {}: {}", key, value),
+            FieldChange::Changed(key, old, new) => write!(f, "~ {}: {} -> {}", key, old, new),
+        }
+    }
+}
+
+/// What changed from `old` to `new`, by field, in key order.
+///
+/// Pure — it compares two maps.
+pub fn diff(old: &Map<String, Value>, new: &Map<String, Value>) -> Vec<FieldChange> {
+    let keys: std::collections::BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+    keys.into_iter()
+        .filter_map(|key| match (old.get(key), new.get(key)) {
+            (None, Some(value)) => Some(FieldChange::Added(key.clone(), value.clone())),
+            (Some(value), None) => Some(FieldChange::Removed(key.clone(), value.clone())),
+            (Some(before), Some(after)) if before != after => Some(FieldChange::Changed(
+                key.clone(),
+                before.clone(),
+                after.clone(),
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Ask before publishing. With no terminal to ask on, the answer is no — a
+/// profile or follow list is never replaced unseen; `--yes` is the way to
+/// script it.
+pub(crate) fn confirm(question: &str) -> Result<bool> {
+    if !std::io::stdin().is_terminal() {
+        bail!("not publishing without confirmation: pass --yes to publish from a script");
+    }
+    print!("{} [y/N]: ", question);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// `profile set`: edit the current kind:0 and republish it.
+///
+/// A kind:0 is replaced as a whole, so the edit starts from the current one
+/// and nothing is published unless it was actually read: a relay that times
+/// out would otherwise turn "change my bio" into "erase my profile". `create`
+/// is the explicit way to publish a first profile. The changes are shown
+/// field by field and confirmed before anything is sent.
+pub async fn set(changes: &ProfileChanges, create: bool, yes: bool) -> Result<()> {
+    if changes.is_empty() {
+        bail!(
+            "At least one change must be given (--name, --display-name, --about, --picture, \
+             --lud16, --lud06, --nip05, --banner, --website, --field, --unset, --from-file)"
+        );
+    }
+    // Everything that can be checked offline is checked before connecting.
+    let mut set: Vec<(String, Value)> = changes
+        .named
+        .iter()
+        .map(|(key, value)| (key.to_string(), Value::String(value.clone())))
+        .collect();
+    for spec in &changes.fields {
+        set.push(parse_field(spec)?);
     }
+    let file = match &changes.from_file {
+        Some(path) => Some(
+            parse_profile(&super::event::read_source(path)?)
+                .with_context(|| format!("in {}", path.display()))?,
+        ),
+        None => None,
+    };
 
     let config = NostaroConfig::load()?;
     let own_keys = keys::keys_from_config(&config)?;
     let nostr_client = client::create_client(&own_keys, &config).await?;
 
-    let mut metadata = client::fetch_profile(&nostr_client, &own_keys.public_key())
-        .await?
-        .unwrap_or_else(Metadata::new);
+    let current_event = client::fetch_profile_event(&nostr_client, &own_keys.public_key())
+        .await
+        .context("could not fetch your current profile (kind:0); not publishing over it")?;
+    let cache = CacheDb::open().ok();
+    if let (Some(cache), Some(event)) = (cache.as_ref(), current_event.as_ref()) {
+        snapshot_event(cache, event);
+    }
+    let current = match &current_event {
+        Some(event) => parse_profile(&event.content).context(
+            "your current profile (kind:0) is not a JSON object; not publishing over it",
+        )?,
+        None if create => Map::new(),
+        None => {
+            nostr_client.disconnect().await;
+            bail!(
+                "no current profile (kind:0) was found on your relays. Publishing now could \
+                 wipe a profile a relay failed to return; if this account has none yet, \
+                 pass --create"
+            );
+        }
+    };
+
+    let profile = apply(file.as_ref().unwrap_or(&current), &set, &changes.unset)?;
+    let changed = diff(&current, &profile);
+    if changed.is_empty() {
+        println!("Nothing to change.");
+        nostr_client.disconnect().await;
+        return Ok(());
+    }
+    println!("Profile changes:");
+    for change in &changed {
+        println!("  {}", change);
+    }
+
+    if !yes && !client::is_dry_run() && !confirm("Publish this profile?")? {
+        println!("Not published.");
+        nostr_client.disconnect().await;
+        return Ok(());
+    }
+
+    println!("Setting profile metadata...");
+    publish_profile(&nostr_client, &profile, cache.as_ref()).await?;
+    client::report_published("Profile updated successfully!");
 
-    if let Some(v) = name {
-        metadata = metadata.name(v);
+    nostr_client.disconnect().await;
+    Ok(())
+}
+
+/// Publish `profile` as our kind:0 and keep the published version.
+async fn publish_profile(
+    nostr_client: &Client,
+    profile: &Map<String, Value>,
+    cache: Option<&CacheDb>,
+) -> Result<()> {
+    let builder = EventBuilder::new(Kind::Metadata, serde_json::to_string(profile)?);
+    let event = nostr_client.sign_event_builder(builder).await?;
+    client::publish_event(nostr_client, &event).await?;
+    if let (Some(cache), false) = (cache, client::is_dry_run()) {
+        snapshot_event(cache, &event);
     }
-    if let Some(v) = display_name {
-        metadata = metadata.display_name(v);
+    Ok(())
+}
+
+/// Keep the kind:0 the relays have now, if they can be reached; history and
+/// restore still work offline from what was recorded before.
+async fn record_current(nostr_client: &Client, pubkey: &PublicKey, cache: &CacheDb) -> Result<()> {
+    if let Some(event) = client::fetch_profile_event(nostr_client, pubkey).await? {
+        snapshot_event(cache, &event);
     }
-    if let Some(v) = about {
-        metadata = metadata.about(v);
+    Ok(())
+}
+
+/// `profile history`: every version of our kind:0 nostaro has seen or
+/// published, each with what changed since the one before.
+pub async fn history() -> Result<()> {
+    let config = NostaroConfig::load()?;
+    let keys = keys::keys_from_config(&config)?;
+    let nostr_client = client::create_client(&keys, &config).await?;
+    let cache = CacheDb::open()?;
+
+    if let Err(e) = record_current(&nostr_client, &keys.public_key(), &cache).await {
+        eprintln!("Warning: could not fetch the current profile: {}", e);
     }
-    if let Some(v) = picture {
-        let url = Url::parse(v)?;
-        metadata = metadata.picture(url);
+    nostr_client.disconnect().await;
+
+    let versions = versions(&cache, &keys.public_key(), Kind::Metadata)?;
+    if versions.is_empty() {
+        println!("No profile versions recorded yet.");
+        return Ok(());
     }
-    if let Some(v) = lud16 {
-        metadata = metadata.lud16(v);
+
+    println!(
+        "Profile history ({} version(s), oldest first):\n",
+        versions.len()
+    );
+    let mut previous = Map::new();
+    for (index, event) in versions.iter().enumerate() {
+        let fields = parse_profile(&event.content).unwrap_or_default();
+        let latest = if index + 1 == versions.len() {
+            " (latest)"
+        } else {
+            ""
+        };
+        println!(
+            "v{}  {}  {}{}",
+            index + 1,
+            format_time(event.created_at),
+            event.id.to_bech32()?,
+            latest
+        );
+        let changes = diff(&previous, &fields);
+        if changes.is_empty() {
+            println!("  (no field changes)");
+        }
+        for change in &changes {
+            println!("  {}", change);
+        }
+        previous = fields;
     }
-    if let Some(v) = lud06 {
-        metadata = metadata.lud06(v);
+    Ok(())
+}
+
+/// `profile restore <version>`: republish a recorded version as a new kind:0,
+/// after showing what it changes compared with the current one.
+pub async fn restore(version: &str, yes: bool) -> Result<()> {
+    let config = NostaroConfig::load()?;
+    let keys = keys::keys_from_config(&config)?;
+    let cache = CacheDb::open()?;
+    let nostr_client = client::create_client(&keys, &config).await?;
+
+    let current_event = client::fetch_profile_event(&nostr_client, &keys.public_key())
+        .await
+        .context("could not fetch your current profile (kind:0) to compare with")?;
+    if let Some(event) = &current_event {
+        snapshot_event(&cache, event);
     }
-    if let Some(v) = nip05 {
-        metadata = metadata.nip05(v);
+    let current = current_event
+        .and_then(|event| parse_profile(&event.content).ok())
+        .unwrap_or_default();
+
+    let versions = versions(&cache, &keys.public_key(), Kind::Metadata)?;
+    let (number, chosen) = find_version(&versions, version)?;
+    let restored = parse_profile(&chosen.content)
+        .with_context(|| format!("version {} is not a profile nostaro can restore", number))?;
+
+    let changed = diff(&current, &restored);
+    if changed.is_empty() {
+        println!("The current profile already matches version {}.", number);
+        nostr_client.disconnect().await;
+        return Ok(());
     }
-    if let Some(v) = banner {
-        let url = Url::parse(v)?;
-        metadata = metadata.banner(url);
+    println!(
+        "Restoring version {} ({}):",
+        number,
+        format_time(chosen.created_at)
+    );
+    for change in &changed {
+        println!("  {}", change);
     }
-    if let Some(v) = website {
-        let url = Url::parse(v)?;
-        metadata = metadata.website(url);
+
+    if !yes && !client::is_dry_run() && !confirm("Publish this profile?")? {
+        println!("Not published.");
+        nostr_client.disconnect().await;
+        return Ok(());
     }
 
-    println!("Setting profile metadata...");
-    client::set_metadata(&nostr_client, &metadata).await?;
-    println!("Profile updated successfully!");
+    publish_profile(&nostr_client, &restored, Some(&cache)).await?;
+    client::report_published(&format!("Profile restored to version {}.", number));
 
     nostr_client.disconnect().await;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn profile(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn fields_are_json_when_they_parse_and_strings_otherwise() {
+        assert_eq!(
+            parse_field("bot=true").unwrap(),
+            ("bot".to_string(), json!(true))
+        );
+        assert_eq!(
+            parse_field("pronouns=they/them").unwrap(),
+            ("pronouns".to_string(), json!("they/them"))
+        );
+        assert_eq!(parse_field("x=\"true\"").unwrap().1, json!("true"));
+        assert_eq!(parse_field("about=a=b").unwrap().1, json!("a=b"));
+        assert!(parse_field("novalue").is_err());
+        assert!(parse_field("=value").is_err());
+    }
+
+    #[test]
+    fn apply_keeps_unknown_fields_and_unsets_last() {
+        let current = profile(json!({"name": "bot", "lud06": "lnurl1", "bot": true}));
+        let set = vec![
+            ("about".to_string(), json!("hello")),
+            ("pronouns".to_string(), json!("it/its")),
+        ];
+        let new = apply(&current, &set, &["lud06".to_string()]).unwrap();
+        assert_eq!(
+            Value::Object(new),
+            json!({"name": "bot", "bot": true, "about": "hello", "pronouns": "it/its"})
+        );
+
+        assert!(apply(&current, &set, &["about".to_string()]).is_err());
+        let bad_url = vec![("picture".to_string(), json!("not a url"))];
+        assert!(apply(&current, &bad_url, &[]).is_err());
+    }
+
+    #[test]
+    fn diff_lists_added_removed_and_changed_fields_in_key_order() {
+        let old = profile(json!({"about": "old\nbio", "lud06": "lnurl1", "name": "bot"}));
+        let new = profile(json!({"about": "new", "name": "bot", "website": "https://a.example"}));
+        let lines: Vec<String> = diff(&old, &new).iter().map(|c| c.to_string()).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "~ about: \"old\\nbio\" -> \"new\"",
+                "- lud06: \"lnurl1\"",
+                "+ website: \"https://a.example\"",
+            ]
+        );
+        assert!(diff(&old, &old).is_empty());
+    }
+
+    #[test]
+    fn a_profile_must_be_an_object() {
+        assert!(parse_profile("{\"name\": \"bot\"}").is_ok());
+        assert!(parse_profile("[]").is_err());
+        assert!(parse_profile("not json").is_err());
+    }
+}
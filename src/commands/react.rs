@@ -1,39 +1,215 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use nostr_sdk::prelude::*;
+use std::time::Duration;
 
 use crate::client;
 use crate::config::NostaroConfig;
 use crate::keys;
+use crate::utils::parse_event_reference;
 
-pub async fn run(event_id_str: &str, reaction: &str) -> Result<()> {
+use super::emoji::{self, CustomEmoji};
+
+/// The kind:7 reaction to `target` (NIP-25). A custom emoji adds its NIP-30
+/// `emoji` tag, so clients can render `:shortcode:` as the image.
+///
+/// Pure — it builds the event and talks to no relay.
+fn reaction_builder(
+    target: &Event,
+    reaction: &str,
+    custom: Option<&CustomEmoji>,
+) -> Result<EventBuilder> {
+    let mut tags = vec![
+        Tag::event(target.id),
+        Tag::public_key(target.pubkey),
+        Tag::custom(
+            TagKind::Custom("k".into()),
+            vec![target.kind.as_u16().to_string()],
+        ),
+    ];
+    if let Some(custom) = custom {
+        tags.push(custom.tag()?);
+    }
+    Ok(EventBuilder::new(Kind::Reaction, reaction).tags(tags))
+}
+
+/// The kind:5 deletion request (NIP-09) for our reactions.
+///
+/// Pure — it builds the event and talks to no relay.
+fn deletion_builder(reactions: &[Event]) -> EventBuilder {
+    let mut tags: Vec<Tag> = reactions.iter().map(|r| Tag::event(r.id)).collect();
+    tags.push(Tag::custom(
+        TagKind::Custom("k".into()),
+        vec![Kind::Reaction.as_u16().to_string()],
+    ));
+    EventBuilder::new(Kind::EventDeletion, "").tags(tags)
+}
+
+async fn connect_and_fetch_target(event_id_str: &str) -> Result<(Keys, Client, Event)> {
     let config = NostaroConfig::load()?;
     let keys = keys::keys_from_config(&config)?;
     let nostr_client = client::create_client(&keys, &config).await?;
 
-    let event_id = EventId::parse(event_id_str).or_else(|_| EventId::from_bech32(event_id_str))?;
+    let (event_id, relay_hints) = parse_event_reference(event_id_str)?;
+    for relay in &relay_hints {
+        let _ = nostr_client.add_relay(relay).await;
+    }
+    if !relay_hints.is_empty() {
+        nostr_client.connect().await;
+    }
 
     let target_event = client::fetch_event_by_id(&nostr_client, &event_id)
         .await?
         .ok_or_else(|| anyhow!("Event not found: {}", event_id_str))?;
 
-    let tags = vec![
-        Tag::event(event_id),
-        Tag::public_key(target_event.pubkey),
-        Tag::custom(
-            TagKind::Custom("k".into()),
-            vec![target_event.kind.as_u16().to_string()],
-        ),
-    ];
+    Ok((keys, nostr_client, target_event))
+}
+
+pub async fn run(event_id_str: &str, reaction: &str) -> Result<()> {
+    let (keys, nostr_client, target_event) = connect_and_fetch_target(event_id_str).await?;
+
+    let custom = match emoji::parse_shortcode(reaction) {
+        Some(shortcode) => {
+            let packs = emoji::fetch_packs(&nostr_client, &keys.public_key()).await?;
+            match emoji::resolve(&packs, shortcode) {
+                Some(custom) => Some(custom.clone()),
+                None => bail!(
+                    "Unknown custom emoji :{}: — it is not in your emoji list or sets \
+                     (see `nostaro emoji list`)",
+                    shortcode
+                ),
+            }
+        }
+        None => None,
+    };
 
-    let builder = EventBuilder::new(Kind::Reaction, reaction).tags(tags);
+    let builder = reaction_builder(&target_event, reaction, custom.as_ref())?;
     client::publish(&nostr_client, builder).await?;
 
-    println!(
+    client::report_published(&format!(
         "Reacted with '{}' to event {}",
         reaction,
-        &event_id.to_hex()[..8]
-    );
+        &target_event.id.to_hex()[..8]
+    ));
 
     nostr_client.disconnect().await;
     Ok(())
 }
+
+/// Find our own kind:7 reactions to the note and ask relays to delete them.
+pub async fn unreact(event_id_str: &str) -> Result<()> {
+    let (keys, nostr_client, target_event) = connect_and_fetch_target(event_id_str).await?;
+
+    let filter = Filter::new()
+        .kind(Kind::Reaction)
+        .author(keys.public_key())
+        .event(target_event.id);
+    let mut reactions: Vec<Event> = nostr_client
+        .fetch_events(filter, Duration::from_secs(10))
+        .await?
+        .into_iter()
+        .collect();
+    // `#e` matches any e tag; the reaction's target is the last one (NIP-25).
+    reactions.retain(|reaction| {
+        let targets: Vec<EventId> = reaction
+            .tags
+            .iter()
+            .filter_map(|tag| match tag.as_standardized() {
+                Some(TagStandard::Event { event_id, .. }) => Some(*event_id),
+                _ => None,
+            })
+            .collect();
+        targets.last() == Some(&target_event.id)
+    });
+
+    if reactions.is_empty() {
+        bail!(
+            "No reaction of yours found for event {}",
+            &target_event.id.to_hex()[..8]
+        );
+    }
+
+    client::publish(&nostr_client, deletion_builder(&reactions)).await?;
+
+    let emojis: Vec<&str> = reactions.iter().map(|r| r.content.as_str()).collect();
+    client::report_published(&format!(
+        "Requested deletion of {} reaction(s) ({}) to event {}",
+        reactions.len(),
+        emojis.join(", "),
+        &target_event.id.to_hex()[..8]
+    ));
+
+    nostr_client.disconnect().await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag_vecs(event: &Event) -> Vec<Vec<String>> {
+        event.tags.iter().map(|tag| tag.as_slice().to_vec()).collect()
+    }
+
+    #[test]
+    fn a_custom_emoji_reaction_carries_its_emoji_tag() {
+        let author = Keys::generate();
+        let note = EventBuilder::text_note("hi")
+            .sign_with_keys(&author)
+            .unwrap();
+        let custom = CustomEmoji {
+            shortcode: "blobcat".to_string(),
+            url: "https://example.com/blobcat.png".to_string(),
+        };
+        let reaction = reaction_builder(&note, ":blobcat:", Some(&custom))
+            .unwrap()
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+
+        assert_eq!(reaction.kind, Kind::Reaction);
+        assert_eq!(reaction.content, ":blobcat:");
+        assert_eq!(
+            tag_vecs(&reaction).last().unwrap(),
+            &vec!["emoji", "blobcat", "https://example.com/blobcat.png"]
+        );
+    }
+
+    #[test]
+    fn a_plain_reaction_has_no_emoji_tag() {
+        let note = EventBuilder::text_note("hi")
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+        let reaction = reaction_builder(&note, "🤙", None)
+            .unwrap()
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+        assert!(tag_vecs(&reaction).iter().all(|tag| tag[0] != "emoji"));
+    }
+
+    #[test]
+    fn unreact_deletes_every_reaction_by_id_with_a_k_tag() {
+        let me = Keys::generate();
+        let note = EventBuilder::text_note("hi")
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+        let reactions: Vec<Event> = ["+", "🤙"]
+            .iter()
+            .map(|content| {
+                reaction_builder(&note, content, None)
+                    .unwrap()
+                    .sign_with_keys(&me)
+                    .unwrap()
+            })
+            .collect();
+
+        let deletion = deletion_builder(&reactions).sign_with_keys(&me).unwrap();
+        assert_eq!(deletion.kind, Kind::EventDeletion);
+        assert_eq!(
+            tag_vecs(&deletion),
+            vec![
+                vec!["e".to_string(), reactions[0].id.to_hex()],
+                vec!["e".to_string(), reactions[1].id.to_hex()],
+                vec!["k".to_string(), "7".to_string()],
+            ]
+        );
+    }
+}
@@ -4,8 +4,11 @@ use nostr_sdk::prelude::*;
 use crate::client;
 use crate::config::NostaroConfig;
 use crate::keys;
+use crate::post_options::PostOptions;
+
+pub async fn run(note_id: &str, message: &str, options: &PostOptions) -> Result<()> {
+    let option_tags = options.tags(Timestamp::now())?;
 
-pub async fn run(note_id: &str, message: &str) -> Result<()> {
     let config = NostaroConfig::load()?;
     let keys = keys::keys_from_config(&config)?;
     let nostr_client = client::create_client(&keys, &config).await?;
@@ -17,8 +20,8 @@ pub async fn run(note_id: &str, message: &str) -> Result<()> {
         .ok_or_else(|| anyhow!("Event not found: {}", note_id))?;
 
     println!("Replying to {}...", &event_id.to_hex()[..8]);
-    client::reply_note(&nostr_client, &target_event, message).await?;
-    println!("Reply published successfully!");
+    client::reply_note(&nostr_client, &target_event, message, option_tags).await?;
+    client::report_published("Reply published successfully!");
 
     nostr_client.disconnect().await;
     Ok(())
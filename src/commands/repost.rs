@@ -4,22 +4,172 @@ use nostr_sdk::prelude::*;
 use crate::client;
 use crate::config::NostaroConfig;
 use crate::keys;
+use crate::utils::parse_event_target;
+
+use super::article;
+
+/// A quote post (NIP-18): a kind:1 with the comment, a `nostr:` link to the
+/// target and a `q` tag — an address for addressable events, the id otherwise.
+///
+/// Pure — it builds the event and talks to no relay.
+fn quote_builder(target: &Event, comment: &str, relay_hint: Option<&str>) -> Result<EventBuilder> {
+    let relay = relay_hint.unwrap_or_default();
+    let relays: Vec<String> = relay_hint.map(str::to_string).into_iter().collect();
+
+    let (reference, q_tag) = if (30000..40000).contains(&target.kind.as_u16()) {
+        let identifier = article::tag_value(target, "d").unwrap_or_default();
+        let coordinate = format!(
+            "{}:{}:{}",
+            target.kind.as_u16(),
+            target.pubkey.to_hex(),
+            identifier
+        );
+        (
+            article::naddr(target.kind, target.pubkey, identifier, &relays)?,
+            Tag::parse(["q", coordinate.as_str(), relay])?,
+        )
+    } else {
+        let mut nevent = Nip19Event::new(target.id)
+            .author(target.pubkey)
+            .kind(target.kind);
+        if let Some(relay) = relay_hint {
+            nevent = nevent.relays([RelayUrl::parse(relay)?]);
+        }
+        (
+            nevent.to_bech32()?,
+            Tag::parse([
+                "q",
+                target.id.to_hex().as_str(),
+                relay,
+                target.pubkey.to_hex().as_str(),
+            ])?,
+        )
+    };
+
+    let content = format!("{}\n\nnostr:{}", comment.trim_end(), reference);
+    Ok(EventBuilder::text_note(content).tags([q_tag, Tag::public_key(target.pubkey)]))
+}
+
+/// Where the target can be found: a relay that actually returned it,
+/// preferring one the reference itself named. None when no relay can be
+/// vouched for — an empty hint beats one pointing at a relay without the event.
+fn relay_hint(hints: &[String], sources: &[RelayUrl]) -> Option<String> {
+    hints
+        .iter()
+        .find(|hint| RelayUrl::parse(hint).is_ok_and(|hint| sources.contains(&hint)))
+        .cloned()
+        .or_else(|| sources.first().map(|source| source.to_string()))
+}
+
+/// Repost any event: kind:6 for a text note, kind:16 for everything else, or a
+/// quote post when `comment` is given.
+pub async fn run(target: &str, comment: Option<&str>) -> Result<()> {
+    let parsed = parse_event_target(target)?;
 
-pub async fn run(note_id: &str) -> Result<()> {
     let config = NostaroConfig::load()?;
     let keys = keys::keys_from_config(&config)?;
     let nostr_client = client::create_client(&keys, &config).await?;
 
-    let event_id = EventId::parse(note_id).or_else(|_| EventId::from_bech32(note_id))?;
+    let hints = parsed.relay_hints().to_vec();
+    for relay in &hints {
+        let _ = nostr_client.add_relay(relay).await;
+    }
+    if !hints.is_empty() {
+        nostr_client.connect().await;
+    }
 
-    let target_event = client::fetch_event_by_id(&nostr_client, &event_id)
+    let (target_event, sources) = client::fetch_event_target_with_sources(&nostr_client, &parsed)
         .await?
-        .ok_or_else(|| anyhow!("Event not found: {}", note_id))?;
+        .ok_or_else(|| anyhow!("Event not found: {}", target))?;
+    let relay_hint = relay_hint(&hints, &sources);
 
-    println!("Reposting {}...", &event_id.to_hex()[..8]);
-    client::repost_event(&nostr_client, &target_event).await?;
-    println!("Reposted successfully!");
+    match comment.map(str::trim).filter(|c| !c.is_empty()) {
+        Some(comment) => {
+            println!("Quoting {}...", &target_event.id.to_hex()[..8]);
+            let builder = quote_builder(&target_event, comment, relay_hint.as_deref())?;
+            let output = client::publish(&nostr_client, builder).await?;
+            println!("Event ID: {}", output.id().to_bech32()?);
+            client::report_published("Quote published successfully!");
+        }
+        None => {
+            println!(
+                "Reposting {} (kind:{})...",
+                &target_event.id.to_hex()[..8],
+                target_event.kind.as_u16()
+            );
+            client::repost_event(&nostr_client, &target_event, relay_hint.as_deref()).await?;
+            client::report_published("Reposted successfully!");
+        }
+    }
 
     nostr_client.disconnect().await;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_relay_hint_is_a_relay_that_returned_the_event() {
+        let a = RelayUrl::parse("wss://a.example.com").unwrap();
+        let b = RelayUrl::parse("wss://b.example.com").unwrap();
+        let named = |url: &str| vec![url.to_string()];
+
+        assert_eq!(
+            relay_hint(&named("wss://b.example.com"), &[a.clone(), b.clone()]),
+            Some("wss://b.example.com".to_string())
+        );
+        // A named relay that did not return it is no better than none.
+        assert_eq!(
+            relay_hint(&named("wss://c.example.com"), &[a.clone()]),
+            Some("wss://a.example.com".to_string())
+        );
+        assert_eq!(relay_hint(&named("wss://c.example.com"), &[]), None);
+        assert_eq!(relay_hint(&[], &[]), None);
+    }
+
+    #[test]
+    fn a_comment_turns_the_repost_into_a_quote() {
+        let author = Keys::generate();
+        let note = EventBuilder::text_note("original")
+            .sign_with_keys(&author)
+            .unwrap();
+        let quote = quote_builder(&note, "so true", Some("wss://relay.example.com"))
+            .unwrap()
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+
+        assert_eq!(quote.kind, Kind::TextNote);
+        assert!(quote.content.starts_with("so true\n\nnostr:nevent1"));
+        let tags: Vec<Vec<String>> = quote.tags.iter().map(|t| t.as_slice().to_vec()).collect();
+        assert_eq!(tags[0][0], "q");
+        assert_eq!(tags[0][1], note.id.to_hex());
+        assert_eq!(tags[0][2], "wss://relay.example.com");
+        assert_eq!(tags[1], vec!["p".to_string(), author.public_key().to_hex()]);
+    }
+
+    #[test]
+    fn quoting_an_article_links_its_address() {
+        let author = Keys::generate();
+        let article = EventBuilder::new(Kind::from(30023u16), "# hello")
+            .tags([Tag::identifier("hello")])
+            .sign_with_keys(&author)
+            .unwrap();
+        let quote = quote_builder(&article, "worth a read", None)
+            .unwrap()
+            .sign_with_keys(&Keys::generate())
+            .unwrap();
+
+        assert!(quote.content.contains("nostr:naddr1"));
+        let q = quote.tags.iter().next().unwrap().as_slice().to_vec();
+        assert_eq!(
+            q,
+            vec![
+                "q".to_string(),
+                format!("30023:{}:hello", author.public_key().to_hex()),
+                String::new(),
+            ]
+        );
+    }
+}
@@ -1,40 +1,487 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
 use chrono::{DateTime, Utc};
 use nostr_sdk::prelude::*;
+use std::time::Duration;
 
+use crate::cache::{CacheDb, EventQuery};
 use crate::client;
 use crate::config::NostaroConfig;
 use crate::keys;
 use crate::outln;
 use crate::output;
+use crate::utils::{parse_since, resolve_pubkey, resolve_pubkeys};
+
+use super::{mute, timeline};
+
+/// How long a relay's answer to "do you support NIP-50?" is trusted before its
+/// NIP-11 document is fetched again.
+const NIP50_SUPPORT_TTL_SECS: u64 = 24 * 60 * 60;
+
+/// How long to wait for one relay's NIP-11 document.
+const NIP11_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The structured filters of `search`, as given on the command line.
+#[derive(Debug, Clone, Default, PartialEq, Eq, clap::Args)]
+pub struct SearchArgs {
+    /// Only notes by this author (npub, hex, nprofile or NIP-05; repeatable)
+    #[arg(long = "author", value_name = "PUBKEY")]
+    pub authors: Vec<String>,
+
+    /// Only events of this kind (repeatable; default: 1)
+    #[arg(long = "kind", value_name = "KIND")]
+    pub kinds: Vec<u16>,
+
+    /// Only notes tagged with this hashtag, with or without the `#` (repeatable)
+    #[arg(long = "hashtag", value_name = "TAG")]
+    pub hashtags: Vec<String>,
+
+    /// Only notes that mention this pubkey in a `p` tag (repeatable)
+    #[arg(long = "mentions", value_name = "PUBKEY")]
+    pub mentions: Vec<String>,
+
+    /// Only notes newer than this: a duration back (2d, 12h), a date or a Unix timestamp
+    #[arg(long, value_name = "WHEN")]
+    pub since: Option<String>,
+
+    /// Only notes older than this: a duration back (2d, 12h), a date or a Unix timestamp
+    #[arg(long, value_name = "WHEN")]
+    pub until: Option<String>,
+
+    /// Ask only this relay instead of the configured ones (repeatable)
+    #[arg(long = "relay", value_name = "URL")]
+    pub relays: Vec<String>,
+}
+
+/// [`SearchArgs`] with every pubkey and time resolved.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchFilters {
+    pub authors: Vec<PublicKey>,
+    pub kinds: Vec<Kind>,
+    /// Lowercase, without the `#` (NIP-24 `t` tags are lowercase).
+    pub hashtags: Vec<String>,
+    pub mentions: Vec<PublicKey>,
+    pub since: Option<Timestamp>,
+    pub until: Option<Timestamp>,
+}
+
+impl SearchArgs {
+    /// Resolve pubkeys and times; `now` anchors durations such as `2d`.
+    pub fn resolve(&self, now: Timestamp) -> Result<SearchFilters> {
+        let pubkeys = |inputs: &[String]| -> Result<Vec<PublicKey>> {
+            inputs.iter().map(|input| resolve_pubkey(input)).collect()
+        };
+        let filters = SearchFilters {
+            authors: pubkeys(&self.authors)?,
+            kinds: self.kinds.iter().map(|kind| Kind::from(*kind)).collect(),
+            hashtags: self
+                .hashtags
+                .iter()
+                .map(|tag| tag.trim().trim_start_matches('#').to_lowercase())
+                .filter(|tag| !tag.is_empty())
+                .collect(),
+            mentions: pubkeys(&self.mentions)?,
+            since: self
+                .since
+                .as_deref()
+                .map(|since| parse_since(since, now))
+                .transpose()?,
+            until: self
+                .until
+                .as_deref()
+                .map(|until| parse_since(until, now))
+                .transpose()?,
+        };
+        if let (Some(since), Some(until)) = (filters.since, filters.until) {
+            if since > until {
+                bail!("--since is later than --until");
+            }
+        }
+        Ok(filters)
+    }
+}
+
+impl SearchFilters {
+    /// Whether nothing narrows the search besides the default kind.
+    pub fn is_empty(&self) -> bool {
+        self.authors.is_empty()
+            && self.kinds.is_empty()
+            && self.hashtags.is_empty()
+            && self.mentions.is_empty()
+            && self.since.is_none()
+            && self.until.is_none()
+    }
+
+    /// The kinds searched: the given ones, or text notes.
+    pub fn kinds_or_default(&self) -> Vec<Kind> {
+        if self.kinds.is_empty() {
+            vec![Kind::TextNote]
+        } else {
+            self.kinds.clone()
+        }
+    }
+
+    /// The relay filter. With a `query` it carries a NIP-50 `search` field;
+    /// without one it is a plain author/tag/time filter any relay can answer.
+    ///
+    /// Pure — it only builds the filter.
+    pub fn to_filter(&self, query: Option<&str>, limit: usize) -> Filter {
+        let mut filter = Filter::new().kinds(self.kinds_or_default()).limit(limit);
+        if let Some(query) = query {
+            filter = filter.search(query);
+        }
+        if !self.authors.is_empty() {
+            filter = filter.authors(self.authors.clone());
+        }
+        if !self.hashtags.is_empty() {
+            filter = filter.hashtags(self.hashtags.clone());
+        }
+        if !self.mentions.is_empty() {
+            filter = filter.pubkeys(self.mentions.clone());
+        }
+        if let Some(since) = self.since {
+            filter = filter.since(since);
+        }
+        if let Some(until) = self.until {
+            filter = filter.until(until);
+        }
+        filter
+    }
+
+    /// The same search against the local cache.
+    ///
+    /// Pure — it only translates the filters.
+    pub fn to_event_query(&self, query: Option<&str>, limit: usize) -> EventQuery {
+        let hex = |pubkeys: &[PublicKey]| -> Vec<String> {
+            pubkeys.iter().map(|pk| pk.to_hex()).collect()
+        };
+        EventQuery {
+            text: query.map(str::to_string),
+            authors: hex(&self.authors),
+            kinds: self
+                .kinds_or_default()
+                .iter()
+                .map(|kind| kind.as_u16())
+                .collect(),
+            hashtags: self.hashtags.clone(),
+            mentions: hex(&self.mentions),
+            since: self.since.map(|at| at.as_u64() as i64),
+            until: self.until.map(|at| at.as_u64() as i64),
+            limit,
+        }
+    }
+}
+
+/// What is being searched for, for the progress line.
+fn describe(query: Option<&str>, filters: &SearchFilters) -> String {
+    let mut parts = Vec::new();
+    if let Some(query) = query {
+        parts.push(format!("\"{}\"", query));
+    }
+    if !filters.authors.is_empty() {
+        parts.push(format!("by {} author(s)", filters.authors.len()));
+    }
+    if !filters.kinds.is_empty() {
+        let kinds: Vec<String> = filters
+            .kinds
+            .iter()
+            .map(|kind| kind.as_u16().to_string())
+            .collect();
+        parts.push(format!("kind {}", kinds.join(",")));
+    }
+    for tag in &filters.hashtags {
+        parts.push(format!("#{}", tag));
+    }
+    if !filters.mentions.is_empty() {
+        parts.push(format!("mentioning {} pubkey(s)", filters.mentions.len()));
+    }
+    let day = |at: Timestamp| {
+        DateTime::<Utc>::from_timestamp(at.as_u64() as i64, 0)
+            .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default()
+    };
+    if let Some(since) = filters.since {
+        parts.push(format!("since {}", day(since)));
+    }
+    if let Some(until) = filters.until {
+        parts.push(format!("until {}", day(until)));
+    }
+    parts.join(" ")
+}
+
+/// Whether a remembered `"<checked_at> <0|1>"` answer is still fresh, and
+/// what it was.
+///
+/// Pure — it only reads the stored string.
+fn cached_support(value: &str, now: Timestamp) -> Option<bool> {
+    let (checked_at, supported) = value.split_once(' ')?;
+    let checked_at: u64 = checked_at.parse().ok()?;
+    if now.as_u64().saturating_sub(checked_at) > NIP50_SUPPORT_TTL_SECS {
+        return None;
+    }
+    Some(supported == "1")
+}
+
+fn support_key(relay: &str) -> String {
+    format!("nip11.search.{}", relay.trim_end_matches('/'))
+}
+
+/// The relays among `relays` whose NIP-11 document lists NIP-50. Answers are
+/// remembered in the cache for a day; a relay whose document cannot be
+/// fetched is not known to support search and is left out (and not
+/// remembered, so it is asked again next time).
+async fn nip50_relays(relays: &[String], cache: Option<&CacheDb>) -> Vec<String> {
+    let now = Timestamp::now();
+    let mut supported = Vec::new();
+    let mut lookups = tokio::task::JoinSet::new();
+
+    for relay in relays {
+        let remembered = cache
+            .and_then(|cache| cache.get_state(&support_key(relay)).ok().flatten())
+            .and_then(|value| cached_support(&value, now));
+        match remembered {
+            Some(true) => supported.push(relay.clone()),
+            Some(false) => {}
+            None => {
+                let relay = relay.clone();
+                lookups.spawn(async move {
+                    let info = client::fetch_relay_info(&relay, NIP11_TIMEOUT).await;
+                    (relay, info)
+                });
+            }
+        }
+    }
+
+    while let Some(joined) = lookups.join_next().await {
+        let Ok((relay, info)) = joined else { continue };
+        let Ok(info) = info else { continue };
+        let supports = client::supported_nips(&info).contains(&50);
+        if let Some(cache) = cache {
+            let value = format!("{} {}", now.as_u64(), if supports { 1 } else { 0 });
+            let _ = cache.set_state(&support_key(&relay), &value);
+        }
+        if supports {
+            supported.push(relay);
+        }
+    }
+
+    // Keep the configured order, whatever order the lookups finished in.
+    supported.sort_by_key(|relay| relays.iter().position(|r| r == relay));
+    supported
+}
+
+/// One search result, with what the local index adds to it.
+struct Hit {
+    event: Event,
+    /// The matching part of the content (local search with a query).
+    snippet: Option<String>,
+    /// BM25 score, lower is better (local search with a query).
+    rank: Option<f64>,
+}
+
+impl Hit {
+    fn from_relay(event: Event) -> Self {
+        Self {
+            event,
+            snippet: None,
+            rank: None,
+        }
+    }
+}
+
+/// Search the cached events. Rows whose raw JSON no longer parses are skipped.
+fn search_local(
+    cache: &CacheDb,
+    query: Option<&str>,
+    filters: &SearchFilters,
+    limit: usize,
+) -> Result<Vec<Hit>> {
+    let hits = cache.search_events(&filters.to_event_query(query, limit))?;
+    Ok(hits
+        .into_iter()
+        .filter_map(|hit| {
+            let event = Event::from_json(&hit.event.raw_json).ok()?;
+            Some(Hit {
+                event,
+                snippet: hit.snippet,
+                rank: hit.rank,
+            })
+        })
+        .collect())
+}
+
+/// Ask the relays: NIP-50 relays only when there is a query. `None` sends the
+/// caller to the local cache: when none of the relays could be reached, or
+/// when there is a query and no relay answered it with a match.
+async fn search_relays(
+    config: &NostaroConfig,
+    args: &SearchArgs,
+    query: Option<&str>,
+    filters: &SearchFilters,
+    limit: usize,
+    cache: Option<&CacheDb>,
+) -> Result<Option<(Vec<String>, Vec<Event>)>> {
+    let keys = keys::keys_from_config(config)?;
+    let relays = if args.relays.is_empty() {
+        config.active_relays()
+    } else {
+        args.relays.clone()
+    };
+
+    let asked = match query {
+        Some(_) => {
+            let supported = nip50_relays(&relays, cache).await;
+            if supported.is_empty() {
+                eprintln!(
+                    "None of the relays advertises NIP-50 search ({}); searching the local cache.",
+                    relays.join(", ")
+                );
+                return Ok(None);
+            }
+            for relay in relays.iter().filter(|relay| !supported.contains(relay)) {
+                eprintln!("Skipping {}: no NIP-50 search support", relay);
+            }
+            supported
+        }
+        None => relays,
+    };
+
+    let nostr_client = client::create_client_with_relay_list(&keys, &asked).await?;
+    let filter = filters.to_filter(query, limit);
+    let result = client::search_events(&nostr_client, filter, Some(&asked), limit).await;
+    // A relay that cannot be reached does not fail the fetch: it just answers
+    // nothing once the timeout runs out, so that is checked for here.
+    let connected = nostr_client
+        .relays()
+        .await
+        .values()
+        .any(|relay| relay.is_connected());
+    nostr_client.disconnect().await;
+
+    if !connected {
+        eprintln!(
+            "Could not connect to {}; searching the local cache.",
+            asked.join(", ")
+        );
+        return Ok(None);
+    }
+    match result {
+        Ok(events) if events.is_empty() && query.is_some() => {
+            eprintln!("No NIP-50 relay returned a match; searching the local cache.");
+            Ok(None)
+        }
+        Ok(events) => Ok(Some((asked, events))),
+        Err(err) if query.is_some() => {
+            eprintln!(
+                "No NIP-50 relay answered ({}); searching the local cache.",
+                err
+            );
+            Ok(None)
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// `search [query] [filters]`: NIP-50 full-text search when there is a query
+/// (sent only to relays that advertise NIP-50), a plain filter query when
+/// there is not. `local` searches the cache's full-text index instead, which
+/// is also where a query goes when no NIP-50 relay is there to answer it.
+pub async fn run(query: Option<&str>, limit: usize, local: bool, args: &SearchArgs) -> Result<()> {
+    let query = query.map(str::trim).filter(|query| !query.is_empty());
+    // NIP-05 addresses need a lookup, so they are turned into hex up front and
+    // the rest of the resolution stays offline.
+    let hex = |pubkeys: Vec<PublicKey>| -> Vec<String> {
+        pubkeys.iter().map(PublicKey::to_hex).collect()
+    };
+    let args = SearchArgs {
+        authors: hex(resolve_pubkeys(&args.authors).await?),
+        mentions: hex(resolve_pubkeys(&args.mentions).await?),
+        ..args.clone()
+    };
+    let filters = args.resolve(Timestamp::now())?;
+    if query.is_none() && filters.is_empty() {
+        bail!(
+            "give a search query or at least one filter \
+             (--author, --kind, --hashtag, --mentions, --since, --until)"
+        );
+    }
 
-pub async fn run(query: &str, limit: usize) -> Result<()> {
     let config = NostaroConfig::load()?;
-    let keys = keys::keys_from_config(&config)?;
-    let nostr_client = client::create_client(&keys, &config).await?;
+    let cache = CacheDb::open().ok();
 
-    println!("Searching for \"{}\"...\n", query);
+    println!("Searching for {}...\n", describe(query, &filters));
 
-    let events = client::search_notes(&nostr_client, query, limit).await?;
+    let from_relays = if local {
+        None
+    } else {
+        search_relays(&config, &args, query, &filters, limit, cache.as_ref()).await?
+    };
+    let (source, relays, hits) = match from_relays {
+        Some((relays, events)) => {
+            // Kept so they can be searched offline later.
+            if let Some(cache) = cache.as_ref() {
+                timeline::cache_events(cache, &events);
+            }
+            let hits = events.into_iter().map(Hit::from_relay).collect();
+            ("relays", relays, hits)
+        }
+        None => {
+            let cache = cache
+                .as_ref()
+                .ok_or_else(|| anyhow!("the local cache could not be opened"))?;
+            (
+                "local",
+                Vec::new(),
+                search_local(cache, query, &filters, limit)?,
+            )
+        }
+    };
+
+    // A local search stays offline, mute list included.
+    let mutes = if source == "local" {
+        mute::load_cached(&config)
+    } else {
+        mute::load_with_config(&config).await
+    };
+    let hits: Vec<Hit> = hits
+        .into_iter()
+        .filter(|hit| !mutes.is_muted(&hit.event))
+        .collect();
 
     // No match is still a result: --out gets an empty listing rather than no
     // file at all, so the body is emitted in every case.
-    if events.is_empty() {
-        println!("No notes found matching \"{}\".", query);
+    if hits.is_empty() {
+        println!("No notes found.");
     }
 
     if output::is_json() {
-        let notes: Result<Vec<serde_json::Value>> = events
+        let notes: Result<Vec<serde_json::Value>> = hits
             .iter()
-            .map(|event| Ok(serde_json::to_value(event)?))
+            .map(|hit| Ok(serde_json::to_value(&hit.event)?))
             .collect();
-        output::write_json(&serde_json::json!({
-            "count": events.len(),
+        let mut document = serde_json::json!({
+            "query": query,
+            "source": source,
+            "relays": relays,
+            "count": hits.len(),
             "events": notes?,
-        }))?;
+        });
+        if source == "local" {
+            document["matches"] = hits
+                .iter()
+                .map(|hit| {
+                    serde_json::json!({
+                        "id": hit.event.id.to_hex(),
+                        "snippet": hit.snippet,
+                        "rank": hit.rank,
+                    })
+                })
+                .collect();
+        }
+        output::write_json(&document)?;
     } else {
         output::open_body()?;
-        for event in &events {
+        for hit in &hits {
+            let event = &hit.event;
             let npub = event.pubkey.to_bech32()?;
             let short_npub = &npub;
             let timestamp = event.created_at.as_u64() as i64;
@@ -43,17 +490,103 @@ pub async fn run(query: &str, limit: usize) -> Result<()> {
                 .unwrap_or_else(|| "unknown".to_string());
 
             outln!("[{}] {}", short_npub, datetime)?;
-            outln!("{}", event.content)?;
+            outln!("{}", hit.snippet.as_deref().unwrap_or(&event.content))?;
             let note_id = event.id.to_bech32()?;
             outln!("  id: {}", note_id)?;
             outln!("{}", "-".repeat(60))?;
         }
     }
 
-    if !events.is_empty() {
-        println!("\nFound {} note(s).", events.len());
+    if !hits.is_empty() {
+        let from = if source == "local" {
+            " in the local cache"
+        } else {
+            ""
+        };
+        println!("\nFound {} note(s){}.", hits.len(), from);
     }
 
-    nostr_client.disconnect().await;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_resolve_pubkeys_hashtags_and_durations() {
+        let keys = Keys::generate();
+        let now = Timestamp::from(1_700_000_000);
+        let args = SearchArgs {
+            authors: vec![keys.public_key().to_bech32().unwrap()],
+            hashtags: vec!["#Nostr".to_string(), "rust".to_string()],
+            since: Some("7d".to_string()),
+            until: Some("1d".to_string()),
+            ..SearchArgs::default()
+        };
+        let filters = args.resolve(now).unwrap();
+        assert_eq!(filters.authors, vec![keys.public_key()]);
+        assert_eq!(filters.hashtags, vec!["nostr", "rust"]);
+        assert_eq!(filters.since.unwrap().as_u64(), 1_700_000_000 - 7 * 86400);
+        assert_eq!(filters.until.unwrap().as_u64(), 1_700_000_000 - 86400);
+
+        let backwards = SearchArgs {
+            since: Some("1d".to_string()),
+            until: Some("7d".to_string()),
+            ..SearchArgs::default()
+        };
+        assert!(backwards.resolve(now).is_err());
+        assert!(SearchArgs::default().resolve(now).unwrap().is_empty());
+    }
+
+    #[test]
+    fn only_a_query_makes_the_filter_a_nip50_search() {
+        let keys = Keys::generate();
+        let filters = SearchFilters {
+            authors: vec![keys.public_key()],
+            hashtags: vec!["nostr".to_string()],
+            ..SearchFilters::default()
+        };
+
+        let plain = filters.to_filter(None, 20);
+        assert!(plain.search.is_none());
+        assert_eq!(plain.kinds, Some([Kind::TextNote].into_iter().collect()));
+        assert!(plain.authors.unwrap().contains(&keys.public_key()));
+        assert_eq!(plain.limit, Some(20));
+
+        let search = filters.to_filter(Some("relay"), 20);
+        assert_eq!(search.search.as_deref(), Some("relay"));
+    }
+
+    #[test]
+    fn the_local_query_carries_the_same_filters() {
+        let keys = Keys::generate();
+        let filters = SearchFilters {
+            authors: vec![keys.public_key()],
+            hashtags: vec!["nostr".to_string()],
+            since: Some(Timestamp::from(100)),
+            ..SearchFilters::default()
+        };
+        let query = filters.to_event_query(Some("relay"), 5);
+        assert_eq!(query.text.as_deref(), Some("relay"));
+        assert_eq!(query.authors, vec![keys.public_key().to_hex()]);
+        assert_eq!(
+            query.kinds,
+            vec![1],
+            "text notes unless --kind says otherwise"
+        );
+        assert_eq!(query.hashtags, vec!["nostr"]);
+        assert_eq!(query.since, Some(100));
+        assert_eq!(query.until, None);
+        assert_eq!(query.limit, 5);
+    }
+
+    #[test]
+    fn remembered_support_expires_after_a_day() {
+        let now = Timestamp::from(1_700_000_000);
+        assert_eq!(cached_support("1699990000 1", now), Some(true));
+        assert_eq!(cached_support("1699990000 0", now), Some(false));
+        assert_eq!(cached_support("1600000000 1", now), None);
+        assert_eq!(cached_support("garbage", now), None);
+    }
+}
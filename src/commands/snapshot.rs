@@ -0,0 +1,88 @@
+//! Our own versions of replaceable events (kind:0, kind:3), kept in the
+//! cache's `snapshots` table for `profile history` and `follow history`, and
+//! picked out again by number or id for `diff` and `restore`.
+
+use anyhow::{bail, Result};
+use nostr_sdk::prelude::*;
+
+use crate::cache::CacheDb;
+
+/// Keep a version of one of our replaceable events (kind:0, kind:3) for
+/// `profile history` and `follow history`.
+pub(crate) fn snapshot_event(cache: &CacheDb, event: &Event) {
+    let _ = cache.store_snapshot(
+        &event.id.to_hex(),
+        &event.pubkey.to_hex(),
+        event.kind.as_u16(),
+        event.created_at.as_u64() as i64,
+        &event.as_json(),
+    );
+}
+
+/// Our recorded versions of a replaceable event, oldest first. Version numbers
+/// count from 1 in this order.
+pub(crate) fn versions(cache: &CacheDb, pubkey: &PublicKey, kind: Kind) -> Result<Vec<Event>> {
+    cache
+        .snapshots(&pubkey.to_hex(), kind.as_u16())?
+        .iter()
+        .map(|snapshot| Ok(Event::from_json(&snapshot.raw_json)?))
+        .collect()
+}
+
+/// Pick a version by number (`3` or `v3`) or by event id (hex or `note1`),
+/// returning its number too.
+///
+/// Pure — it searches the list it is given.
+pub(crate) fn find_version<'a>(versions: &'a [Event], version: &str) -> Result<(usize, &'a Event)> {
+    let version = version.trim();
+    let wanted = version.strip_prefix('v').unwrap_or(version);
+    if let Ok(number) = wanted.parse::<usize>() {
+        return match versions.get(number.wrapping_sub(1)) {
+            Some(event) => Ok((number, event)),
+            None => bail!(
+                "no version {}: {} version(s) are recorded",
+                number,
+                versions.len()
+            ),
+        };
+    }
+    let id = EventId::parse(version)
+        .or_else(|_| EventId::from_bech32(version))
+        .map_err(|_| anyhow::anyhow!("not a version number or event id: {}", version))?;
+    versions
+        .iter()
+        .enumerate()
+        .find(|(_, event)| event.id == id)
+        .map(|(index, event)| (index + 1, event))
+        .ok_or_else(|| anyhow::anyhow!("no recorded version has the id {}", version))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_are_found_by_number_or_id() {
+        let keys = Keys::generate();
+        let versions: Vec<Event> = ["{\"name\":\"a\"}", "{\"name\":\"b\"}"]
+            .iter()
+            .map(|content| {
+                EventBuilder::new(Kind::Metadata, *content)
+                    .sign_with_keys(&keys)
+                    .unwrap()
+            })
+            .collect();
+
+        assert_eq!(find_version(&versions, "1").unwrap().1.id, versions[0].id);
+        assert_eq!(find_version(&versions, "v2").unwrap().0, 2);
+        let note = versions[1].id.to_bech32().unwrap();
+        assert_eq!(find_version(&versions, &note).unwrap().0, 2);
+        assert_eq!(
+            find_version(&versions, &versions[0].id.to_hex()).unwrap().0,
+            1
+        );
+        assert!(find_version(&versions, "0").is_err());
+        assert!(find_version(&versions, "3").is_err());
+        assert!(find_version(&versions, "latest").is_err());
+    }
+}
@@ -0,0 +1,837 @@
+//! `thread <note>`: the whole conversation a note belongs to, as one tree.
+//!
+//! The note is walked up to its NIP-10 root, every reply to that root is
+//! fetched (page by page, so a long discussion is not cut at the relay's
+//! default limit), and the replies are hung under their parents. Replies whose
+//! parent could not be found are kept under a placeholder rather than dropped,
+//! so the shape of the discussion survives a relay that lost one note.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use nostr_sdk::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use crate::cache::CacheDb;
+use crate::client;
+use crate::config::NostaroConfig;
+use crate::keys;
+use crate::outln;
+use crate::output;
+use crate::post_options;
+use crate::utils::parse_event_reference;
+
+use super::mute::{self, Mutes};
+use super::timeline;
+
+/// Events asked for per page when collecting replies.
+const PAGE_SIZE: usize = 500;
+
+/// Upper bound on pages per query, so a relay that keeps answering with the
+/// same page cannot keep the command busy forever.
+const MAX_PAGES: usize = 20;
+
+/// Ids per `#e` filter. Relays cap filter sizes; this stays well under the
+/// common limits.
+const IDS_PER_FILTER: usize = 200;
+
+/// Rounds of "fetch replies to what we know, fetch parents we are missing".
+/// Each round only fires when the previous one found something new.
+const MAX_ROUNDS: usize = 8;
+
+/// The `e` tags of an event, as `(id, marker)` in tag order. Markers other
+/// than `root`/`reply` (i.e. `mention`) are returned too so callers can skip
+/// them.
+fn e_tags(event: &Event) -> Vec<(EventId, Option<String>)> {
+    event
+        .tags
+        .iter()
+        .filter_map(|tag| match tag.as_slice() {
+            [name, id, rest @ ..] if name == "e" => {
+                let id = EventId::from_hex(id).ok()?;
+                let marker = rest.get(1).filter(|m| !m.is_empty()).cloned();
+                Some((id, marker))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// The NIP-10 root of `event`, or `None` when the event is not a reply.
+///
+/// Marked tags win. Without markers the deprecated positional scheme applies:
+/// the first `e` tag is the root.
+pub fn root_id(event: &Event) -> Option<EventId> {
+    let tags = e_tags(event);
+    if let Some((id, _)) = tags.iter().find(|(_, m)| m.as_deref() == Some("root")) {
+        return Some(*id);
+    }
+    if tags.iter().any(|(_, m)| m.is_some()) {
+        // Marked, but without a root: a `reply` alone points at the root too.
+        return tags
+            .iter()
+            .find(|(_, m)| m.as_deref() == Some("reply"))
+            .map(|(id, _)| *id);
+    }
+    tags.first().map(|(id, _)| *id)
+}
+
+/// The NIP-10 parent of `event` (the note it directly answers), or `None` when
+/// the event is not a reply.
+///
+/// `reply` wins, then `root` (a direct reply to the root only marks the
+/// root). Without markers the last `e` tag is the parent.
+pub fn parent_id(event: &Event) -> Option<EventId> {
+    let tags = e_tags(event);
+    if let Some((id, _)) = tags.iter().find(|(_, m)| m.as_deref() == Some("reply")) {
+        return Some(*id);
+    }
+    if let Some((id, _)) = tags.iter().find(|(_, m)| m.as_deref() == Some("root")) {
+        return Some(*id);
+    }
+    if tags.iter().any(|(_, m)| m.is_some()) {
+        // Only `mention`s: a quote, not a reply.
+        return None;
+    }
+    tags.last().map(|(id, _)| *id)
+}
+
+/// The event a reaction or zap receipt is about: the last `e` tag (NIP-25).
+pub(crate) fn target_id(event: &Event) -> Option<EventId> {
+    e_tags(event).last().map(|(id, _)| *id)
+}
+
+/// The amount of a bolt11 invoice in millisatoshis, read from its
+/// human-readable part (`lnbc2500u1...` is 250,000 sats). `None` for invoices
+/// without an amount or that do not parse.
+pub fn bolt11_msats(invoice: &str) -> Option<u64> {
+    let invoice = invoice.trim().to_ascii_lowercase();
+    let hrp = &invoice[..invoice.rfind('1')?];
+    let rest = hrp.strip_prefix("ln")?;
+    let amount = rest.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+    if amount.is_empty() {
+        return None;
+    }
+
+    let (digits, multiplier) = match amount.chars().last()? {
+        c if c.is_ascii_digit() => (amount, None),
+        c => (&amount[..amount.len() - 1], Some(c)),
+    };
+    let value: u64 = digits.parse().ok()?;
+    // 1 BTC = 100_000_000_000 msat.
+    match multiplier {
+        None => value.checked_mul(100_000_000_000),
+        Some('m') => value.checked_mul(100_000_000),
+        Some('u') => value.checked_mul(100_000),
+        Some('n') => value.checked_mul(100),
+        Some('p') => value.is_multiple_of(10).then_some(value / 10),
+        Some(_) => None,
+    }
+}
+
+/// The amount a zap receipt (kind:9735) paid, in millisatoshis: the invoice's
+/// amount, or the zap request's `amount` tag when the invoice has none.
+pub fn zap_msats(receipt: &Event) -> Option<u64> {
+    let tag_value = |name: &str| {
+        receipt.tags.iter().find_map(|tag| match tag.as_slice() {
+            [n, value, ..] if n == name => Some(value.clone()),
+            _ => None,
+        })
+    };
+
+    if let Some(msats) = tag_value("bolt11").as_deref().and_then(bolt11_msats) {
+        return Some(msats);
+    }
+
+    let request: serde_json::Value = serde_json::from_str(&tag_value("description")?).ok()?;
+    request["tags"].as_array()?.iter().find_map(|tag| {
+        let tag = tag.as_array()?;
+        if tag.first()?.as_str()? != "amount" {
+            return None;
+        }
+        tag.get(1)?.as_str()?.parse().ok()
+    })
+}
+
+/// Reaction and zap totals for one note.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Engagement {
+    pub reactions: usize,
+    pub zaps: usize,
+    pub zap_msats: u64,
+}
+
+/// Tally reactions (kind:7) and zap receipts (kind:9735) by the note they are
+/// about.
+///
+/// Pure — it counts events that have already been fetched.
+fn tally(events: &[Event]) -> HashMap<EventId, Engagement> {
+    let mut totals: HashMap<EventId, Engagement> = HashMap::new();
+    for event in events {
+        let Some(target) = target_id(event) else {
+            continue;
+        };
+        let entry = totals.entry(target).or_default();
+        if event.kind == Kind::Reaction {
+            entry.reactions += 1;
+        } else if event.kind == Kind::from(9735u16) {
+            entry.zaps += 1;
+            entry.zap_msats += zap_msats(event).unwrap_or(0);
+        }
+    }
+    totals
+}
+
+/// One node of the reply tree. `event` is `None` for a note that is referenced
+/// as a parent (or as the root) but could not be fetched.
+#[derive(Debug, Clone)]
+pub struct ThreadNode {
+    pub id: EventId,
+    pub event: Option<Event>,
+    pub replies: Vec<ThreadNode>,
+}
+
+impl ThreadNode {
+    fn len(&self) -> usize {
+        1 + self.replies.iter().map(ThreadNode::len).sum::<usize>()
+    }
+}
+
+/// Hang `events` under their NIP-10 parents, starting from `root`.
+///
+/// A reply whose parent is not among `events` gets a placeholder for that
+/// parent, attached to the root (where the missing note sat is unknown).
+/// Children are ordered oldest first. Events that are not reachable from the
+/// root — i.e. a cycle of forged tags — are left out.
+///
+/// Pure — it arranges events that have already been fetched.
+fn build_tree(root: EventId, events: &HashMap<EventId, Event>) -> ThreadNode {
+    let mut children: HashMap<EventId, Vec<EventId>> = HashMap::new();
+    let mut placeholders: HashSet<EventId> = HashSet::new();
+
+    for event in events.values() {
+        if event.id == root {
+            continue;
+        }
+        let parent = parent_id(event)
+            .filter(|parent| *parent != event.id)
+            .unwrap_or(root);
+        if parent != root && !events.contains_key(&parent) && placeholders.insert(parent) {
+            children.entry(root).or_default().push(parent);
+        }
+        children.entry(parent).or_default().push(event.id);
+    }
+
+    for ids in children.values_mut() {
+        ids.sort_by_key(|id| {
+            (
+                events.get(id).map(|e| e.created_at).unwrap_or_default(),
+                *id,
+            )
+        });
+    }
+
+    fn grow(
+        id: EventId,
+        events: &HashMap<EventId, Event>,
+        children: &HashMap<EventId, Vec<EventId>>,
+        visited: &mut HashSet<EventId>,
+    ) -> ThreadNode {
+        visited.insert(id);
+        let mut replies = Vec::new();
+        for child in children.get(&id).into_iter().flatten() {
+            if !visited.contains(child) {
+                replies.push(grow(*child, events, children, visited));
+            }
+        }
+        ThreadNode {
+            id,
+            event: events.get(&id).cloned(),
+            replies,
+        }
+    }
+
+    grow(root, events, &children, &mut HashSet::new())
+}
+
+/// Every event matching `filter`, following `until` back page by page.
+pub(crate) async fn fetch_all_pages(nostr_client: &Client, filter: Filter) -> Result<Vec<Event>> {
+    let mut seen: HashSet<EventId> = HashSet::new();
+    let mut all = Vec::new();
+    let mut until: Option<Timestamp> = None;
+
+    for _ in 0..MAX_PAGES {
+        let mut page_filter = filter.clone().limit(PAGE_SIZE);
+        if let Some(until) = until {
+            page_filter = page_filter.until(until);
+        }
+        let page: Vec<Event> = nostr_client
+            .fetch_events(page_filter, Duration::from_secs(10))
+            .await?
+            .into_iter()
+            .collect();
+
+        let full = page.len() >= PAGE_SIZE;
+        let oldest = page.iter().map(|e| e.created_at).min();
+        let mut new = 0;
+        for event in page {
+            if seen.insert(event.id) {
+                all.push(event);
+                new += 1;
+            }
+        }
+
+        // `until` is inclusive, so the oldest second of a page comes back on
+        // the next one; a page with nothing new means we are done.
+        match oldest {
+            Some(oldest) if full && new > 0 => until = Some(oldest),
+            _ => break,
+        }
+    }
+
+    Ok(all)
+}
+
+/// `kinds` events that reference any of `ids` with an `e` tag, in batches.
+async fn fetch_referencing(
+    nostr_client: &Client,
+    kinds: Vec<Kind>,
+    ids: &[EventId],
+) -> Result<Vec<Event>> {
+    let mut events = Vec::new();
+    for chunk in ids.chunks(IDS_PER_FILTER) {
+        let filter = Filter::new().kinds(kinds.clone()).events(chunk.to_vec());
+        events.extend(fetch_all_pages(nostr_client, filter).await?);
+    }
+    Ok(events)
+}
+
+/// Collect the root and every reply reachable from it: replies to anything
+/// already known (old clients tag only the parent, not the root), and parents
+/// that are referenced but not yet seen. Expired notes (NIP-40) are left out.
+async fn collect_thread(
+    nostr_client: &Client,
+    root: EventId,
+    start: Event,
+) -> Result<HashMap<EventId, Event>> {
+    let now = Timestamp::now();
+    let mut events: HashMap<EventId, Event> = HashMap::new();
+    let mut queried_children: HashSet<EventId> = HashSet::new();
+    let mut queried_ids: HashSet<EventId> = HashSet::new();
+
+    let keep = |events: &mut HashMap<EventId, Event>, event: Event| -> bool {
+        if post_options::is_expired(&event, now) || events.contains_key(&event.id) {
+            return false;
+        }
+        events.insert(event.id, event);
+        true
+    };
+
+    keep(&mut events, start);
+    if !events.contains_key(&root) {
+        queried_ids.insert(root);
+        if let Some(event) = client::fetch_event_by_id(nostr_client, &root).await? {
+            keep(&mut events, event);
+        }
+    }
+
+    for _ in 0..MAX_ROUNDS {
+        let mut found = false;
+
+        let mut parents_of: Vec<EventId> = vec![root];
+        parents_of.extend(events.keys().copied());
+        parents_of.retain(|id| queried_children.insert(*id));
+        for reply in fetch_referencing(nostr_client, vec![Kind::TextNote], &parents_of).await? {
+            // Only replies belong in the tree; a note that merely quotes one
+            // of these (`mention`) is not part of the conversation.
+            if parent_id(&reply).is_some() {
+                found |= keep(&mut events, reply);
+            }
+        }
+
+        let missing: Vec<EventId> = events
+            .values()
+            .filter_map(parent_id)
+            .filter(|id| !events.contains_key(id) && queried_ids.insert(*id))
+            .collect();
+        for chunk in missing.chunks(IDS_PER_FILTER) {
+            let filter = Filter::new().ids(chunk.to_vec());
+            for event in nostr_client
+                .fetch_events(filter, Duration::from_secs(10))
+                .await?
+            {
+                found |= keep(&mut events, event);
+            }
+        }
+
+        if !found {
+            break;
+        }
+    }
+
+    Ok(events)
+}
+
+/// The cached display name (or name) for `pubkey`, if the cache has one.
+pub(crate) fn cached_name(cache: Option<&CacheDb>, pubkey: &PublicKey) -> Option<String> {
+    cache
+        .and_then(|cache| cache.get_profile(&pubkey.to_hex()).ok().flatten())
+        .and_then(|profile| profile.display_name.or(profile.name))
+        .filter(|name| !name.is_empty())
+}
+
+pub(crate) fn format_sats(msats: u64) -> String {
+    let sats = (msats / 1000).to_string();
+    let mut out = String::new();
+    for (i, ch) in sats.chars().enumerate() {
+        if i > 0 && (sats.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(ch);
+    }
+    out
+}
+
+/// The nested `--out-format json` document. Each node is
+/// `{id, missing, event, author, reactions, zaps, zap_sats, replies}`; a
+/// missing node has `event` and `author` set to `null`.
+///
+/// Pure — it renders what has already been fetched; the cache is only read.
+fn to_json(
+    tree: &ThreadNode,
+    focus: EventId,
+    engagement: &HashMap<EventId, Engagement>,
+    cache: Option<&CacheDb>,
+) -> Result<serde_json::Value> {
+    fn node_json(
+        node: &ThreadNode,
+        engagement: &HashMap<EventId, Engagement>,
+        cache: Option<&CacheDb>,
+    ) -> Result<serde_json::Value> {
+        let totals = engagement.get(&node.id).copied().unwrap_or_default();
+        let author = match &node.event {
+            Some(event) => serde_json::json!({
+                "npub": event.pubkey.to_bech32()?,
+                "name": cached_name(cache, &event.pubkey),
+            }),
+            None => serde_json::Value::Null,
+        };
+        let replies = node
+            .replies
+            .iter()
+            .map(|reply| node_json(reply, engagement, cache))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(serde_json::json!({
+            "id": node.id.to_hex(),
+            "missing": node.event.is_none(),
+            "event": node.event.as_ref().map(serde_json::to_value).transpose()?,
+            "author": author,
+            "reactions": totals.reactions,
+            "zaps": totals.zaps,
+            "zap_sats": totals.zap_msats / 1000,
+            "replies": replies,
+        }))
+    }
+
+    Ok(serde_json::json!({
+        "focus": focus.to_hex(),
+        "count": tree.len(),
+        "root": node_json(tree, engagement, cache)?,
+    }))
+}
+
+/// The indented text rendering, one block per note. The note the command was
+/// asked about is marked with `<<`.
+fn render_text(
+    node: &ThreadNode,
+    depth: usize,
+    focus: EventId,
+    engagement: &HashMap<EventId, Engagement>,
+    cache: Option<&CacheDb>,
+) -> Result<()> {
+    let indent = "  ".repeat(depth);
+    let note_id = node.id.to_bech32()?;
+
+    match &node.event {
+        None => outln!("{}[missing note {}]", indent, note_id)?,
+        Some(event) => {
+            let npub = event.pubkey.to_bech32()?;
+            let author = match cached_name(cache, &event.pubkey) {
+                Some(name) => format!("{} ({})", name, npub),
+                None => npub,
+            };
+            let datetime = DateTime::<Utc>::from_timestamp(event.created_at.as_u64() as i64, 0)
+                .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+
+            let mut counts = Vec::new();
+            let totals = engagement.get(&node.id).copied().unwrap_or_default();
+            if totals.reactions > 0 {
+                counts.push(format!("♥ {}", totals.reactions));
+            }
+            if totals.zaps > 0 {
+                counts.push(format!(
+                    "⚡ {} ({} sats)",
+                    totals.zaps,
+                    format_sats(totals.zap_msats)
+                ));
+            }
+            let counts = if counts.is_empty() {
+                String::new()
+            } else {
+                format!("  {}", counts.join("  "))
+            };
+            let marker = if node.id == focus { "  <<" } else { "" };
+
+            outln!("{}[{}] {}{}{}", indent, author, datetime, counts, marker)?;
+            for line in post_options::display_content(event).lines() {
+                outln!("{}  {}", indent, line)?;
+            }
+            outln!("{}  id: {}", indent, note_id)?;
+        }
+    }
+
+    for reply in &node.replies {
+        render_text(reply, depth + 1, focus, engagement, cache)?;
+    }
+    Ok(())
+}
+
+/// Drop muted replies and everything under them: an answer to a hidden note
+/// makes no sense on its own. The notes in `keep` (the root, and the note that
+/// was asked for) stay whatever the mute list says. Returns how many went.
+///
+/// Pure — it filters what has already been collected.
+fn drop_muted(events: &mut HashMap<EventId, Event>, keep: &[EventId], mutes: &Mutes) -> usize {
+    let before = events.len();
+    let mut hidden: HashSet<EventId> = events
+        .values()
+        .filter(|event| !keep.contains(&event.id) && mutes.is_muted(event))
+        .map(|event| event.id)
+        .collect();
+    loop {
+        let below: Vec<EventId> = events
+            .values()
+            .filter(|event| !keep.contains(&event.id) && !hidden.contains(&event.id))
+            .filter(|event| parent_id(event).is_some_and(|parent| hidden.contains(&parent)))
+            .map(|event| event.id)
+            .collect();
+        if below.is_empty() {
+            break;
+        }
+        hidden.extend(below);
+    }
+    events.retain(|id, _| !hidden.contains(id));
+    before - events.len()
+}
+
+pub async fn run(note: &str) -> Result<()> {
+    let config = NostaroConfig::load()?;
+    let keys = keys::keys_from_config(&config)?;
+    let nostr_client = client::create_client(&keys, &config).await?;
+
+    let (event_id, relay_hints) = parse_event_reference(note)?;
+    for relay in &relay_hints {
+        let _ = nostr_client.add_relay(relay).await;
+    }
+    if !relay_hints.is_empty() {
+        nostr_client.connect().await;
+    }
+
+    println!("Fetching thread...\n");
+
+    let start = client::fetch_event_by_id(&nostr_client, &event_id)
+        .await?
+        .ok_or_else(|| anyhow!("Event not found: {}", note))?;
+    let root = root_id(&start).unwrap_or(start.id);
+
+    let mut events = collect_thread(&nostr_client, root, start).await?;
+    let mutes = mute::load(&nostr_client, &keys).await;
+    let hidden = drop_muted(&mut events, &[root, event_id], &mutes);
+    if hidden > 0 {
+        eprintln!("{} muted note(s) hidden.", hidden);
+    }
+    let tree = build_tree(root, &events);
+
+    let ids: Vec<EventId> = events.keys().copied().collect();
+    let engagement_events = fetch_referencing(
+        &nostr_client,
+        vec![Kind::Reaction, Kind::from(9735u16)],
+        &ids,
+    )
+    .await?;
+    let engagement = tally(&engagement_events);
+
+    if let Ok(cache) = CacheDb::open() {
+        let authors: Vec<PublicKey> = events
+            .values()
+            .map(|e| e.pubkey)
+            .collect::<HashSet<PublicKey>>()
+            .into_iter()
+            .collect();
+        let _ = timeline::fetch_and_cache_profiles(&nostr_client, authors, &cache).await;
+
+        for event in events.values() {
+            let tags_json = serde_json::to_string(&event.tags).unwrap_or_default();
+            let _ = cache.store_event(
+                &event.id.to_hex(),
+                &event.pubkey.to_hex(),
+                event.kind.as_u16(),
+                &event.content,
+                event.created_at.as_u64() as i64,
+                &tags_json,
+                &event.as_json(),
+            );
+        }
+    }
+    let cache = CacheDb::open().ok();
+
+    if output::is_json() {
+        output::write_json(&to_json(&tree, event_id, &engagement, cache.as_ref())?)?;
+    } else {
+        output::open_body()?;
+        render_text(&tree, 0, event_id, &engagement, cache.as_ref())?;
+    }
+
+    println!("\nShowing {} note(s) in the thread.", tree.len());
+
+    nostr_client.disconnect().await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn note(keys: &Keys, content: &str, tags: Vec<Tag>, at: u64) -> Event {
+        EventBuilder::text_note(content)
+            .tags(tags)
+            .custom_created_at(Timestamp::from(at))
+            .sign_with_keys(keys)
+            .unwrap()
+    }
+
+    fn e(id: EventId, marker: &str) -> Tag {
+        Tag::parse(["e", &id.to_hex(), "", marker]).unwrap()
+    }
+
+    fn by_id(events: &[&Event]) -> HashMap<EventId, Event> {
+        events.iter().map(|e| (e.id, (*e).clone())).collect()
+    }
+
+    fn shape(node: &ThreadNode) -> String {
+        let name = match &node.event {
+            Some(event) => event.content.clone(),
+            None => "?".to_string(),
+        };
+        if node.replies.is_empty() {
+            name
+        } else {
+            let replies: Vec<String> = node.replies.iter().map(shape).collect();
+            format!("{}({})", name, replies.join(" "))
+        }
+    }
+
+    #[test]
+    fn marked_tags_name_the_root_and_the_parent() {
+        let keys = Keys::generate();
+        let root = note(&keys, "root", vec![], 1);
+        let first = note(&keys, "first", vec![e(root.id, "root")], 2);
+        let second = note(
+            &keys,
+            "second",
+            vec![e(root.id, "root"), e(first.id, "reply")],
+            3,
+        );
+
+        assert_eq!(root_id(&root), None);
+        assert_eq!(parent_id(&root), None);
+        assert_eq!(root_id(&first), Some(root.id));
+        assert_eq!(parent_id(&first), Some(root.id));
+        assert_eq!(root_id(&second), Some(root.id));
+        assert_eq!(parent_id(&second), Some(first.id));
+    }
+
+    #[test]
+    fn positional_tags_are_root_first_parent_last() {
+        let keys = Keys::generate();
+        let root = note(&keys, "root", vec![], 1);
+        let mid = note(&keys, "mid", vec![Tag::event(root.id)], 2);
+        let leaf = note(
+            &keys,
+            "leaf",
+            vec![Tag::event(root.id), Tag::event(mid.id)],
+            3,
+        );
+
+        assert_eq!(root_id(&mid), Some(root.id));
+        assert_eq!(parent_id(&mid), Some(root.id));
+        assert_eq!(root_id(&leaf), Some(root.id));
+        assert_eq!(parent_id(&leaf), Some(mid.id));
+    }
+
+    #[test]
+    fn a_quote_is_not_a_reply() {
+        let keys = Keys::generate();
+        let quoted = note(&keys, "quoted", vec![], 1);
+        let quote = note(&keys, "quote", vec![e(quoted.id, "mention")], 2);
+        assert_eq!(parent_id(&quote), None);
+        assert_eq!(root_id(&quote), None);
+    }
+
+    #[test]
+    fn replies_hang_under_their_parents_oldest_first() {
+        let keys = Keys::generate();
+        let root = note(&keys, "root", vec![], 1);
+        let late = note(&keys, "late", vec![e(root.id, "root")], 5);
+        let early = note(&keys, "early", vec![e(root.id, "root")], 2);
+        let nested = note(
+            &keys,
+            "nested",
+            vec![e(root.id, "root"), e(early.id, "reply")],
+            3,
+        );
+
+        let tree = build_tree(root.id, &by_id(&[&root, &late, &early, &nested]));
+        assert_eq!(shape(&tree), "root(early(nested) late)");
+        assert_eq!(tree.len(), 4);
+    }
+
+    #[test]
+    fn missing_notes_become_placeholders_instead_of_dropping_replies() {
+        let keys = Keys::generate();
+        let root = note(&keys, "root", vec![], 1);
+        let lost = note(&keys, "lost", vec![e(root.id, "root")], 2);
+        let orphan = note(
+            &keys,
+            "orphan",
+            vec![e(root.id, "root"), e(lost.id, "reply")],
+            3,
+        );
+
+        // Neither the root nor the parent of `orphan` was fetched.
+        let tree = build_tree(root.id, &by_id(&[&orphan]));
+        assert_eq!(shape(&tree), "?(?(orphan))");
+        assert_eq!(tree.id, root.id);
+        assert_eq!(tree.replies[0].id, lost.id);
+    }
+
+    #[test]
+    fn reactions_and_zaps_are_tallied_per_note() {
+        let keys = Keys::generate();
+        let target = note(&keys, "target", vec![], 1);
+        let reaction = EventBuilder::new(Kind::Reaction, "+")
+            .tags([Tag::event(target.id)])
+            .sign_with_keys(&keys)
+            .unwrap();
+        let zap = EventBuilder::new(Kind::from(9735u16), "")
+            .tags([
+                Tag::event(target.id),
+                Tag::parse(["bolt11", "lnbc210n1pjunk"]).unwrap(),
+            ])
+            .sign_with_keys(&keys)
+            .unwrap();
+
+        let totals = tally(&[reaction.clone(), reaction, zap]);
+        assert_eq!(
+            totals[&target.id],
+            Engagement {
+                reactions: 2,
+                zaps: 1,
+                zap_msats: 21_000,
+            }
+        );
+    }
+
+    #[test]
+    fn bolt11_amounts_follow_the_multiplier() {
+        assert_eq!(bolt11_msats("lnbc1pjunk"), None);
+        assert_eq!(bolt11_msats("lnbc2500u1pjunk"), Some(250_000_000));
+        assert_eq!(bolt11_msats("lnbc1m1pjunk"), Some(100_000_000));
+        assert_eq!(bolt11_msats("lnbc10n1pjunk"), Some(1_000));
+        assert_eq!(bolt11_msats("lnbc10p1pjunk"), Some(1));
+        assert_eq!(bolt11_msats("LNTB1U1PJUNK"), Some(100_000));
+        assert_eq!(bolt11_msats("not an invoice"), None);
+    }
+
+    #[test]
+    fn zap_amount_falls_back_to_the_zap_request() {
+        let keys = Keys::generate();
+        let request = serde_json::json!({
+            "kind": 9734,
+            "tags": [["amount", "5000"]],
+        });
+        let receipt = EventBuilder::new(Kind::from(9735u16), "")
+            .tags([Tag::parse(["description", &request.to_string()]).unwrap()])
+            .sign_with_keys(&keys)
+            .unwrap();
+        assert_eq!(zap_msats(&receipt), Some(5000));
+    }
+
+    #[test]
+    fn json_nests_replies_and_marks_missing_nodes() {
+        let keys = Keys::generate();
+        let root = note(&keys, "root", vec![], 1);
+        let reply = note(&keys, "reply", vec![e(root.id, "root")], 2);
+        let tree = build_tree(root.id, &by_id(&[&reply]));
+
+        let mut engagement = HashMap::new();
+        engagement.insert(
+            reply.id,
+            Engagement {
+                reactions: 3,
+                zaps: 1,
+                zap_msats: 21_000,
+            },
+        );
+        let document = to_json(&tree, reply.id, &engagement, None).unwrap();
+
+        assert_eq!(document["focus"], reply.id.to_hex());
+        assert_eq!(document["count"], 2);
+        assert_eq!(document["root"]["missing"], true);
+        assert!(document["root"]["event"].is_null());
+        let child = &document["root"]["replies"][0];
+        assert_eq!(child["missing"], false);
+        assert_eq!(child["event"], serde_json::to_value(&reply).unwrap());
+        assert_eq!(
+            child["author"]["npub"],
+            keys.public_key().to_bech32().unwrap()
+        );
+        assert_eq!(child["reactions"], 3);
+        assert_eq!(child["zap_sats"], 21);
+        assert_eq!(child["replies"].as_array().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn sats_are_grouped_by_thousands() {
+        assert_eq!(format_sats(999), "0");
+        assert_eq!(format_sats(21_000), "21");
+        assert_eq!(format_sats(1_234_567_000), "1,234,567");
+    }
+
+    #[test]
+    fn muted_replies_go_with_everything_under_them() {
+        let alice = Keys::generate();
+        let spammer = Keys::generate();
+        let root = note(&alice, "root", vec![], 100);
+        let spam = note(&spammer, "spam", vec![e(root.id, "root")], 110);
+        let answer = note(
+            &alice,
+            "answer",
+            vec![e(root.id, "root"), e(spam.id, "reply")],
+            120,
+        );
+        let fine = note(&alice, "fine", vec![e(root.id, "root")], 130);
+        let mut events = by_id(&[&root, &spam, &answer, &fine]);
+
+        let mutes = Mutes::from_items(&crate::commands::list::ListItems {
+            public: vec![Tag::public_key(spammer.public_key())],
+            private: Vec::new(),
+        });
+        assert_eq!(drop_muted(&mut events, &[root.id], &mutes), 2);
+        assert_eq!(shape(&build_tree(root.id, &events)), "root(fine)");
+
+        let mut asked_for = by_id(&[&root, &spam]);
+        assert_eq!(drop_muted(&mut asked_for, &[root.id, spam.id], &mutes), 0);
+    }
+}
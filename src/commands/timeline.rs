@@ -1,15 +1,25 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use nostr_sdk::prelude::*;
 use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::time::Duration;
 
 use crate::cache::CacheDb;
 use crate::client;
 use crate::config::NostaroConfig;
 use crate::keys;
+use crate::nip05;
 use crate::outln;
 use crate::output;
+use crate::post_options;
+
+use super::embed::{self, Embeds};
+use super::list;
+use super::mute::{self, Mutes};
+use super::snapshot::snapshot_event;
+use super::thread;
+use super::watch::EventDeduplicator;
 
 /// Resolve who reacted: the npub, the cached display name (if any) and whether
 /// it is the local user. Reactions by the local user are never name-resolved,
@@ -30,7 +40,7 @@ fn reactor_identity(
     (npub, name, false)
 }
 
-fn reaction_emoji(reaction: &Event) -> String {
+pub(crate) fn reaction_emoji(reaction: &Event) -> String {
     if reaction.content.is_empty() {
         "+".to_string()
     } else {
@@ -109,14 +119,19 @@ fn profile_batch_filter(pubkeys: Vec<PublicKey>) -> Filter {
 /// is the 979 round trips #8/#9 removed.
 const _: fn(Vec<PublicKey>) -> Filter = profile_batch_filter;
 
-async fn fetch_and_cache_profiles(
+pub(crate) async fn fetch_and_cache_profiles(
     nostr_client: &Client,
     pubkeys: Vec<PublicKey>,
     cache: &CacheDb,
 ) -> Result<()> {
+    // A profile cached before NIP-05 claims were recorded counts as missing,
+    // so its claim is picked up once.
     let missing_pubkeys: Vec<PublicKey> = pubkeys
         .into_iter()
-        .filter(|pk| cache.get_profile(&pk.to_hex()).ok().flatten().is_none())
+        .filter(|pk| {
+            cache.get_profile(&pk.to_hex()).ok().flatten().is_none()
+                || cache.get_nip05(&pk.to_hex()).ok().flatten().is_none()
+        })
         .collect();
 
     if missing_pubkeys.is_empty() {
@@ -138,6 +153,8 @@ async fn fetch_and_cache_profiles(
                 metadata.about.as_deref(),
                 metadata.picture.as_deref(),
             );
+            let claimed = metadata.nip05.as_deref().filter(|nip05| !nip05.is_empty());
+            let _ = cache.store_nip05_claim(&event.pubkey.to_hex(), claimed);
         }
     }
 
@@ -159,35 +176,22 @@ fn to_json(
     following_set: &HashSet<PublicKey>,
     own_pubkey: PublicKey,
     reactions_by_event: &HashMap<EventId, Vec<Event>>,
+    embeds: &Embeds,
     cache: Option<&CacheDb>,
 ) -> Result<serde_json::Value> {
-    let mut notes = Vec::with_capacity(events.len());
-    for event in events {
-        let reactions: Vec<serde_json::Value> = reactions_by_event
-            .get(&event.id)
-            .map(|reactions| {
-                reactions
-                    .iter()
-                    .map(|reaction| {
-                        let (npub, name, is_self) = reactor_identity(reaction, own_pubkey, cache);
-                        serde_json::json!({
-                            "emoji": reaction_emoji(reaction),
-                            "npub": npub,
-                            "name": name,
-                            "is_self": is_self,
-                        })
-                    })
-                    .collect()
-            })
-            .unwrap_or_default();
-
-        notes.push(serde_json::json!({
-            "event": serde_json::to_value(event)?,
-            "following": following_set.contains(&event.pubkey),
-            "is_self": event.pubkey == own_pubkey,
-            "reactions": reactions,
-        }));
-    }
+    let notes = events
+        .iter()
+        .map(|event| {
+            note_json(
+                event,
+                following_set,
+                own_pubkey,
+                reactions_by_event,
+                embeds,
+                cache,
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
 
     Ok(serde_json::json!({
         "count": notes.len(),
@@ -195,6 +199,186 @@ fn to_json(
     }))
 }
 
+/// One entry of the document's `notes`; also one line of `timeline --follow`
+/// in JSON mode, so the live tail and the one-shot document agree on shape.
+/// `repost` and `references` describe what the note points at (see
+/// [`Embeds`]); the event itself is passed through untouched.
+fn note_json(
+    event: &Event,
+    following_set: &HashSet<PublicKey>,
+    own_pubkey: PublicKey,
+    reactions_by_event: &HashMap<EventId, Vec<Event>>,
+    embeds: &Embeds,
+    cache: Option<&CacheDb>,
+) -> Result<serde_json::Value> {
+    let reactions: Vec<serde_json::Value> = reactions_by_event
+        .get(&event.id)
+        .map(|reactions| {
+            reactions
+                .iter()
+                .map(|reaction| {
+                    let (npub, name, is_self) = reactor_identity(reaction, own_pubkey, cache);
+                    serde_json::json!({
+                        "emoji": reaction_emoji(reaction),
+                        "npub": npub,
+                        "name": name,
+                        "is_self": is_self,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(serde_json::json!({
+        "event": serde_json::to_value(event)?,
+        "following": following_set.contains(&event.pubkey),
+        "is_self": event.pubkey == own_pubkey,
+        "nip05": nip05::cached(cache, &event.pubkey),
+        "reactions": reactions,
+        "repost": embeds.repost_json(event, cache)?,
+        "references": embeds.references_json(
+            &embeds.original(event).unwrap_or(event).content,
+            cache,
+        ),
+    }))
+}
+
+pub(crate) fn format_time(timestamp: Timestamp) -> String {
+    DateTime::<Utc>::from_timestamp(timestamp.as_u64() as i64, 0)
+        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// One note of the text listing, separator included. A repost is shown as
+/// who reposted it, then the original; `nostr:` references become previews.
+fn write_note_text(
+    event: &Event,
+    following_set: &HashSet<PublicKey>,
+    own_pubkey: PublicKey,
+    reactions_by_event: &HashMap<EventId, Vec<Event>>,
+    embeds: &Embeds,
+    cache: Option<&CacheDb>,
+) -> Result<()> {
+    let npub = event.pubkey.to_bech32()?;
+    let short_npub = &npub;
+    let is_following = following_set.contains(&event.pubkey);
+    let is_self = event.pubkey == own_pubkey;
+
+    let label = if is_self {
+        " [you]"
+    } else if is_following {
+        " [following]"
+    } else {
+        ""
+    };
+
+    let nip05 = nip05::cached(cache, &event.pubkey)
+        .map(|check| format!(" {}", check.label()))
+        .unwrap_or_default();
+
+    let note_id = event.id.to_bech32()?;
+    outln!(
+        "[{}]{}{} {}",
+        short_npub,
+        nip05,
+        label,
+        format_time(event.created_at)
+    )?;
+    if embed::is_repost(event) {
+        match embeds.original(event) {
+            Some(original) => {
+                let author = original.pubkey.to_bech32()?;
+                let author = match thread::cached_name(cache, &original.pubkey) {
+                    Some(name) => format!("{} ({})", name, author),
+                    None => author,
+                };
+                outln!(
+                    "🔁 reposted {} {}",
+                    author,
+                    format_time(original.created_at)
+                )?;
+                outln!(
+                    "{}",
+                    embeds.render(&post_options::display_content(original), cache)
+                )?;
+                outln!("  original: {}", original.id.to_bech32()?)?;
+            }
+            None => outln!("🔁 reposted a note that could not be fetched")?,
+        }
+    } else {
+        outln!(
+            "{}",
+            embeds.render(&post_options::display_content(event), cache)
+        )?;
+    }
+    outln!("  id: {}", note_id)?;
+
+    if let Some(reactions) = reactions_by_event.get(&event.id) {
+        let mut counts: HashMap<String, (usize, Vec<String>)> = HashMap::new();
+
+        for reaction in reactions {
+            let (reactor_npub, name, is_self) = reactor_identity(reaction, own_pubkey, cache);
+            let reactor_label = if is_self {
+                format!("you({})", reactor_npub)
+            } else {
+                match name {
+                    Some(name) => format!("{}({})", name, reactor_npub),
+                    None => reactor_npub,
+                }
+            };
+            let entry = counts
+                .entry(reaction_emoji(reaction))
+                .or_insert_with(|| (0, Vec::new()));
+            entry.0 += 1;
+            entry.1.push(reactor_label);
+        }
+
+        if !counts.is_empty() {
+            let mut parts = Vec::new();
+
+            for (emoji, (count, names)) in &counts {
+                parts.push(format!("{} x{} ({})", emoji, count, names.join(", ")));
+            }
+
+            outln!("  Reactions: {}", parts.join(", "))?;
+        }
+    }
+
+    outln!("{}", "-".repeat(60))?;
+    Ok(())
+}
+
+/// NIP-05 markers for the authors of `events`: the kind:0s the cache lacks in
+/// one batched read, then the claims without a fresh check verified against
+/// their domains. The renderers only read the results back from the cache. The
+/// names in those kind:0s are not shown; the note header stays the npub.
+async fn verify_authors(nostr_client: &Client, events: &[Event], cache: &CacheDb) {
+    let authors: Vec<PublicKey> = events
+        .iter()
+        .map(|event| event.pubkey)
+        .collect::<HashSet<PublicKey>>()
+        .into_iter()
+        .collect();
+    let _ = fetch_and_cache_profiles(nostr_client, authors.clone(), cache).await;
+    nip05::refresh(cache, &authors).await;
+}
+
+/// Keep fetched events in the cache (and so in its search index).
+pub(crate) fn cache_events(cache: &CacheDb, events: &[Event]) {
+    for event in events {
+        let tags_json = serde_json::to_string(&event.tags).unwrap_or_default();
+        let _ = cache.store_event(
+            &event.id.to_hex(),
+            &event.pubkey.to_hex(),
+            event.kind.as_u16(),
+            &event.content,
+            event.created_at.as_u64() as i64,
+            &tags_json,
+            &event.as_json(),
+        );
+    }
+}
+
 /// Fetch the newest kind:1 written by the people the user follows (plus the
 /// user), topped up with the relay-wide feed when the follow set is too quiet
 /// to fill `limit`.
@@ -232,56 +416,206 @@ async fn fetch_following(
 /// swapped with `with_reactions` by accident. This is the same argument the
 /// rest of #10 makes with `const _` — the internal representation, not a new
 /// option: the CLI surface is still the `--global` flag.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TimelineScope {
     /// The people you follow (plus you), topped up from the relay when the
     /// follow set is too quiet to fill `limit`.
     Following,
     /// The relay's newest kind:1, whoever wrote them.
     Global,
+    /// The members of one of your NIP-51 follow sets (`--list`), public and
+    /// private alike. Never topped up: a list is asked for by name, so notes
+    /// from outside it would be noise.
+    List(String),
+    /// The pubkeys in a file, one per line (`--authors-file`; `-` is stdin).
+    /// Not topped up either, for the same reason.
+    AuthorsFile(PathBuf),
+    /// Notes with any of these hashtags (`--tag`, `#t`), from the people you
+    /// follow or, when `global`, from anyone.
+    Tags { hashtags: Vec<String>, global: bool },
+    /// One relay's own feed (`--relay`): what that relay holds, from the
+    /// people you follow or, when `global`, from anyone, narrowed to
+    /// `hashtags` when there are any. Only the feed comes from `url`; the
+    /// follow and mute lists are still read from the configured relays.
+    Relay {
+        url: String,
+        hashtags: Vec<String>,
+        global: bool,
+    },
+}
+
+impl TimelineScope {
+    /// Whether the feed is narrowed to an author set.
+    fn by_author(&self) -> bool {
+        match self {
+            TimelineScope::Global => false,
+            TimelineScope::Tags { global, .. } | TimelineScope::Relay { global, .. } => !global,
+            _ => true,
+        }
+    }
+
+    fn hashtags(&self) -> &[String] {
+        match self {
+            TimelineScope::Tags { hashtags, .. } | TimelineScope::Relay { hashtags, .. } => {
+                hashtags
+            }
+            _ => &[],
+        }
+    }
+
+    fn relay(&self) -> Option<&str> {
+        match self {
+            TimelineScope::Relay { url, .. } => Some(url),
+            _ => None,
+        }
+    }
+}
+
+/// Whether `timeline` exits after the first page or keeps the subscription
+/// open and appends new notes as they arrive (`--follow`). A type rather than
+/// a third positional `bool`, for the reason given on [`TimelineScope`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelineMode {
+    Once,
+    Follow,
+}
+
+/// The live subscription of `timeline --follow`: new notes and reposts from the same
+/// authors as the first page (everyone, for the global feed). The follow-based
+/// feed is not topped up from the relay-wide feed here — a quiet follow set is
+/// simply quiet. None when the scope is by author and there are no authors:
+/// relays read an empty author list as "anyone", which would turn an empty
+/// set into the global feed.
+///
+/// Pure — it builds the filter and talks to no relay.
+fn follow_filter(scope: &TimelineScope, authors: &[PublicKey], since: Timestamp) -> Option<Filter> {
+    if scope.by_author() && authors.is_empty() {
+        return None;
+    }
+    Some(scope_filter(scope, authors).since(since))
+}
+
+/// The notes and reposts `scope` is about: by `authors` unless the scope is
+/// global, with one of its hashtags when it has any. Shared by the first page
+/// of the scopes that are not topped up and by every live tail.
+///
+/// Pure — it builds the filter and talks to no relay.
+fn scope_filter(scope: &TimelineScope, authors: &[PublicKey]) -> Filter {
+    let mut filter = Filter::new().kinds(client::timeline_kinds());
+    if scope.by_author() {
+        filter = filter.authors(authors.iter().copied());
+    }
+    if !scope.hashtags().is_empty() {
+        filter = filter.hashtags(scope.hashtags().iter().cloned());
+    }
+    filter
+}
+
+/// The first page of a scope without a top-up: newest first, from `--relay`
+/// alone when one was given. An empty author set is an empty page, not an
+/// author-less (global) query.
+async fn fetch_scoped(
+    nostr_client: &Client,
+    scope: &TimelineScope,
+    authors: &[PublicKey],
+    limit: usize,
+) -> Result<Vec<Event>> {
+    if scope.by_author() && authors.is_empty() {
+        return Ok(Vec::new());
+    }
+    let filter = scope_filter(scope, authors).limit(limit);
+    let timeout = Duration::from_secs(10);
+    let events = match scope.relay() {
+        Some(url) => {
+            nostr_client
+                .fetch_events_from([url], filter, timeout)
+                .await?
+        }
+        None => nostr_client.fetch_events(filter, timeout).await?,
+    };
+    let mut events: Vec<Event> = events.into_iter().collect();
+    events.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+    Ok(events)
 }
 
 /// [`TimelineScope::Global`] swaps the author filter off — `timeline` stays the
 /// follow-based view it has always been, `timeline --global` is "what is on the
 /// relay right now, whoever wrote it". Everything after the fetch (caching,
 /// reactions, rendering, the JSON document) is shared, so the two views cannot
-/// drift apart.
-pub async fn run(limit: usize, with_reactions: bool, scope: TimelineScope) -> Result<()> {
+/// drift apart. [`TimelineMode::Follow`] then keeps going: new notes are
+/// appended in the same shapes, text blocks or one JSON line per note.
+pub async fn run(
+    limit: usize,
+    with_reactions: bool,
+    scope: TimelineScope,
+    mode: TimelineMode,
+) -> Result<()> {
     let config = NostaroConfig::load()?;
     let keys = keys::keys_from_config(&config)?;
     let nostr_client = client::create_client(&keys, &config).await?;
 
-    match scope {
+    if let Some(url) = scope.relay() {
+        nostr_client
+            .add_relay(url)
+            .await
+            .with_context(|| format!("invalid relay URL {}", url))?;
+        nostr_client.connect().await;
+    }
+
+    let tags: Vec<String> = scope.hashtags().iter().map(|t| format!("#{}", t)).collect();
+    match &scope {
         TimelineScope::Global => println!("Fetching global timeline...\n"),
-        TimelineScope::Following => println!("Fetching timeline...\n"),
+        TimelineScope::List(name) => println!("Fetching timeline of list \"{}\"...\n", name),
+        TimelineScope::Tags { .. } => println!("Fetching {} timeline...\n", tags.join(" ")),
+        TimelineScope::Relay { url, .. } if tags.is_empty() => {
+            println!("Fetching timeline from {}...\n", url)
+        }
+        TimelineScope::Relay { url, .. } => {
+            println!("Fetching {} timeline from {}...\n", tags.join(" "), url)
+        }
+        _ => println!("Fetching timeline...\n"),
     }
 
     // Read once in both modes. This is a single kind:3 — constant in the number
     // of follows — and it is only used to label who you already follow, never
     // expanded into a profile lookup per author.
-    let contacts = client::fetch_contacts(&nostr_client, &keys.public_key()).await?;
+    let contact_list = client::fetch_contact_list(&nostr_client, &keys.public_key()).await?;
+    let contacts = match &contact_list {
+        Some(event) => {
+            if let Ok(cache) = CacheDb::open() {
+                snapshot_event(&cache, event);
+            }
+            client::contact_pubkeys(event)
+        }
+        None => Vec::new(),
+    };
     let following_set: HashSet<PublicKey> = contacts.iter().copied().collect();
 
+    // Who the first page and the live tail are about. The kind:3 above still
+    // labels `[following]` for a list or a file.
+    let authors = match &scope {
+        TimelineScope::List(name) => list::fetch_members(&nostr_client, &keys, name).await?,
+        TimelineScope::AuthorsFile(path) => list::parse_authors(&super::event::read_source(path)?)?,
+        _ => {
+            let mut authors = contacts.clone();
+            authors.push(keys.public_key());
+            authors
+        }
+    };
+
+    // Notes newer than this come from the live subscription under --follow.
+    let started_at = Timestamp::now();
+
     let mut all_events = match scope {
         // One author-less filter, so the cost does not grow with the size of
         // the follow set.
         TimelineScope::Global => client::fetch_timeline(&nostr_client, limit).await?,
-        TimelineScope::Following => {
-            let mut authors = contacts.clone();
-            authors.push(keys.public_key());
-            fetch_following(&nostr_client, &authors, limit).await?
-        }
+        TimelineScope::Following => fetch_following(&nostr_client, &authors, limit).await?,
+        // Everything else asks exactly for what the scope names.
+        _ => fetch_scoped(&nostr_client, &scope, &authors, limit).await?,
     };
 
     match scope {
-        // Nobody is privileged in the global feed: newest first, full stop.
-        // `fetch_timeline` already returns newest-first, so this changes
-        // nothing — it is here to say, at the point where the follow-based
-        // branch applies its ordering, that the global branch deliberately does
-        // not go through that comparator.
-        TimelineScope::Global => {
-            all_events.sort_by_key(|event| std::cmp::Reverse(event.created_at))
-        }
         TimelineScope::Following => all_events.sort_by(|a, b| {
             let a_following = following_set.contains(&a.pubkey) || a.pubkey == keys.public_key();
             let b_following = following_set.contains(&b.pubkey) || b.pubkey == keys.public_key();
@@ -291,8 +625,25 @@ pub async fn run(limit: usize, with_reactions: bool, scope: TimelineScope) -> Re
                 _ => b.created_at.cmp(&a.created_at),
             }
         }),
+        // Nobody is privileged in the global feed: newest first, full stop.
+        // `fetch_timeline` already returns newest-first, so this changes
+        // nothing — it is here to say, at the point where the follow-based
+        // branch applies its ordering, that the global branch deliberately does
+        // not go through that comparator. A list, a file, a hashtag or a relay
+        // feed is likewise everyone in it, equally.
+        _ => all_events.sort_by_key(|event| std::cmp::Reverse(event.created_at)),
     }
 
+    // NIP-40: a relay that does not implement expiration still hands expired
+    // notes out. They are dropped before `limit` is applied so they do not
+    // take up a slot.
+    let now = Timestamp::now();
+    all_events.retain(|event| !post_options::is_expired(event, now));
+
+    // NIP-51 mute list: same rule, same place — before `limit`.
+    let mutes = mute::load(&nostr_client, &keys).await;
+    all_events.retain(|event| !mutes.is_muted(event));
+
     all_events.truncate(limit);
 
     let reactions_by_event = if with_reactions {
@@ -318,20 +669,17 @@ pub async fn run(limit: usize, with_reactions: bool, scope: TimelineScope) -> Re
 
     let cache = CacheDb::open().ok();
 
+    // What the page points at — repost originals and `nostr:` references —
+    // in one batched read. A repost of something muted is muted too.
+    let embeds = Embeds::fetch(&nostr_client, &all_events, cache.as_ref()).await;
+    all_events.retain(
+        |event| !matches!(embeds.original(event), Some(original) if mutes.is_muted(original)),
+    );
+
     // Cache events
-    if let Ok(cache) = CacheDb::open() {
-        for event in &all_events {
-            let tags_json = serde_json::to_string(&event.tags).unwrap_or_default();
-            let _ = cache.store_event(
-                &event.id.to_hex(),
-                &event.pubkey.to_hex(),
-                event.kind.as_u16(),
-                &event.content,
-                event.created_at.as_u64() as i64,
-                &tags_json,
-                &event.as_json(),
-            );
-        }
+    if let Some(cache) = cache.as_ref() {
+        cache_events(cache, &all_events);
+        verify_authors(&nostr_client, &all_events, cache).await;
     }
 
     // An empty timeline is still a result: --out gets an empty listing rather
@@ -342,89 +690,159 @@ pub async fn run(limit: usize, with_reactions: bool, scope: TimelineScope) -> Re
 
     let own_pubkey = keys.public_key();
 
-    if output::is_json() {
+    if output::is_json() && mode == TimelineMode::Follow {
+        // A live tail cannot append to one JSON document, so the first page is
+        // written as JSON Lines too: one `notes` entry per line.
+        output::open_json_lines()?;
+        for event in &all_events {
+            output::write_json_line(&note_json(
+                event,
+                &following_set,
+                own_pubkey,
+                &reactions_by_event,
+                &embeds,
+                cache.as_ref(),
+            )?)?;
+        }
+    } else if output::is_json() {
         output::write_json(&to_json(
             &all_events,
             &following_set,
             own_pubkey,
             &reactions_by_event,
+            &embeds,
             cache.as_ref(),
         )?)?;
-
-        if !all_events.is_empty() {
-            println!("\nShowing {} note(s).", all_events.len());
+    } else {
+        output::open_body()?;
+        for event in &all_events {
+            write_note_text(
+                event,
+                &following_set,
+                own_pubkey,
+                &reactions_by_event,
+                &embeds,
+                cache.as_ref(),
+            )?;
         }
-        nostr_client.disconnect().await;
-        return Ok(());
     }
 
-    output::open_body()?;
-    for event in &all_events {
-        let npub = event.pubkey.to_bech32()?;
-        let short_npub = &npub;
-        let is_following = following_set.contains(&event.pubkey);
-        let is_self = event.pubkey == own_pubkey;
+    if !all_events.is_empty() {
+        println!("\nShowing {} note(s).", all_events.len());
+    }
 
-        let label = if is_self {
-            " [you]"
-        } else if is_following {
-            " [following]"
-        } else {
-            ""
+    if mode == TimelineMode::Follow {
+        let Some(filter) = follow_filter(&scope, &authors, started_at) else {
+            println!("Nobody to follow in this timeline; not waiting for new notes.");
+            nostr_client.disconnect().await;
+            return Ok(());
+        };
+        let seen: Vec<EventId> = all_events.iter().map(|event| event.id).collect();
+        let reader = Reader {
+            following_set: &following_set,
+            own_pubkey,
+            mutes: &mutes,
+            cache: cache.as_ref(),
         };
+        follow(&nostr_client, scope.relay(), filter, &seen, &reader).await?;
+    }
 
-        let timestamp = event.created_at.as_u64() as i64;
-        let datetime = DateTime::<Utc>::from_timestamp(timestamp, 0)
-            .map(|dt| dt.format("%Y-%m-%d %H:%M:%S UTC").to_string())
-            .unwrap_or_else(|| "unknown".to_string());
-
-        let note_id = event.id.to_bech32()?;
-        outln!("[{}]{} {}", short_npub, label, datetime)?;
-        outln!("{}", event.content)?;
-        outln!("  id: {}", note_id)?;
-
-        if with_reactions {
-            if let Some(reactions) = reactions_by_event.get(&event.id) {
-                let mut counts: HashMap<String, (usize, Vec<String>)> = HashMap::new();
-
-                for reaction in reactions {
-                    let (reactor_npub, name, is_self) =
-                        reactor_identity(reaction, own_pubkey, cache.as_ref());
-                    let reactor_label = if is_self {
-                        format!("you({})", reactor_npub)
-                    } else {
-                        match name {
-                            Some(name) => format!("{}({})", name, reactor_npub),
-                            None => reactor_npub,
-                        }
-                    };
-                    let entry = counts
-                        .entry(reaction_emoji(reaction))
-                        .or_insert_with(|| (0, Vec::new()));
-                    entry.0 += 1;
-                    entry.1.push(reactor_label);
-                }
+    nostr_client.disconnect().await;
+    Ok(())
+}
 
-                if !counts.is_empty() {
-                    let mut parts = Vec::new();
+/// Who the live notes are rendered for: what `[following]` is checked
+/// against, whose mutes apply, and where notes and names are cached.
+struct Reader<'a> {
+    following_set: &'a HashSet<PublicKey>,
+    own_pubkey: PublicKey,
+    mutes: &'a Mutes,
+    cache: Option<&'a CacheDb>,
+}
 
-                    for (emoji, (count, names)) in &counts {
-                        parts.push(format!("{} x{} ({})", emoji, count, names.join(", ")));
-                    }
+/// Keep the subscription open and append each new note, once, until Ctrl+C.
+/// Notes already on the first page and relay replays are dropped by the same
+/// deduplicator `watch` uses. Live notes have no reactions yet, so they are
+/// rendered with none; what each one points at is fetched as it arrives.
+async fn follow(
+    nostr_client: &Client,
+    relay: Option<&str>,
+    filter: Filter,
+    seen: &[EventId],
+    reader: &Reader<'_>,
+) -> Result<()> {
+    let Reader {
+        following_set,
+        own_pubkey,
+        mutes,
+        cache,
+    } = *reader;
+    let mut dedup = EventDeduplicator::new();
+    for id in seen {
+        dedup.remember(*id);
+    }
+    let no_reactions = HashMap::new();
 
-                    outln!("  Reactions: {}", parts.join(", "))?;
-                }
-            }
+    match relay {
+        Some(url) => nostr_client.subscribe_to([url], filter, None).await?,
+        None => nostr_client.subscribe(filter, None).await?,
+    };
+    println!("Following new notes. Press Ctrl+C to stop.\n");
+
+    let mut notifications = nostr_client.notifications();
+    let mut shown = 0usize;
+    loop {
+        let notification = tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            notification = notifications.recv() => match notification {
+                Ok(notification) => notification,
+                Err(_) => break,
+            },
+        };
+        let RelayPoolNotification::Event { event, .. } = notification else {
+            continue;
+        };
+        if !client::timeline_kinds().contains(&event.kind)
+            || !dedup.accept(&event)
+            || mutes.is_muted(&event)
+        {
+            continue;
         }
 
-        outln!("{}", "-".repeat(60))?;
-    }
+        let page = std::slice::from_ref(event.as_ref());
+        let embeds = Embeds::fetch(nostr_client, page, cache).await;
+        if matches!(embeds.original(&event), Some(original) if mutes.is_muted(original)) {
+            continue;
+        }
 
-    if !all_events.is_empty() {
-        println!("\nShowing {} note(s).", all_events.len());
+        if let Some(cache) = cache {
+            cache_events(cache, page);
+            verify_authors(nostr_client, page, cache).await;
+        }
+        if output::is_json() {
+            output::write_json_line(&note_json(
+                &event,
+                following_set,
+                own_pubkey,
+                &no_reactions,
+                &embeds,
+                cache,
+            )?)?;
+        } else {
+            write_note_text(
+                &event,
+                following_set,
+                own_pubkey,
+                &no_reactions,
+                &embeds,
+                cache,
+            )?;
+            output::flush();
+        }
+        shown += 1;
     }
 
-    nostr_client.disconnect().await;
+    println!("\nStopped following ({} new note(s)).", shown);
     Ok(())
 }
 
@@ -478,8 +896,8 @@ mod tests {
         assert!(kinds.contains(&Kind::Metadata));
     }
 
-    /// Note authors are never name-resolved, so the kind:0 path is not reached
-    /// for them at all (only reactors under `--with-reactions` are): `to_json`
+    /// Note authors are never name-resolved: their kind:0 is read only for the
+    /// NIP-05 claim (`nip05`, `null` when there is no fresh check), so `to_json`
     /// renders bare events and the text body prints npubs.
     #[test]
     fn the_json_body_carries_no_profile_name_for_note_authors() {
@@ -490,14 +908,24 @@ mod tests {
             &HashSet::new(),
             Keys::generate().public_key(),
             &HashMap::new(),
+            &Embeds::default(),
             None,
         )
         .unwrap();
 
         assert_eq!(
             note_key_sets(&document),
-            vec![vec!["event", "following", "is_self", "reactions"]],
-            "a note is the raw event plus follow/self/reactions — no resolved name"
+            vec![vec![
+                "event",
+                "following",
+                "is_self",
+                "nip05",
+                "reactions",
+                "references",
+                "repost"
+            ]],
+            "a note is the raw event plus follow/self/NIP-05/reactions and what it points \
+             at — no resolved name for its author"
         );
         assert_eq!(
             document["notes"][0]["event"],
@@ -523,6 +951,7 @@ mod tests {
             &following,
             me.public_key(),
             &HashMap::new(),
+            &Embeds::default(),
             None,
         )
         .unwrap();
@@ -537,13 +966,30 @@ mod tests {
             &following,
             me.public_key(),
             &HashMap::new(),
+            &Embeds::default(),
             None,
         )
         .unwrap();
 
         let expected = vec![
-            vec!["event", "following", "is_self", "reactions"],
-            vec!["event", "following", "is_self", "reactions"],
+            vec![
+                "event",
+                "following",
+                "is_self",
+                "nip05",
+                "reactions",
+                "references",
+                "repost",
+            ],
+            vec![
+                "event",
+                "following",
+                "is_self",
+                "nip05",
+                "reactions",
+                "references",
+                "repost",
+            ],
         ];
         for document in [&follow_based, &global] {
             assert_eq!(document["count"], 2);
@@ -573,10 +1019,158 @@ mod tests {
             &HashSet::new(),
             Keys::generate().public_key(),
             &HashMap::new(),
+            &Embeds::default(),
             None,
         )
         .unwrap();
         assert_eq!(document["count"], 0);
         assert_eq!(document["notes"].as_array().unwrap().len(), 0);
     }
+
+    /// The live tail asks for the same authors as the first page — and for
+    /// nobody in particular on the global feed — and only for what is new.
+    #[test]
+    fn the_live_filter_follows_the_scope() {
+        let authors = vec![Keys::generate().public_key(), Keys::generate().public_key()];
+        let since = Timestamp::from(1_700_000_000);
+
+        let following = follow_filter(&TimelineScope::Following, &authors, since).unwrap();
+        assert_eq!(following.authors.unwrap().len(), 2);
+        assert_eq!(following.since, Some(since));
+        assert!(following.kinds.unwrap().contains(&Kind::TextNote));
+
+        let global = follow_filter(&TimelineScope::Global, &authors, since).unwrap();
+        assert!(global.authors.is_none());
+        assert_eq!(global.since, Some(since));
+
+        let list =
+            follow_filter(&TimelineScope::List("core".to_string()), &authors, since).unwrap();
+        assert_eq!(list.authors.unwrap().len(), 2);
+
+        let hashtags = vec!["nostr".to_string()];
+        let tagged = follow_filter(
+            &TimelineScope::Tags {
+                hashtags: hashtags.clone(),
+                global: false,
+            },
+            &authors,
+            since,
+        )
+        .unwrap();
+        assert_eq!(tagged.authors.unwrap().len(), 2);
+        let relay_feed = follow_filter(
+            &TimelineScope::Relay {
+                url: "wss://relay.example.com".to_string(),
+                hashtags,
+                global: true,
+            },
+            &authors,
+            since,
+        )
+        .unwrap();
+        assert!(relay_feed.authors.is_none());
+
+        // An empty author set tails nothing rather than everyone; the global
+        // feed never had authors to begin with.
+        assert!(follow_filter(&TimelineScope::Following, &[], since).is_none());
+        assert!(follow_filter(&TimelineScope::List("empty".to_string()), &[], since).is_none());
+        assert!(follow_filter(&TimelineScope::Global, &[], since).is_some());
+        assert_eq!(
+            serde_json::to_value(&relay_feed).unwrap()["#t"],
+            serde_json::json!(["nostr"])
+        );
+    }
+
+    /// A live line is exactly one entry of the one-shot document.
+    #[test]
+    fn a_followed_note_has_the_documents_note_shape() {
+        let me = Keys::generate();
+        let event = note(&me, "live");
+        let line = note_json(
+            &event,
+            &HashSet::new(),
+            me.public_key(),
+            &HashMap::new(),
+            &Embeds::default(),
+            None,
+        )
+        .unwrap();
+        let document = to_json(
+            std::slice::from_ref(&event),
+            &HashSet::new(),
+            me.public_key(),
+            &HashMap::new(),
+            &Embeds::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(document["notes"][0], line);
+    }
+
+    /// `nip05` is the cached check of the author's claim, failed ones included,
+    /// so an impersonator's note says so.
+    #[test]
+    fn a_note_carries_its_authors_nip05_check() {
+        let me = Keys::generate();
+        let verified = Keys::generate();
+        let impostor = Keys::generate();
+        let cache = CacheDb::in_memory().unwrap();
+        let now = Timestamp::now().as_u64() as i64;
+        for (keys, ok) in [(&verified, true), (&impostor, false)] {
+            cache
+                .store_nip05_check(&keys.public_key().to_hex(), "bob@example.com", ok, now)
+                .unwrap();
+        }
+
+        let document = to_json(
+            &[
+                note(&verified, "hi"),
+                note(&impostor, "send me sats"),
+                note(&me, "no claim"),
+            ],
+            &HashSet::new(),
+            me.public_key(),
+            &HashMap::new(),
+            &Embeds::default(),
+            Some(&cache),
+        )
+        .unwrap();
+        assert_eq!(
+            document["notes"][0]["nip05"],
+            serde_json::json!({"address": "bob@example.com", "verified": true})
+        );
+        assert_eq!(document["notes"][1]["nip05"]["verified"], false);
+        assert!(document["notes"][2]["nip05"].is_null());
+    }
+
+    /// A repost carries its original in `repost`; a plain note has `null`
+    /// there, so a caller can tell the two apart without looking at the kind.
+    #[test]
+    fn a_repost_carries_its_original() {
+        let me = Keys::generate();
+        let author = Keys::generate();
+        let original = note(&author, "worth sharing");
+        let repost = client::repost_builder(&original, None)
+            .unwrap()
+            .sign_with_keys(&me)
+            .unwrap();
+        let plain = note(&me, "gm");
+        let page = [repost, plain];
+        let (embeds, _) = Embeds::plan(&page);
+        let document = to_json(
+            &page,
+            &HashSet::new(),
+            me.public_key(),
+            &HashMap::new(),
+            &embeds,
+            None,
+        )
+        .unwrap();
+
+        let shared = &document["notes"][0]["repost"];
+        assert_eq!(shared["id"], original.id.to_hex());
+        assert_eq!(shared["event"], serde_json::to_value(&original).unwrap());
+        assert!(document["notes"][1]["repost"].is_null());
+        assert_eq!(document["notes"][1]["references"], serde_json::json!([]));
+    }
 }
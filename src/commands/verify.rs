@@ -0,0 +1,914 @@
+//! `verify` and `lint`: one gatekeeper for event JSON, whoever produced it.
+//!
+//! `verify` is about authenticity: does the `id` really hash the event, and does
+//! `sig` really sign that id for `pubkey`. `lint` is about meaning: does the
+//! event follow the NIPs for its kind — NIP-10 markers, the kind:3 tag shape,
+//! `d` tags on addressable kinds, NIP-40 expirations, the tags a kind cannot do
+//! without. Lint accepts signed events as well as the unsigned
+//! `{kind, content, tags}` documents `event --file` takes.
+//!
+//! Both read one JSON object, a JSON array of them, or JSON Lines, from a file
+//! or stdin (`-`), and report per event — as text, or as one JSON line per
+//! event with `--out-format json` — and both exit non-zero when anything fails, so they
+//! slot into a pipeline as a check step.
+
+use anyhow::{bail, Result};
+use nostr_sdk::prelude::*;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::outln;
+use crate::output;
+
+use super::event;
+
+/// One event of the input: its position (from 0), the line it started on when
+/// the input is JSON Lines, and the parsed JSON (or why it did not parse).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    pub index: usize,
+    pub line: Option<usize>,
+    pub json: std::result::Result<Value, String>,
+}
+
+/// Split the input into events: a JSON array is a list of events, any other
+/// single JSON document is one event, and otherwise every non-blank line is
+/// one (JSON Lines). A line that is not JSON is kept, as a failure, so it is
+/// reported where it is rather than dropped.
+///
+/// Pure — it parses text that has already been read.
+pub fn parse_documents(text: &str) -> Result<Vec<Document>> {
+    if text.trim().is_empty() {
+        bail!("the input is empty; expected a JSON event, a JSON array of events, or JSON Lines");
+    }
+
+    if let Ok(value) = serde_json::from_str::<Value>(text) {
+        let values = match value {
+            Value::Array(values) => values,
+            other => vec![other],
+        };
+        return Ok(values
+            .into_iter()
+            .enumerate()
+            .map(|(index, value)| Document {
+                index,
+                line: None,
+                json: Ok(value),
+            })
+            .collect());
+    }
+
+    Ok(text
+        .lines()
+        .enumerate()
+        .filter(|(_, raw)| !raw.trim().is_empty())
+        .enumerate()
+        .map(|(index, (offset, raw))| Document {
+            index,
+            line: Some(offset + 1),
+            json: serde_json::from_str(raw).map_err(|err| format!("not valid JSON: {}", err)),
+        })
+        .collect())
+}
+
+fn location(document: &Document) -> String {
+    match document.line {
+        Some(line) => format!("line {}", line),
+        None => format!("event {}", document.index),
+    }
+}
+
+/// The `verify` verdict on one event, as emitted by `verify --out-format json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Verification {
+    pub index: usize,
+    pub line: Option<usize>,
+    pub ok: bool,
+    pub id: Option<String>,
+    /// Whether `id` is the hash of the serialized event (NIP-01).
+    pub id_valid: bool,
+    /// Whether `sig` is a valid Schnorr signature of `id` by `pubkey`.
+    pub sig_valid: bool,
+    pub error: Option<String>,
+}
+
+/// Check the id hash and the signature of one signed event.
+///
+/// Pure — it does the arithmetic on the event and talks to no relay.
+pub fn verify_document(document: &Document) -> Verification {
+    let failed = |error: String| Verification {
+        index: document.index,
+        line: document.line,
+        ok: false,
+        id: None,
+        id_valid: false,
+        sig_valid: false,
+        error: Some(error),
+    };
+
+    let value = match &document.json {
+        Ok(value) => value,
+        Err(err) => return failed(err.clone()),
+    };
+    let event: Event = match serde_json::from_value(value.clone()) {
+        Ok(event) => event,
+        Err(err) => {
+            return failed(format!(
+                "not a signed event (id, pubkey, created_at, kind, tags, content and sig are all required): {}",
+                err
+            ))
+        }
+    };
+
+    let id_valid = event.verify_id();
+    let sig_valid = event.verify_signature();
+    let error = match (id_valid, sig_valid) {
+        (true, true) => None,
+        (false, _) => Some(
+            "id does not match the event's contents: it was edited after signing, \
+             or the id was computed differently"
+                .to_string(),
+        ),
+        (true, false) => Some(format!(
+            "sig is not a valid signature of this id by {}",
+            event.pubkey.to_hex()
+        )),
+    };
+
+    Verification {
+        index: document.index,
+        line: document.line,
+        ok: error.is_none(),
+        id: Some(event.id.to_hex()),
+        id_valid,
+        sig_valid,
+        error,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The event is wrong: relays or clients will reject or misread it.
+    Error,
+    /// The event works, but probably not the way its author meant.
+    Warning,
+}
+
+/// One finding of `lint`. `rule` is a stable name to match on; `path` points
+/// into the event (`kind`, `tags[3]`, ...).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub rule: &'static str,
+    pub path: String,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(rule: &'static str, path: impl Into<String>, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            rule,
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    fn warning(rule: &'static str, path: impl Into<String>, message: impl Into<String>) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            rule,
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Tags a kind cannot do without: every group must be present, and a group
+/// lists alternatives (`["e", "a"]` is "an e tag or an a tag").
+const REQUIRED_TAGS: [(u16, &str, &[&[&str]]); 9] = [
+    (5, "NIP-09", &[&["e", "a"]]),
+    (6, "NIP-18", &[&["e"], &["p"]]),
+    (7, "NIP-25", &[&["e", "a"], &["p"]]),
+    (16, "NIP-18", &[&["e", "a"], &["k"]]),
+    (41, "NIP-28", &[&["e"]]),
+    (42, "NIP-28", &[&["e"]]),
+    (
+        1111,
+        "NIP-22",
+        &[&["K"], &["k"], &["E", "A", "I"], &["e", "a", "i"]],
+    ),
+    (9734, "NIP-57", &[&["p"], &["relays"]]),
+    (9735, "NIP-57", &[&["p"], &["bolt11"], &["description"]]),
+];
+
+const NIP10_MARKERS: [&str; 3] = ["root", "reply", "mention"];
+
+fn is_hex(value: &str, bytes: usize) -> bool {
+    value.len() == bytes * 2 && value.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+/// The signed-only fields, when present, must at least have the right shape;
+/// whether they are *correct* is `verify`'s job.
+fn lint_signed_fields(object: &serde_json::Map<String, Value>, out: &mut Vec<Diagnostic>) {
+    for (field, bytes) in [("id", 32), ("pubkey", 32), ("sig", 64)] {
+        match object.get(field) {
+            None => {}
+            Some(Value::String(value)) if is_hex(value, bytes) => {}
+            Some(_) => out.push(Diagnostic::error(
+                "signed-fields",
+                field,
+                format!("\"{}\" must be {} hex characters", field, bytes * 2),
+            )),
+        }
+    }
+    if let Some(created_at) = object.get("created_at") {
+        if created_at.as_u64().is_none() {
+            out.push(Diagnostic::error(
+                "signed-fields",
+                "created_at",
+                "\"created_at\" must be a unix timestamp in seconds (a non-negative integer)",
+            ));
+        }
+    }
+
+    let signed = ["id", "pubkey", "sig", "created_at"]
+        .iter()
+        .filter(|field| object.contains_key(**field))
+        .count();
+    if signed > 0 && signed < 4 {
+        out.push(Diagnostic::warning(
+            "signed-fields",
+            "",
+            "only some of id/pubkey/created_at/sig are present: the event is neither a signed \
+             event nor an unsigned spec (`event --file` rejects it)",
+        ));
+    }
+
+    for key in object.keys() {
+        if ![
+            "id",
+            "pubkey",
+            "sig",
+            "created_at",
+            "kind",
+            "content",
+            "tags",
+        ]
+        .contains(&key.as_str())
+        {
+            out.push(Diagnostic::warning(
+                "structure",
+                key.as_str(),
+                format!("unknown field \"{}\" is ignored by relays", key),
+            ));
+        }
+    }
+}
+
+/// NIP-10 on a kind:1: markers are from the known set, there is at most one
+/// root and one reply, and the deprecated positional scheme is flagged.
+fn lint_nip10(tags: &[Vec<String>], out: &mut Vec<Diagnostic>) {
+    let mut roots = 0;
+    let mut replies = 0;
+    let mut marked = 0;
+    let mut positional = 0;
+
+    for (index, tag) in tags.iter().enumerate() {
+        if tag.first().map(String::as_str) != Some("e") {
+            continue;
+        }
+        match tag.get(3).map(String::as_str).unwrap_or_default() {
+            "" => positional += 1,
+            "root" => {
+                roots += 1;
+                marked += 1;
+            }
+            "reply" => {
+                replies += 1;
+                marked += 1;
+            }
+            "mention" => marked += 1,
+            other => out.push(Diagnostic::error(
+                "nip10-marker",
+                format!("tags[{}]", index),
+                format!(
+                    "unknown e tag marker \"{}\"; NIP-10 markers are {}",
+                    other,
+                    NIP10_MARKERS.join(", ")
+                ),
+            )),
+        }
+    }
+
+    if roots > 1 {
+        out.push(Diagnostic::error(
+            "nip10-marker",
+            "tags",
+            format!("{} e tags are marked \"root\"; a reply has one root", roots),
+        ));
+    }
+    if replies > 1 {
+        out.push(Diagnostic::error(
+            "nip10-marker",
+            "tags",
+            format!(
+                "{} e tags are marked \"reply\"; a reply has one parent",
+                replies
+            ),
+        ));
+    }
+    if replies > 0 && roots == 0 {
+        out.push(Diagnostic::warning(
+            "nip10-marker",
+            "tags",
+            "a \"reply\" e tag without a \"root\" one; a direct reply to the root marks it \"root\"",
+        ));
+    }
+    if positional > 0 && marked > 0 {
+        out.push(Diagnostic::warning(
+            "nip10-marker",
+            "tags",
+            "marked and unmarked e tags are mixed; clients read the thread differently",
+        ));
+    } else if positional > 0 {
+        out.push(Diagnostic::warning(
+            "nip10-marker",
+            "tags",
+            "e tags without markers use NIP-10's deprecated positional scheme",
+        ));
+    }
+}
+
+/// A kind:3 replaces the whole follow list, so anything odd in it is worth
+/// a look before it goes out.
+fn lint_contact_list(tags: &[Vec<String>], content: &str, out: &mut Vec<Diagnostic>) {
+    let mut seen = HashSet::new();
+    let mut follows = 0;
+
+    for (index, tag) in tags.iter().enumerate() {
+        let path = format!("tags[{}]", index);
+        match tag.as_slice() {
+            [name, pubkey, rest @ ..] if name == "p" => {
+                follows += 1;
+                if !seen.insert(pubkey.as_str()) {
+                    out.push(Diagnostic::warning(
+                        "contact-list",
+                        path.clone(),
+                        format!("{} is followed twice", pubkey),
+                    ));
+                }
+                if let Some(relay) = rest.first().filter(|r| !r.is_empty()) {
+                    if !relay.starts_with("wss://") && !relay.starts_with("ws://") {
+                        out.push(Diagnostic::warning(
+                            "contact-list",
+                            path,
+                            format!("relay hint \"{}\" is not a ws:// or wss:// URL", relay),
+                        ));
+                    }
+                }
+            }
+            [name, ..] if name == "p" => out.push(Diagnostic::error(
+                "contact-list",
+                path,
+                "a p tag without a pubkey",
+            )),
+            _ => out.push(Diagnostic::warning(
+                "contact-list",
+                path,
+                "a kind:3 holds only p tags; clients ignore anything else",
+            )),
+        }
+    }
+
+    if follows == 0 {
+        out.push(Diagnostic::warning(
+            "contact-list",
+            "tags",
+            "no p tags: publishing this empties the follow list",
+        ));
+    }
+    if !content.is_empty() && serde_json::from_str::<Value>(content).is_err() {
+        out.push(Diagnostic::warning(
+            "contact-list",
+            "content",
+            "kind:3 content is empty or a legacy relay-list JSON object",
+        ));
+    }
+}
+
+fn lint_d_tag(kind: u16, tags: &[Vec<String>], out: &mut Vec<Diagnostic>) {
+    let d_tags: Vec<usize> = tags
+        .iter()
+        .enumerate()
+        .filter(|(_, tag)| tag.first().map(String::as_str) == Some("d"))
+        .map(|(index, _)| index)
+        .collect();
+
+    if (30000..40000).contains(&kind) {
+        match d_tags.as_slice() {
+            [] => out.push(Diagnostic::error(
+                "d-tag",
+                "tags",
+                format!(
+                    "kind:{} is addressable and needs a d tag (use [\"d\", \"\"] for a single one)",
+                    kind
+                ),
+            )),
+            [_] => {}
+            [_, rest @ ..] => out.push(Diagnostic::error(
+                "d-tag",
+                format!("tags[{}]", rest[0]),
+                "more than one d tag; relays address the event by the first one only",
+            )),
+        }
+    } else if kind == 0 || kind == 3 || (10000..20000).contains(&kind) {
+        if let Some(index) = d_tags.first() {
+            out.push(Diagnostic::warning(
+                "d-tag",
+                format!("tags[{}]", index),
+                format!(
+                    "kind:{} is replaceable per author, not per d tag; the d tag is ignored",
+                    kind
+                ),
+            ));
+        }
+    }
+}
+
+/// NIP-40: one `expiration` tag, a unix timestamp in seconds, after the event
+/// was created.
+fn lint_expiration(tags: &[Vec<String>], created_at: Timestamp, out: &mut Vec<Diagnostic>) {
+    let expirations: Vec<(usize, &Vec<String>)> = tags
+        .iter()
+        .enumerate()
+        .filter(|(_, tag)| tag.first().map(String::as_str) == Some("expiration"))
+        .collect();
+
+    if expirations.len() > 1 {
+        out.push(Diagnostic::error(
+            "expiration",
+            format!("tags[{}]", expirations[1].0),
+            "more than one expiration tag",
+        ));
+    }
+    for (index, tag) in expirations {
+        let path = format!("tags[{}]", index);
+        match tag.get(1).map(|v| v.parse::<u64>()) {
+            Some(Ok(at)) if at <= created_at.as_u64() => out.push(Diagnostic::warning(
+                "expiration",
+                path,
+                format!(
+                    "expires at {}, which is not after created_at {}: relays drop it on arrival",
+                    at,
+                    created_at.as_u64()
+                ),
+            )),
+            Some(Ok(_)) => {}
+            _ => out.push(Diagnostic::error(
+                "expiration",
+                path,
+                "the expiration value must be a unix timestamp in seconds, e.g. [\"expiration\", \"1700000000\"]",
+            )),
+        }
+    }
+}
+
+fn lint_required_tags(kind: u16, tags: &[Vec<String>], out: &mut Vec<Diagnostic>) {
+    let Some((_, nip, groups)) = REQUIRED_TAGS.iter().find(|(k, _, _)| *k == kind) else {
+        return;
+    };
+    for group in groups.iter() {
+        let present = tags.iter().any(|tag| {
+            tag.first()
+                .is_some_and(|name| group.contains(&name.as_str()))
+        });
+        if !present {
+            out.push(Diagnostic::error(
+                "required-tag",
+                "tags",
+                format!(
+                    "kind:{} needs {} tag ({})",
+                    kind,
+                    group
+                        .iter()
+                        .map(|name| format!("a {}", name))
+                        .collect::<Vec<_>>()
+                        .join(" or "),
+                    nip
+                ),
+            ));
+        }
+    }
+}
+
+/// Every NIP rule `lint` knows, applied to one event — signed, or an unsigned
+/// `{kind, content, tags}` spec. `now` stands in for `created_at` when the
+/// event has none.
+///
+/// Pure — it inspects JSON that is already in hand.
+pub fn lint_value(value: &Value, now: Timestamp) -> Vec<Diagnostic> {
+    let mut out = Vec::new();
+
+    let Some(object) = value.as_object() else {
+        out.push(Diagnostic::error(
+            "structure",
+            "",
+            "an event must be a JSON object",
+        ));
+        return out;
+    };
+
+    let kind = match object.get("kind").and_then(Value::as_u64) {
+        Some(kind) if kind <= u16::MAX as u64 => kind as u16,
+        _ => {
+            out.push(Diagnostic::error(
+                "structure",
+                "kind",
+                "\"kind\" is required and must be an integer from 0 to 65535",
+            ));
+            return out;
+        }
+    };
+
+    let content = match object.get("content") {
+        None if object.contains_key("sig") => {
+            out.push(Diagnostic::error(
+                "structure",
+                "content",
+                "a signed event needs \"content\" (the empty string at least)",
+            ));
+            String::new()
+        }
+        None => String::new(),
+        Some(Value::String(content)) => content.clone(),
+        Some(_) => {
+            out.push(Diagnostic::error(
+                "structure",
+                "content",
+                "\"content\" must be a string",
+            ));
+            String::new()
+        }
+    };
+
+    let mut tags: Vec<Vec<String>> = Vec::new();
+    match object.get("tags") {
+        None => {}
+        Some(Value::Array(raw)) => {
+            for (index, tag) in raw.iter().enumerate() {
+                let values: Option<Vec<String>> = tag.as_array().and_then(|values| {
+                    values
+                        .iter()
+                        .map(|v| v.as_str().map(str::to_string))
+                        .collect()
+                });
+                match values {
+                    Some(values) => {
+                        if let Err(err) = event::validate_tag(index, &values) {
+                            out.push(Diagnostic::error(
+                                "tag",
+                                format!("tags[{}]", index),
+                                err.to_string(),
+                            ));
+                        }
+                        tags.push(values);
+                    }
+                    None => {
+                        out.push(Diagnostic::error(
+                            "structure",
+                            format!("tags[{}]", index),
+                            "a tag must be an array of strings",
+                        ));
+                        tags.push(Vec::new());
+                    }
+                }
+            }
+        }
+        Some(_) => out.push(Diagnostic::error(
+            "structure",
+            "tags",
+            "\"tags\" must be an array of arrays of strings",
+        )),
+    }
+
+    lint_signed_fields(object, &mut out);
+
+    let created_at = object
+        .get("created_at")
+        .and_then(Value::as_u64)
+        .map(Timestamp::from)
+        .unwrap_or(now);
+
+    if kind == 1 {
+        lint_nip10(&tags, &mut out);
+    }
+    if kind == 3 {
+        lint_contact_list(&tags, &content, &mut out);
+    }
+    lint_d_tag(kind, &tags, &mut out);
+    lint_expiration(&tags, created_at, &mut out);
+    lint_required_tags(kind, &tags, &mut out);
+
+    out
+}
+
+/// `verify <file|->`: check the id and signature of every event.
+pub fn run(source: &Path) -> Result<()> {
+    let documents = parse_documents(&event::read_source(source)?)?;
+
+    let json = output::is_json();
+    if json {
+        output::open_json_lines()?;
+    } else {
+        output::open_body()?;
+    }
+    let mut failures = 0;
+    for document in &documents {
+        let verification = verify_document(document);
+        if !verification.ok {
+            failures += 1;
+        }
+        if json {
+            output::write_json_line(&serde_json::to_value(&verification)?)?;
+        } else {
+            match (&verification.id, &verification.error) {
+                (Some(id), None) => outln!("{}: ok {}", location(document), id)?,
+                (id, Some(error)) => outln!(
+                    "{}: FAILED{} — {}",
+                    location(document),
+                    id.as_ref().map(|id| format!(" {}", id)).unwrap_or_default(),
+                    error
+                )?,
+                (None, None) => unreachable!("a verification without an id has an error"),
+            }
+        }
+    }
+
+    eprintln!(
+        "{} of {} event(s) verified.",
+        documents.len() - failures,
+        documents.len()
+    );
+    if failures > 0 {
+        bail!("{} event(s) failed verification", failures);
+    }
+    Ok(())
+}
+
+/// `lint <file|->`: apply the NIP rules to every event. Errors fail the run;
+/// warnings are reported but do not.
+pub fn lint(source: &Path) -> Result<()> {
+    let documents = parse_documents(&event::read_source(source)?)?;
+    let now = Timestamp::now();
+
+    let json = output::is_json();
+    if json {
+        output::open_json_lines()?;
+    } else {
+        output::open_body()?;
+    }
+    let mut errors = 0;
+    let mut warnings = 0;
+    for document in &documents {
+        let diagnostics = match &document.json {
+            Ok(value) => lint_value(value, now),
+            Err(err) => vec![Diagnostic::error("structure", "", err.clone())],
+        };
+        let event_errors = diagnostics
+            .iter()
+            .filter(|d| d.severity == Severity::Error)
+            .count();
+        errors += event_errors;
+        warnings += diagnostics.len() - event_errors;
+
+        if json {
+            output::write_json_line(&serde_json::json!({
+                "index": document.index,
+                "line": document.line,
+                "ok": event_errors == 0,
+                "diagnostics": diagnostics,
+            }))?;
+        } else {
+            for diagnostic in &diagnostics {
+                let severity = match diagnostic.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                };
+                let path = if diagnostic.path.is_empty() {
+                    String::new()
+                } else {
+                    format!(" {}:", diagnostic.path)
+                };
+                outln!(
+                    "{}: {}[{}]{} {}",
+                    location(document),
+                    severity,
+                    diagnostic.rule,
+                    path,
+                    diagnostic.message
+                )?;
+            }
+        }
+    }
+
+    eprintln!(
+        "{} event(s) checked: {} error(s), {} warning(s).",
+        documents.len(),
+        errors,
+        warnings
+    );
+    if errors > 0 {
+        bail!("lint found {} error(s)", errors);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(value: Value) -> Vec<(Severity, &'static str)> {
+        lint_value(&value, Timestamp::from(1_700_000_000))
+            .into_iter()
+            .map(|d| (d.severity, d.rule))
+            .collect()
+    }
+
+    #[test]
+    fn documents_are_an_object_an_array_or_json_lines() {
+        assert_eq!(parse_documents(r#"{"kind":1}"#).unwrap().len(), 1);
+        assert_eq!(
+            parse_documents(r#"[{"kind":1},{"kind":7}]"#).unwrap().len(),
+            2
+        );
+
+        let lines = parse_documents("{\"kind\":1}\n\nnot json\n{\"kind\":3}\n").unwrap();
+        let positions: Vec<(usize, Option<usize>, bool)> = lines
+            .iter()
+            .map(|d| (d.index, d.line, d.json.is_ok()))
+            .collect();
+        assert_eq!(
+            positions,
+            vec![(0, Some(1), true), (1, Some(3), false), (2, Some(4), true)]
+        );
+        assert!(parse_documents("  \n").is_err());
+    }
+
+    #[test]
+    fn verify_catches_edits_and_forged_signatures() {
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("hello")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let document = |json: Value| Document {
+            index: 0,
+            line: None,
+            json: Ok(json),
+        };
+
+        let good = verify_document(&document(serde_json::to_value(&event).unwrap()));
+        assert!(good.ok && good.id_valid && good.sig_valid);
+
+        let mut edited = serde_json::to_value(&event).unwrap();
+        edited["content"] = Value::String("goodbye".into());
+        let edited = verify_document(&document(edited));
+        assert!(!edited.ok && !edited.id_valid);
+
+        let other = EventBuilder::text_note("other")
+            .sign_with_keys(&keys)
+            .unwrap();
+        let mut forged = serde_json::to_value(&event).unwrap();
+        forged["sig"] = serde_json::to_value(other.sig).unwrap();
+        let forged = verify_document(&document(forged));
+        assert!(!forged.ok && forged.id_valid && !forged.sig_valid);
+
+        let unsigned = verify_document(&document(serde_json::json!({"kind": 1})));
+        assert!(!unsigned.ok && unsigned.id.is_none());
+    }
+
+    #[test]
+    fn nip10_markers_are_checked_on_text_notes() {
+        let root = "a".repeat(64);
+        let parent = "b".repeat(64);
+        assert!(rules(serde_json::json!({
+            "kind": 1,
+            "tags": [["e", root, "", "root"], ["e", parent, "", "reply"]]
+        }))
+        .is_empty());
+
+        assert_eq!(
+            rules(serde_json::json!({"kind": 1, "tags": [["e", root, "", "parent"]]})),
+            vec![(Severity::Error, "nip10-marker")]
+        );
+        assert_eq!(
+            rules(serde_json::json!({"kind": 1, "tags": [["e", parent, "", "reply"]]})),
+            vec![(Severity::Warning, "nip10-marker")]
+        );
+        assert_eq!(
+            rules(serde_json::json!({"kind": 1, "tags": [["e", root]]})),
+            vec![(Severity::Warning, "nip10-marker")]
+        );
+    }
+
+    #[test]
+    fn contact_lists_hold_hex_p_tags_only() {
+        let alice = "a".repeat(64);
+        assert!(rules(serde_json::json!({
+            "kind": 3,
+            "tags": [["p", alice, "wss://relay.example.com", "alice"]]
+        }))
+        .is_empty());
+
+        let found = rules(serde_json::json!({
+            "kind": 3,
+            "tags": [["p", "npub1xyz"], ["p", alice, "relay.example.com"], ["p", alice], ["t", "nostr"]]
+        }));
+        assert_eq!(found[0], (Severity::Error, "tag"));
+        assert_eq!(
+            found[1..].to_vec(),
+            vec![
+                (Severity::Warning, "contact-list"),
+                (Severity::Warning, "contact-list"),
+                (Severity::Warning, "contact-list"),
+            ]
+        );
+
+        assert_eq!(
+            rules(serde_json::json!({"kind": 3, "tags": []})),
+            vec![(Severity::Warning, "contact-list")]
+        );
+    }
+
+    #[test]
+    fn addressable_kinds_need_exactly_one_d_tag() {
+        assert_eq!(
+            rules(serde_json::json!({"kind": 30023, "content": "# hi"})),
+            vec![(Severity::Error, "d-tag")]
+        );
+        assert_eq!(
+            rules(serde_json::json!({"kind": 30023, "tags": [["d", "a"], ["d", "b"]]})),
+            vec![(Severity::Error, "d-tag")]
+        );
+        assert!(rules(serde_json::json!({"kind": 30023, "tags": [["d", ""]]})).is_empty());
+        assert_eq!(
+            rules(serde_json::json!({"kind": 10002, "tags": [["d", "x"]]})),
+            vec![(Severity::Warning, "d-tag")]
+        );
+    }
+
+    #[test]
+    fn expirations_are_timestamps_after_creation() {
+        assert!(rules(serde_json::json!({
+            "kind": 1, "created_at": 1_700_000_000, "tags": [["expiration", "1700003600"]]
+        }))
+        .iter()
+        .all(|(_, rule)| *rule != "expiration"));
+        assert_eq!(
+            rules(serde_json::json!({"kind": 1, "tags": [["expiration", "in 2h"]]})),
+            vec![(Severity::Error, "expiration")]
+        );
+        assert_eq!(
+            rules(serde_json::json!({"kind": 1, "tags": [["expiration", "1600000000"]]})),
+            vec![(Severity::Warning, "expiration")]
+        );
+    }
+
+    #[test]
+    fn kinds_report_the_tags_they_cannot_do_without() {
+        assert_eq!(
+            rules(serde_json::json!({"kind": 7, "content": "+"})),
+            vec![
+                (Severity::Error, "required-tag"),
+                (Severity::Error, "required-tag")
+            ]
+        );
+        assert!(rules(serde_json::json!({
+            "kind": 7, "content": "+", "tags": [["e", "a".repeat(64)], ["p", "b".repeat(64)]]
+        }))
+        .is_empty());
+    }
+
+    #[test]
+    fn structure_problems_are_errors() {
+        assert_eq!(
+            rules(serde_json::json!([1, 2])),
+            vec![(Severity::Error, "structure")]
+        );
+        assert_eq!(
+            rules(serde_json::json!({"kind": "1"})),
+            vec![(Severity::Error, "structure")]
+        );
+        assert_eq!(
+            rules(serde_json::json!({"kind": 1, "content": "", "tags": [["t", 5]], "sig": "zz"})),
+            vec![
+                (Severity::Error, "structure"),
+                (Severity::Error, "signed-fields"),
+                (Severity::Warning, "signed-fields"),
+            ]
+        );
+    }
+}
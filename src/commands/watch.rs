@@ -4,10 +4,16 @@ use serde::Serialize;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::time::Duration;
 
+use crate::cache::CacheDb;
 use crate::client;
 use crate::config::NostaroConfig;
 use crate::keys;
-use crate::utils::resolve_pubkey;
+use crate::nip05::{self, Check};
+use crate::post_options;
+use crate::utils::{resolve_pubkey_online, resolve_pubkeys};
+
+use super::mute::{self, Mutes};
+use super::snapshot::snapshot_event;
 
 /// How stale an event can be (relative to now) before watch drops it as a replay.
 const MAX_EVENT_AGE_SECS: u64 = 300;
@@ -22,13 +28,13 @@ const AUTHOR_NAME_FETCH_TIMEOUT: Duration = Duration::from_secs(3);
 /// than `MAX_EVENT_AGE_SECS` (relays sometimes replay old events on resubscribe).
 /// Shared by both the Discord-webhook loop and the `--json` loop so a fix to this logic
 /// only needs to be made once.
-struct EventDeduplicator {
+pub(crate) struct EventDeduplicator {
     seen: HashSet<EventId>,
     order: VecDeque<EventId>,
 }
 
 impl EventDeduplicator {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Self {
             seen: HashSet::new(),
             order: VecDeque::new(),
@@ -36,7 +42,8 @@ impl EventDeduplicator {
     }
 
     /// Returns true if `event` is fresh and unseen and should be processed.
-    fn accept(&mut self, event: &Event) -> bool {
+    /// An event past its NIP-40 expiration is never fresh, whatever its age.
+    pub(crate) fn accept(&mut self, event: &Event) -> bool {
         let now = chrono::Utc::now().timestamp() as u64;
         let created_at = event.created_at.as_u64();
         if now > created_at && now - created_at > MAX_EVENT_AGE_SECS {
@@ -46,8 +53,19 @@ impl EventDeduplicator {
             );
             return false;
         }
+        if post_options::is_expired(event, Timestamp::from(now)) {
+            eprintln!("Skipping expired event: {}", event.id);
+            return false;
+        }
+
+        self.remember(event.id)
+    }
 
-        if self.seen.contains(&event.id) {
+    /// Record `id` as seen without the freshness checks, e.g. for events that
+    /// were already shown before the subscription opened. Returns false if it
+    /// was already known.
+    pub(crate) fn remember(&mut self, id: EventId) -> bool {
+        if self.seen.contains(&id) {
             return false;
         }
         if self.seen.len() >= MAX_SEEN_EVENTS {
@@ -55,8 +73,8 @@ impl EventDeduplicator {
                 self.seen.remove(&oldest);
             }
         }
-        self.seen.insert(event.id);
-        self.order.push_back(event.id);
+        self.seen.insert(id);
+        self.order.push_back(id);
         true
     }
 }
@@ -453,10 +471,7 @@ pub async fn run(
     let config = NostaroConfig::load()?;
     let own_keys = keys::keys_from_config(&config)?;
 
-    let author_pubkeys: Vec<PublicKey> = authors
-        .iter()
-        .map(|a| resolve_pubkey(a))
-        .collect::<Result<Vec<_>>>()?;
+    let author_pubkeys = resolve_pubkeys(authors).await?;
 
     let nostr_client = if relays.is_empty() {
         client::create_client(&own_keys, &config).await?
@@ -468,7 +483,7 @@ pub async fn run(
     // `--npub` picks the watched pubkey; without it we watch our own. Same for both
     // output modes.
     let target_pubkey = match npub_str {
-        Some(pk) => resolve_pubkey(pk)?,
+        Some(pk) => resolve_pubkey_online(pk).await?,
         None => own_pubkey,
     };
     // Computed before the --json branch so both modes build the filter from exactly the
@@ -508,6 +523,9 @@ pub async fn run(
             .await?;
 
         let list = client::fetch_contact_list(&nostr_client, &own_pubkey).await?;
+        if let (Some(event), Ok(cache)) = (&list, CacheDb::open()) {
+            snapshot_event(&cache, event);
+        }
         let list = match list {
             Some(event) if !contacts_from_event(&event).is_empty() => event,
             _ => bail!(
@@ -525,8 +543,12 @@ pub async fn run(
         );
     }
 
+    // Read once at startup, like the rest of the configuration; both output
+    // modes drop muted events as soon as our own kind:3 updates are handled.
+    let mutes = mute::load(&nostr_client, &own_keys).await;
+
     if json_output {
-        return watch_json(&nostr_client, &mut watch_filter, own_pubkey).await;
+        return watch_json(&nostr_client, &mut watch_filter, own_pubkey, &mutes).await;
     }
     let webhook_url = webhook_url.expect("checked above");
 
@@ -562,6 +584,7 @@ pub async fn run(
     println!("Press Ctrl+C to stop.\n");
 
     let mut profile_cache: HashMap<PublicKey, (String, Option<String>)> = HashMap::new();
+    let db = CacheDb::open().ok();
     let http_client = reqwest::Client::new();
     let mut dedup = EventDeduplicator::new();
 
@@ -586,6 +609,10 @@ pub async fn run(
                 continue;
             }
 
+            if mutes.is_muted(&event) {
+                continue;
+            }
+
             // Channel messages are picked by the channel subscription and checked for
             // channel membership below, but `--author` still applies to them. Everything
             // else goes through the shared filter, exactly as in --json mode.
@@ -603,8 +630,13 @@ pub async fn run(
                 continue;
             };
 
-            let (sender_name, sender_avatar) =
-                get_profile_info(&nostr_client, &event.pubkey, &mut profile_cache).await;
+            let (sender_name, sender_avatar) = get_profile_info(
+                &nostr_client,
+                &event.pubkey,
+                &mut profile_cache,
+                db.as_ref(),
+            )
+            .await;
 
             let note_id = event.id.to_bech32()?;
 
@@ -640,7 +672,10 @@ pub async fn run(
                 Kind::TextNote => match reason {
                     Some(MatchReason::Keyword(ref kw)) => format!(
                         "🔍 **keyword match: {}**\n{}\n> {}\nnote: {}",
-                        kw, sender_name, event.content, note_id
+                        kw,
+                        sender_name,
+                        post_options::display_content(&event),
+                        note_id
                     ),
                     ref other => {
                         let has_e_tag = event.tags.iter().any(|t| {
@@ -649,7 +684,11 @@ pub async fn run(
                         let (emoji, label) = text_note_label(other.as_ref(), has_e_tag);
                         format!(
                             "{} **{}** from {}\n> {}\n🔗 {}",
-                            emoji, label, sender_name, event.content, note_id
+                            emoji,
+                            label,
+                            sender_name,
+                            post_options::display_content(&event),
+                            note_id
                         )
                     }
                 },
@@ -768,8 +807,9 @@ pub async fn run(
                         .pubkey
                         .to_bech32()
                         .unwrap_or_else(|_| event.pubkey.to_hex());
-                    let content_preview: String = event.content.chars().take(500).collect();
-                    let ellipsis = if event.content.chars().count() > 500 {
+                    let content = post_options::display_content(&event);
+                    let content_preview: String = content.chars().take(500).collect();
+                    let ellipsis = if content.chars().count() > 500 {
                         "..."
                     } else {
                         ""
@@ -921,6 +961,9 @@ struct JsonEvent {
     note_id: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     author_name: Option<String>,
+    /// The author's NIP-05 claim and whether its domain vouches for it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nip05: Option<Check>,
     created_at: u64,
     kind: u16,
     content: String,
@@ -983,6 +1026,7 @@ async fn watch_json(
     nostr_client: &Client,
     watch_filter: &mut WatchFilter,
     own_pubkey: PublicKey,
+    mutes: &Mutes,
 ) -> Result<()> {
     eprintln!("Watching (JSON mode)");
     for line in filter_description(watch_filter)? {
@@ -993,7 +1037,8 @@ async fn watch_json(
     let mut active_ids = subscribe_watch(nostr_client, watch_filter, Timestamp::now()).await?;
 
     let mut dedup = EventDeduplicator::new();
-    let mut author_name_cache: HashMap<PublicKey, Option<String>> = HashMap::new();
+    let mut author_cache: HashMap<PublicKey, AuthorInfo> = HashMap::new();
+    let db = CacheDb::open().ok();
 
     let mut notifications = nostr_client.notifications();
     while let Ok(notification) = notifications.recv().await {
@@ -1014,11 +1059,15 @@ async fn watch_json(
                 continue;
             }
 
+            if mutes.is_muted(&event) {
+                continue;
+            }
+
             if watch_filter.match_event(&event).is_none() {
                 continue;
             }
 
-            match build_json_event(nostr_client, &event, &mut author_name_cache).await {
+            match build_json_event(nostr_client, &event, &mut author_cache, db.as_ref()).await {
                 Ok(line) => println!("{}", line),
                 Err(e) => eprintln!("Failed to serialize event {}: {}", event.id, e),
             }
@@ -1031,9 +1080,10 @@ async fn watch_json(
 async fn build_json_event(
     nostr_client: &Client,
     event: &Event,
-    author_name_cache: &mut HashMap<PublicKey, Option<String>>,
+    author_cache: &mut HashMap<PublicKey, AuthorInfo>,
+    db: Option<&CacheDb>,
 ) -> Result<String> {
-    let author_name = get_author_name(nostr_client, &event.pubkey, author_name_cache).await;
+    let (author_name, nip05) = get_author_info(nostr_client, &event.pubkey, author_cache, db).await;
 
     let json_event = JsonEvent {
         id: event.id.to_hex(),
@@ -1041,6 +1091,7 @@ async fn build_json_event(
         npub: event.pubkey.to_bech32().ok(),
         note_id: event.id.to_bech32().ok(),
         author_name,
+        nip05,
         created_at: event.created_at.as_u64(),
         kind: event.kind.as_u16(),
         content: event.content.clone(),
@@ -1050,31 +1101,52 @@ async fn build_json_event(
     Ok(serde_json::to_string(&json_event)?)
 }
 
-async fn get_author_name(
+/// The author's display name and NIP-05 check, looked up once per run.
+type AuthorInfo = (Option<String>, Option<Check>);
+
+async fn get_author_info(
     nostr_client: &Client,
     pubkey: &PublicKey,
-    cache: &mut HashMap<PublicKey, Option<String>>,
-) -> Option<String> {
-    if let Some(name) = cache.get(pubkey) {
-        return name.clone();
+    cache: &mut HashMap<PublicKey, AuthorInfo>,
+    db: Option<&CacheDb>,
+) -> AuthorInfo {
+    if let Some(info) = cache.get(pubkey) {
+        return info.clone();
     }
 
-    let name =
+    let info =
         match client::fetch_profile_with_timeout(nostr_client, pubkey, AUTHOR_NAME_FETCH_TIMEOUT)
             .await
         {
-            Ok(Some(metadata)) => resolve_display_name(&metadata),
-            _ => None,
+            Ok(Some(metadata)) => (
+                resolve_display_name(&metadata),
+                verify_claim(db, pubkey, &metadata).await,
+            ),
+            _ => (None, None),
         };
 
-    cache.insert(*pubkey, name.clone());
-    name
+    cache.insert(*pubkey, info.clone());
+    info
+}
+
+/// Check the NIP-05 address in `metadata`, if it claims one.
+async fn verify_claim(
+    db: Option<&CacheDb>,
+    pubkey: &PublicKey,
+    metadata: &Metadata,
+) -> Option<Check> {
+    let claimed = metadata
+        .nip05
+        .as_deref()
+        .filter(|nip05| !nip05.is_empty())?;
+    nip05::verify(db, pubkey, claimed).await
 }
 
 async fn get_profile_info(
     nostr_client: &Client,
     pubkey: &PublicKey,
     cache: &mut HashMap<PublicKey, (String, Option<String>)>,
+    db: Option<&CacheDb>,
 ) -> (String, Option<String>) {
     if let Some(info) = cache.get(pubkey) {
         return info.clone();
@@ -1084,7 +1156,14 @@ async fn get_profile_info(
 
     let info = match client::fetch_profile(nostr_client, pubkey).await {
         Ok(Some(metadata)) => {
-            let display = resolve_display_name(&metadata).unwrap_or_else(|| npub.clone());
+            let mut display = resolve_display_name(&metadata).unwrap_or_else(|| npub.clone());
+            // Shown as the webhook's user name, so only the mark: the address
+            // itself would not fit Discord's 80 characters.
+            match verify_claim(db, pubkey, &metadata).await {
+                Some(check) if check.verified => display.push_str(" ✔"),
+                Some(_) => display.push_str(" ✘"),
+                None => {}
+            }
             let picture = metadata
                 .picture
                 .map(|u| u.to_string())
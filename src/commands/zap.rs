@@ -7,7 +7,7 @@ use serde::Deserialize;
 use crate::client;
 use crate::config::NostaroConfig;
 use crate::keys;
-use crate::utils::resolve_pubkey;
+use crate::utils::resolve_pubkey_with_hints;
 
 #[derive(Deserialize)]
 struct LnurlResponse {
@@ -30,7 +30,8 @@ pub async fn run(target: &str, amount: u64, message: Option<&str>) -> Result<()>
     let keys = keys::keys_from_config(&config)?;
     let nostr_client = client::create_client(&keys, &config).await?;
 
-    let target_pubkey = resolve_pubkey(target)?;
+    let (target_pubkey, hints) = resolve_pubkey_with_hints(target).await?;
+    client::add_relay_hints(&nostr_client, &hints).await;
 
     let metadata = client::fetch_profile(&nostr_client, &target_pubkey)
         .await?
@@ -99,6 +100,18 @@ pub async fn run(target: &str, amount: u64, message: Option<&str>) -> Result<()>
 
     let target_npub = target_pubkey.to_bech32()?;
 
+    // A dry run goes as far as getting the invoice — which proves the LNURL
+    // endpoint accepts the request — and stops before any money moves.
+    if client::is_dry_run() {
+        println!("Dry run: signed zap request (kind:9734), not paid:");
+        println!("{}", serde_json::to_string_pretty(&zap_request)?);
+        println!("Invoice for {} sats to {}:", amount, target_npub);
+        println!("{}", invoice_resp.pr);
+        println!("Dry run: nothing was paid or published.");
+        nostr_client.disconnect().await;
+        return Ok(());
+    }
+
     // Try Coinos API first, fall back to Cashu
     let coinos_token_path = config.coinos_api_token_path.clone().unwrap_or_else(|| {
         "/Users/kojira/.openclaw/workspace/data/secrets/coinos_api_token.txt".to_string()
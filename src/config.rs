@@ -14,6 +14,10 @@ pub struct NostaroConfig {
     pub blossom_server: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub coinos_api_token_path: Option<String>,
+    /// Largest fraction of the previous follow list a kind:3 update may drop
+    /// before `follow`/`unfollow` refuse to publish it (default 0.1)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contact_drop_limit: Option<f64>,
 }
 
 impl Default for NostaroConfig {
@@ -30,6 +34,7 @@ impl Default for NostaroConfig {
             default_relays,
             blossom_server: None,
             coinos_api_token_path: None,
+            contact_drop_limit: None,
         }
     }
 }
@@ -97,6 +102,11 @@ impl NostaroConfig {
             .clone()
             .unwrap_or_else(|| "https://blossom.primal.net".to_string())
     }
+
+    /// `contact_drop_limit`, clamped to 0..=1. 1 turns the guard off.
+    pub fn contact_drop_limit(&self) -> f64 {
+        self.contact_drop_limit.unwrap_or(0.1).clamp(0.0, 1.0)
+    }
 }
 
 #[cfg(test)]
@@ -139,6 +149,7 @@ mod tests {
             default_relays: vec!["wss://default.relay".to_string()],
             blossom_server: None,
             coinos_api_token_path: None,
+            contact_drop_limit: None,
         };
         let serialized = toml::to_string_pretty(&config).unwrap();
         let deserialized: NostaroConfig = toml::from_str(&serialized).unwrap();
@@ -159,6 +170,7 @@ mod tests {
             default_relays: vec!["wss://default.test.com".to_string()],
             blossom_server: None,
             coinos_api_token_path: None,
+            contact_drop_limit: None,
         };
         config.save_to(&path).unwrap();
 
@@ -186,6 +198,7 @@ mod tests {
             default_relays: vec!["wss://relay.damus.io".to_string()],
             blossom_server: None,
             coinos_api_token_path: None,
+            contact_drop_limit: None,
         };
         let serialized = toml::to_string_pretty(&config).unwrap();
         assert!(!serialized.contains("blossom_server"));
@@ -202,4 +215,20 @@ default_relays = ["wss://relay.damus.io"]
         assert!(config.blossom_server.is_none());
         assert_eq!(config.blossom_url(), "https://blossom.primal.net");
     }
+
+    #[test]
+    fn test_contact_drop_limit_defaults_and_clamps() {
+        let config: NostaroConfig = toml::from_str("relays = []\ndefault_relays = []").unwrap();
+        assert_eq!(config.contact_drop_limit(), 0.1);
+
+        let config: NostaroConfig =
+            toml::from_str("relays = []\ndefault_relays = []\ncontact_drop_limit = 0.5").unwrap();
+        assert_eq!(config.contact_drop_limit(), 0.5);
+
+        let config = NostaroConfig {
+            contact_drop_limit: Some(3.0),
+            ..NostaroConfig::default()
+        };
+        assert_eq!(config.contact_drop_limit(), 1.0);
+    }
 }
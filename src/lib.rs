@@ -3,5 +3,7 @@ pub mod client;
 pub mod commands;
 pub mod config;
 pub mod keys;
+pub mod nip05;
 pub mod output;
+pub mod post_options;
 pub mod utils;
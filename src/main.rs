@@ -1,6 +1,7 @@
 use clap::{Parser, Subcommand};
 use nostaro::commands;
 use nostaro::output::{self, OutFormat};
+use nostaro::post_options::PostOptions;
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -12,7 +13,8 @@ struct Cli {
 
     /// Write the bulk output to this file (overwriting it) instead of stdout;
     /// stdout then keeps only the summary. Supported by: following, followers,
-    /// timeline, search
+    /// timeline, search, thread, notifications, get, decode, encode, verify,
+    /// lint
     #[arg(long, global = true)]
     out: Option<PathBuf>,
 
@@ -20,6 +22,12 @@ struct Cli {
     #[arg(long, global = true, value_enum, requires = "out")]
     out_format: Option<OutFormat>,
 
+    /// Build and sign the event(s) and print them instead of publishing.
+    /// Follow/unfollow also show the change to the contact list; dm shows the
+    /// message before it is sealed; zap stops once it has the invoice
+    #[arg(long, global = true)]
+    dry_run: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -39,6 +47,8 @@ enum Commands {
         /// Quote repost: nevent1 or note1 to quote
         #[arg(long)]
         quote: Option<String>,
+        #[command(flatten)]
+        options: PostOptions,
     },
 
     /// Reply to a note (kind:1 with e/p tags)
@@ -47,12 +57,23 @@ enum Commands {
         note_id: String,
         /// Reply message
         message: String,
+        #[command(flatten)]
+        options: PostOptions,
     },
 
-    /// Repost a note (kind:6)
+    /// Repost an event: kind:6 for notes, kind:16 for other kinds (NIP-18);
+    /// with a comment it becomes a quote post
     Repost {
-        /// Note ID to repost (note1... or hex)
+        /// Event to repost: note1, nevent1, naddr1, or hex event ID
         note_id: String,
+        /// Comment to add, turning the repost into a quote post
+        comment: Option<String>,
+    },
+
+    /// Long-form articles (NIP-23) from Markdown files
+    Article {
+        #[command(subcommand)]
+        action: ArticleAction,
     },
 
     /// View your timeline (--global for the relay-wide feed)
@@ -67,15 +88,39 @@ enum Commands {
         /// instead of the notes of the people you follow
         #[arg(long)]
         global: bool,
+        /// Keep running after the first page and append new notes as they
+        /// arrive (JSON output becomes one note per line)
+        #[arg(short, long)]
+        follow: bool,
+        /// Show the members of one of your follow sets (`nostaro list`)
+        /// instead of the people you follow
+        #[arg(long, conflicts_with_all = ["global", "authors_file"])]
+        list: Option<String>,
+        /// Show the pubkeys listed in a file, one per line (`-` for stdin)
+        #[arg(long, value_name = "PATH", conflicts_with = "global")]
+        authors_file: Option<PathBuf>,
+        /// Only notes with this hashtag, `#` optional (repeatable; any of them
+        /// matches). From the people you follow, or anyone with --global
+        #[arg(long = "tag", value_name = "HASHTAG", conflicts_with_all = ["list", "authors_file"])]
+        tags: Vec<String>,
+        /// Read the feed from this relay only, instead of the configured ones.
+        /// The people you follow there, or everyone on it with --global
+        #[arg(long, value_name = "URL", conflicts_with_all = ["list", "authors_file"])]
+        relay: Option<String>,
     },
 
-    /// Search notes (NIP-50)
+    /// Search notes: NIP-50 full text, author/kind/hashtag/time filters, or both
     Search {
-        /// Search query
-        query: String,
+        /// Full-text query (NIP-50); optional when a filter is given
+        query: Option<String>,
         /// Maximum number of results
         #[arg(short, long, default_value_t = 20)]
         limit: usize,
+        /// Search the local cache's full-text index instead of the relays
+        #[arg(long, conflicts_with = "relays")]
+        local: bool,
+        #[command(flatten)]
+        filters: commands::search::SearchArgs,
     },
 
     /// View or set a Nostr profile
@@ -84,39 +129,103 @@ enum Commands {
         action: ProfileAction,
     },
 
-    /// Follow a user (kind:3)
+    /// Follow a user (kind:3), or review and restore earlier follow lists
+    #[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
     Follow {
-        /// Public key (npub or hex) to follow
-        npub: String,
+        /// Public key (npub, hex, nprofile, or NIP-05 name@domain) to follow
+        #[arg(required_unless_present_any = ["file", "copy_from", "from_list"])]
+        npub: Option<String>,
+        /// Petname to store with the follow (NIP-02); updates an existing follow
+        #[arg(long, conflicts_with_all = ["file", "copy_from", "from_list"])]
+        petname: Option<String>,
+        /// Relay URL to store with the follow as where to find them (NIP-02)
+        #[arg(long, conflicts_with_all = ["file", "copy_from", "from_list"])]
+        relay: Option<String>,
+        /// Follow everyone in this file: one npub, hex, nprofile or NIP-05
+        /// address per line, `#` comments allowed (`-` for stdin)
+        #[arg(long, value_name = "PATH", conflicts_with_all = ["npub", "copy_from", "from_list"])]
+        file: Option<PathBuf>,
+        /// Follow everyone this account follows
+        #[arg(long, value_name = "PUBKEY", conflicts_with_all = ["npub", "from_list"])]
+        copy_from: Option<String>,
+        /// Follow everyone in this NIP-51 set (naddr)
+        #[arg(long, value_name = "NADDR", conflicts_with = "npub")]
+        from_list: Option<String>,
+        /// Publish even if the new list drops more of the previous one than
+        /// `contact_drop_limit` allows
+        #[arg(long)]
+        force: bool,
+        /// With --file, --copy-from or --from-list: publish without asking
+        #[arg(short = 'y', long)]
+        yes: bool,
+        #[command(subcommand)]
+        action: Option<FollowAction>,
     },
 
     /// Unfollow a user (kind:3)
     Unfollow {
-        /// Public key (npub or hex) to unfollow
-        npub: String,
+        /// Public key (npub, hex, nprofile, or NIP-05 name@domain) to unfollow
+        #[arg(required_unless_present = "file")]
+        npub: Option<String>,
+        /// Unfollow everyone in this file, one entry per line as for `follow --file`
+        #[arg(long, value_name = "PATH", conflicts_with = "npub")]
+        file: Option<PathBuf>,
+        /// Publish even if the new list drops more of the previous one than
+        /// `contact_drop_limit` allows
+        #[arg(long)]
+        force: bool,
+        /// With --file: publish without asking
+        #[arg(short = 'y', long)]
+        yes: bool,
     },
 
     /// List users you're following
     Following {
-        /// Public key (npub, hex, or nprofile) to look up; defaults to your own
+        /// Public key (npub, hex, nprofile, or NIP-05 name@domain) to look up; defaults to your own
         npub: Option<String>,
     },
 
     /// List followers
     Followers {
-        /// Public key (npub, hex, or nprofile) to look up; defaults to your own
+        /// Public key (npub, hex, nprofile, or NIP-05 name@domain) to look up; defaults to your own
         npub: Option<String>,
     },
 
     /// React to a note (kind:7)
     React {
-        /// Note ID (note1... or hex)
+        /// Note ID (note1..., nevent1... or hex)
         note_id: String,
-        /// Reaction emoji (default: ⚡)
+        /// Reaction emoji (default: ⚡), or :shortcode: for a custom emoji
+        /// from your emoji list (NIP-30)
         #[arg(default_value = "\u{26A1}")]
         emoji: String,
     },
 
+    /// Remove your reaction from a note (kind:5 deletion of your kind:7)
+    Unreact {
+        /// Note ID (note1..., nevent1... or hex)
+        note_id: String,
+    },
+
+    /// Custom emoji (NIP-30) from your emoji list and sets
+    Emoji {
+        #[command(subcommand)]
+        action: EmojiAction,
+    },
+
+    /// Follow sets (NIP-51 kind:30000): named groups for `timeline --list`
+    List {
+        #[command(subcommand)]
+        action: ListAction,
+    },
+
+    /// Mute people, hashtags, words or threads (NIP-51 kind:10000); applied
+    /// by timeline, search, notifications, thread and watch
+    Mute {
+        #[command(subcommand)]
+        action: MuteAction,
+    },
+
     /// Direct messages (NIP-44/NIP-17)
     Dm {
         #[command(subcommand)]
@@ -125,7 +234,7 @@ enum Commands {
 
     /// Send a zap (NIP-57)
     Zap {
-        /// Target npub or note ID
+        /// Target npub (or hex, nprofile, NIP-05 name@domain)
         target: String,
         /// Amount in satoshis
         amount: u64,
@@ -169,7 +278,7 @@ enum Commands {
         /// Discord webhook URL (required unless --json is used)
         #[arg(long)]
         webhook: Option<String>,
-        /// Target npub to watch (defaults to your own)
+        /// Target npub or NIP-05 name@domain to watch (defaults to your own)
         #[arg(long)]
         npub: Option<String>,
         /// NIP-28 channel ID to watch (hex)
@@ -187,7 +296,7 @@ enum Commands {
         /// Disable mention-only mode (receive all events of watched kinds)
         #[arg(long, conflicts_with = "mention_only")]
         no_mention_only: bool,
-        /// Only receive events from these authors, npub or hex (can be specified multiple times)
+        /// Only receive events from these authors: npub, hex or NIP-05 (repeatable)
         #[arg(long = "author")]
         authors: Vec<String>,
         /// How to combine the mention/keyword/author conditions: "any" keeps events matching
@@ -210,9 +319,18 @@ enum Commands {
     },
 
     /// Post a custom kind Nostr event
+    // --batch and its knobs conflict with the single-event flags as a group: a
+    // knob's `requires = "batch"` alone is not checked once --batch would
+    // conflict with a flag that is present.
+    #[command(group(
+        clap::ArgGroup::new("batch_mode")
+            .args(["batch", "concurrency", "rate", "resume"])
+            .multiple(true)
+            .conflicts_with_all(["kind", "tag", "content", "file"])
+    ))]
     Event {
         /// Event kind number
-        #[arg(short, long, required_unless_present = "file")]
+        #[arg(short, long, required_unless_present_any = ["file", "batch"])]
         kind: Option<u16>,
         /// Tags in "key,value" format (repeatable)
         #[arg(short, long)]
@@ -226,18 +344,92 @@ enum Commands {
         /// --kind/--tag/--content
         #[arg(short = 'f', long, conflicts_with_all = ["kind", "tag", "content"])]
         file: Option<PathBuf>,
+        /// Publish many events from a JSONL file (`-` for stdin), one unsigned
+        /// event per line in the --file format. Every line is validated before
+        /// anything is published; one JSONL result per event is written to
+        /// stdout (or --out)
+        #[arg(long, value_name = "FILE")]
+        batch: Option<PathBuf>,
+        /// With --batch: events in flight at once (default 1)
+        #[arg(long, requires = "batch", value_parser = clap::value_parser!(u32).range(1..))]
+        concurrency: Option<u32>,
+        /// With --batch: at most this many events started per second
+        #[arg(long, requires = "batch")]
+        rate: Option<f64>,
+        /// With --batch: results of an earlier run; events it records as
+        /// published are skipped (and their results repeated)
+        #[arg(long, value_name = "RESULTS", requires = "batch")]
+        resume: Option<PathBuf>,
+        #[command(flatten)]
+        options: PostOptions,
     },
 
-    /// Decode a Nostr bech32 entity (npub, nsec, note, nprofile, nevent, naddr)
+    /// Decode a Nostr bech32 entity (npub, nsec, note, nprofile, nevent, naddr,
+    /// ncryptsec, lnurl), bare or as a nostr: URI
     Decode {
         /// The bech32-encoded entity to decode
         entity: String,
     },
 
-    /// Get an event by ID, note1, or nevent1
+    /// Build a Nostr bech32 entity (npub, note, nprofile, nevent, naddr)
+    Encode {
+        /// Entity to build
+        #[arg(value_enum)]
+        entity_type: commands::encode::EntityType,
+        /// Public key (hex/npub) or event id (hex/note/nevent) to encode; for
+        /// naddr, optionally kind:pubkey:identifier instead of the flags
+        value: Option<String>,
+        /// Relay hint (repeatable; nprofile, nevent, naddr)
+        #[arg(long)]
+        relay: Vec<String>,
+        /// Author public key (nevent, naddr)
+        #[arg(long)]
+        author: Option<String>,
+        /// Event kind (nevent, naddr)
+        #[arg(long)]
+        kind: Option<u16>,
+        /// d tag identifier (naddr)
+        #[arg(long)]
+        identifier: Option<String>,
+    },
+
+    /// Check the id hash and Schnorr signature of signed events
+    Verify {
+        /// JSON event, JSON array of events, or JSON Lines file ("-" reads stdin)
+        source: PathBuf,
+    },
+
+    /// Check signed or unsigned events against NIP rules (NIP-10 markers, kind:3
+    /// tags, d tags, NIP-40 expiration, required tags per kind)
+    Lint {
+        /// JSON event, JSON array of events, or JSON Lines file ("-" reads stdin)
+        source: PathBuf,
+    },
+
+    /// Get an event with its reactions, replies, reposts, quotes and zaps
     Get {
-        /// Event ID, note1, or nevent1 bech32 string
+        /// Event ID, note1, nevent1, or naddr1 (the newest version of that address)
         event_id: String,
+        /// Print only the signed event, exactly as received, as JSON
+        #[arg(long)]
+        raw: bool,
+    },
+
+    /// Show the whole conversation a note belongs to, as an indented reply tree
+    Thread {
+        /// Any note in the thread: note1, nevent1, or hex event ID
+        note: String,
+    },
+
+    /// Mentions, replies, quotes, reactions, reposts and zaps since you last looked
+    Notifications {
+        /// Look back this far instead of to the last read (2h, 3d, a date such
+        /// as 2024-05-01, or a Unix timestamp)
+        #[arg(long)]
+        since: Option<String>,
+        /// Leave the read marker where it is, so the next run shows these again
+        #[arg(long)]
+        no_mark: bool,
     },
 
     /// Search for a vanity npub with a given prefix (omit for an immediate random key)
@@ -261,39 +453,192 @@ enum Commands {
 enum ProfileAction {
     /// Show a Nostr profile
     Show {
-        /// Public key (npub or hex) to look up; defaults to your own
+        /// Public key (npub, hex, nprofile, or NIP-05 name@domain) to look up; defaults to your own
         #[arg(short = 'p', long)]
         pubkey: Option<String>,
     },
     /// Set your profile metadata (kind:0)
-    Set {
-        /// Name (username)
-        #[arg(long)]
-        name: Option<String>,
-        /// Display name
-        #[arg(long)]
-        display_name: Option<String>,
-        /// About / bio
-        #[arg(long)]
-        about: Option<String>,
-        /// Profile picture URL
+    Set(Box<ProfileSetArgs>),
+    /// List every version of your profile seen or published, with what changed
+    History,
+    /// Republish an earlier version of your profile
+    Restore {
+        /// Version number from `profile history` (e.g. 3 or v3), or its event id
+        version: String,
+        /// Publish without asking for confirmation
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+}
+
+/// `profile set`. Boxed in [`ProfileAction`], whose other variants are small.
+#[derive(clap::Args)]
+struct ProfileSetArgs {
+    /// Name (username)
+    #[arg(long)]
+    name: Option<String>,
+    /// Display name
+    #[arg(long)]
+    display_name: Option<String>,
+    /// About / bio
+    #[arg(long)]
+    about: Option<String>,
+    /// Profile picture URL
+    #[arg(long)]
+    picture: Option<String>,
+    /// Lightning address (lud16)
+    #[arg(long)]
+    lud16: Option<String>,
+    /// LNURL pay URL (lud06)
+    #[arg(long)]
+    lud06: Option<String>,
+    /// NIP-05 identifier
+    #[arg(long)]
+    nip05: Option<String>,
+    /// Banner image URL
+    #[arg(long)]
+    banner: Option<String>,
+    /// Website URL
+    #[arg(long)]
+    website: Option<String>,
+    /// Set any kind:0 field, e.g. `bot=true` or `pronouns=they/them`; the value
+    /// is JSON when it parses as JSON, a string otherwise (repeatable)
+    #[arg(long = "field", value_name = "KEY=VALUE")]
+    fields: Vec<String>,
+    /// Remove a field from the profile (repeatable)
+    #[arg(long, value_name = "FIELD")]
+    unset: Vec<String>,
+    /// Start from this JSON object instead of the current profile (`-` for
+    /// stdin); the other options apply on top of it
+    #[arg(long, value_name = "PATH")]
+    from_file: Option<PathBuf>,
+    /// Publish even though no current profile was found (a new account)
+    #[arg(long)]
+    create: bool,
+    /// Publish without asking for confirmation
+    #[arg(short = 'y', long)]
+    yes: bool,
+}
+
+#[derive(Subcommand)]
+enum FollowAction {
+    /// List every version of your follow list seen or published
+    History,
+    /// Show who was added and dropped between two versions (default: the last change)
+    Diff {
+        /// Older version (number, e.g. 3 or v3, or event id); defaults to the one before `to`
+        from: Option<String>,
+        /// Newer version; defaults to the latest
+        to: Option<String>,
+    },
+    /// Republish an earlier version of your follow list
+    Restore {
+        /// Version number from `follow history` (e.g. 3 or v3), or its event id
+        version: String,
+        /// Publish without asking for confirmation
+        #[arg(short = 'y', long)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum EmojiAction {
+    /// List the emoji in your kind:10030 list and kind:30030 sets
+    List,
+}
+
+#[derive(Subcommand)]
+enum ListAction {
+    /// Create an empty follow set
+    Create {
+        /// The list's name (its `d` tag)
+        name: String,
+        /// A human-readable title
         #[arg(long)]
-        picture: Option<String>,
-        /// Lightning address (lud16)
+        title: Option<String>,
+    },
+    /// Add people to a follow set
+    Add {
+        /// The list's name
+        name: String,
+        /// Public keys (npub, hex, nprofile, or NIP-05 name@domain)
+        #[arg(required = true)]
+        pubkeys: Vec<String>,
+        /// Keep them in the list's encrypted part, visible only to you
         #[arg(long)]
-        lud16: Option<String>,
-        /// LNURL pay URL (lud06)
+        private: bool,
+    },
+    /// Remove people from a follow set (public or private)
+    Remove {
+        /// The list's name
+        name: String,
+        /// Public keys (npub, hex, nprofile, or NIP-05 name@domain)
+        #[arg(required = true)]
+        pubkeys: Vec<String>,
+    },
+    /// Show your follow sets, or the members of one
+    Show {
+        /// The list's name; all lists when omitted
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum MuteAction {
+    /// Add to your mute list
+    Add {
+        /// What the values are
+        #[arg(value_enum)]
+        target: commands::mute::MuteTarget,
+        /// Pubkeys, hashtags, words or note IDs
+        #[arg(required = true)]
+        values: Vec<String>,
+        /// Keep them in the list's encrypted part, visible only to you
         #[arg(long)]
-        lud06: Option<String>,
-        /// NIP-05 identifier
+        private: bool,
+    },
+    /// Remove from your mute list (public or private)
+    Remove {
+        /// What the values are
+        #[arg(value_enum)]
+        target: commands::mute::MuteTarget,
+        /// Pubkeys, hashtags, words or note IDs
+        #[arg(required = true)]
+        values: Vec<String>,
+    },
+    /// Show everything you have muted
+    List,
+}
+
+#[derive(Subcommand)]
+enum ArticleAction {
+    /// Publish a Markdown file as a kind:30023 article. YAML (`---`) or TOML (`+++`)
+    /// frontmatter may set title, summary, image, tags, published_at and slug; the
+    /// slug (default: the file name) is the `d` tag, so republishing the same file
+    /// updates the article in place
+    Publish {
+        /// Path to the Markdown file
+        file: PathBuf,
+        /// Publish as a draft (kind:30024) instead
         #[arg(long)]
-        nip05: Option<String>,
-        /// Banner image URL
+        draft: bool,
+    },
+    /// List articles (yours by default)
+    List {
+        /// Author (npub, hex, nprofile, or NIP-05 name@domain); defaults to your own
         #[arg(long)]
-        banner: Option<String>,
-        /// Website URL
+        author: Option<String>,
+        /// Maximum number of articles
+        #[arg(short, long, default_value_t = 20)]
+        limit: usize,
+        /// List drafts (kind:30024) instead of published articles
         #[arg(long)]
-        website: Option<String>,
+        drafts: bool,
+    },
+    /// Fetch an article by naddr and render its Markdown in the terminal
+    Get {
+        /// naddr1... of the article
+        naddr: String,
     },
 }
 
@@ -301,7 +646,7 @@ enum ProfileAction {
 enum DmAction {
     /// Send a direct message
     Send {
-        /// Recipient npub or hex pubkey
+        /// Recipient npub, hex pubkey, or NIP-05 name@domain
         npub: String,
         /// Message to send
         message: String,
@@ -311,7 +656,7 @@ enum DmAction {
     },
     /// Read received direct messages
     Read {
-        /// Filter by sender npub (optional)
+        /// Filter by sender npub or NIP-05 name@domain (optional)
         npub: Option<String>,
     },
 }
@@ -366,6 +711,8 @@ enum CacheAction {
     Clear,
     /// Show cache statistics
     Stats,
+    /// Rebuild the full-text search index from the cached events
+    Reindex,
 }
 
 #[derive(Subcommand)]
@@ -385,7 +732,8 @@ enum RelayAction {
 }
 
 /// The commands that can produce an `--out-format json` document.
-const JSON_OUT_COMMANDS: &str = "following, followers, timeline, search";
+const JSON_OUT_COMMANDS: &str = "following, followers, timeline, search, thread, notifications, \
+     get, decode, encode, verify, lint";
 
 impl Commands {
     /// Whether this command writes a JSON body.
@@ -400,6 +748,13 @@ impl Commands {
                 | Commands::Followers { .. }
                 | Commands::Timeline { .. }
                 | Commands::Search { .. }
+                | Commands::Thread { .. }
+                | Commands::Notifications { .. }
+                | Commands::Get { .. }
+                | Commands::Decode { .. }
+                | Commands::Encode { .. }
+                | Commands::Verify { .. }
+                | Commands::Lint { .. }
         )
     }
 }
@@ -427,6 +782,7 @@ async fn main() -> anyhow::Result<()> {
     }
 
     output::configure(cli.out, cli.out_format.unwrap_or_default());
+    nostaro::client::set_dry_run(cli.dry_run);
 
     match dispatch(cli.command).await {
         Ok(()) => output::finish(),
@@ -442,58 +798,202 @@ async fn dispatch(command: Commands) -> anyhow::Result<()> {
     match command {
         Commands::Init => commands::init::run().await?,
         Commands::Pubkey => commands::pubkey::run().await?,
-        Commands::Post { message, quote } => {
-            commands::post::run(&message, quote.as_deref()).await?
+        Commands::Post {
+            message,
+            quote,
+            options,
+        } => commands::post::run(&message, quote.as_deref(), &options).await?,
+        Commands::Reply {
+            note_id,
+            message,
+            options,
+        } => commands::reply::run(&note_id, &message, &options).await?,
+        Commands::Repost { note_id, comment } => {
+            commands::repost::run(&note_id, comment.as_deref()).await?
         }
-        Commands::Reply { note_id, message } => commands::reply::run(&note_id, &message).await?,
-        Commands::Repost { note_id } => commands::repost::run(&note_id).await?,
+        Commands::Article { action } => match action {
+            ArticleAction::Publish { file, draft } => {
+                commands::article::publish(&file, draft).await?
+            }
+            ArticleAction::List {
+                author,
+                limit,
+                drafts,
+            } => commands::article::list(author.as_deref(), limit, drafts).await?,
+            ArticleAction::Get { naddr } => commands::article::get(&naddr).await?,
+        },
         Commands::Timeline {
             limit,
             with_reactions,
             global,
+            follow,
+            list,
+            authors_file,
+            tags,
+            relay,
         } => {
-            // The flag is a bool on the command line; past this point it is a
-            // scope, so it can no longer be confused with `with_reactions`.
-            let scope = if global {
+            // The flags are bools on the command line; past this point they are
+            // a scope and a mode, so they can no longer be confused with
+            // `with_reactions`.
+            let hashtags: Vec<String> = tags
+                .iter()
+                .map(|tag| tag.trim().trim_start_matches('#').to_lowercase())
+                .filter(|tag| !tag.is_empty())
+                .collect();
+            let scope = if let Some(name) = list {
+                commands::timeline::TimelineScope::List(name)
+            } else if let Some(path) = authors_file {
+                commands::timeline::TimelineScope::AuthorsFile(path)
+            } else if let Some(url) = relay {
+                commands::timeline::TimelineScope::Relay {
+                    url,
+                    hashtags,
+                    global,
+                }
+            } else if !hashtags.is_empty() {
+                commands::timeline::TimelineScope::Tags { hashtags, global }
+            } else if global {
                 commands::timeline::TimelineScope::Global
             } else {
                 commands::timeline::TimelineScope::Following
             };
-            commands::timeline::run(limit, with_reactions, scope).await?
+            let mode = if follow {
+                commands::timeline::TimelineMode::Follow
+            } else {
+                commands::timeline::TimelineMode::Once
+            };
+            commands::timeline::run(limit, with_reactions, scope, mode).await?
         }
-        Commands::Search { query, limit } => commands::search::run(&query, limit).await?,
+        Commands::Search {
+            query,
+            limit,
+            local,
+            filters,
+        } => commands::search::run(query.as_deref(), limit, local, &filters).await?,
         Commands::Profile { action } => match action {
             ProfileAction::Show { pubkey } => commands::profile::show(pubkey.as_deref()).await?,
-            ProfileAction::Set {
-                name,
-                display_name,
-                about,
-                picture,
-                lud16,
-                lud06,
-                nip05,
-                banner,
-                website,
-            } => {
-                commands::profile::set(
-                    name.as_deref(),
-                    display_name.as_deref(),
-                    about.as_deref(),
-                    picture.as_deref(),
-                    lud16.as_deref(),
-                    lud06.as_deref(),
-                    nip05.as_deref(),
-                    banner.as_deref(),
-                    website.as_deref(),
-                )
-                .await?
+            ProfileAction::Set(args) => {
+                let ProfileSetArgs {
+                    name,
+                    display_name,
+                    about,
+                    picture,
+                    lud16,
+                    lud06,
+                    nip05,
+                    banner,
+                    website,
+                    fields,
+                    unset,
+                    from_file,
+                    create,
+                    yes,
+                } = *args;
+                let named = [
+                    ("name", name),
+                    ("display_name", display_name),
+                    ("about", about),
+                    ("picture", picture),
+                    ("lud16", lud16),
+                    ("lud06", lud06),
+                    ("nip05", nip05),
+                    ("banner", banner),
+                    ("website", website),
+                ]
+                .into_iter()
+                .filter_map(|(key, value)| value.map(|value| (key, value)))
+                .collect();
+                let changes = commands::profile::ProfileChanges {
+                    named,
+                    fields,
+                    unset,
+                    from_file,
+                };
+                commands::profile::set(&changes, create, yes).await?
             }
+            ProfileAction::History => commands::profile::history().await?,
+            ProfileAction::Restore { version, yes } => {
+                commands::profile::restore(&version, yes).await?
+            }
+        },
+        Commands::Follow {
+            npub,
+            petname,
+            relay,
+            file,
+            copy_from,
+            from_list,
+            force,
+            yes,
+            action,
+        } => match action {
+            Some(FollowAction::History) => commands::follow::history().await?,
+            Some(FollowAction::Diff { from, to }) => {
+                commands::follow::diff(from.as_deref(), to.as_deref()).await?
+            }
+            Some(FollowAction::Restore { version, yes }) => {
+                commands::follow::restore(&version, yes).await?
+            }
+            None => {
+                let source = match (file, copy_from, from_list) {
+                    (Some(path), _, _) => Some(commands::follow::FollowSource::File(path)),
+                    (_, Some(input), _) => Some(commands::follow::FollowSource::CopyFrom(input)),
+                    (_, _, Some(naddr)) => Some(commands::follow::FollowSource::FromList(naddr)),
+                    _ => None,
+                };
+                match source {
+                    Some(source) => commands::follow::follow_many(&source, force, yes).await?,
+                    None => {
+                        commands::follow::follow(
+                            npub.as_deref().unwrap_or_default(),
+                            relay.as_deref(),
+                            petname.as_deref(),
+                            force,
+                        )
+                        .await?
+                    }
+                }
+            }
+        },
+        Commands::Unfollow {
+            npub,
+            file,
+            force,
+            yes,
+        } => match file {
+            Some(path) => commands::follow::unfollow_many(&path, force, yes).await?,
+            None => commands::follow::unfollow(npub.as_deref().unwrap_or_default(), force).await?,
         },
-        Commands::Follow { npub } => commands::follow::follow(&npub).await?,
-        Commands::Unfollow { npub } => commands::follow::unfollow(&npub).await?,
         Commands::Following { npub } => commands::follow::following(npub.as_deref()).await?,
         Commands::Followers { npub } => commands::follow::followers(npub.as_deref()).await?,
         Commands::React { note_id, emoji } => commands::react::run(&note_id, &emoji).await?,
+        Commands::Unreact { note_id } => commands::react::unreact(&note_id).await?,
+        Commands::Emoji { action } => match action {
+            EmojiAction::List => commands::emoji::list().await?,
+        },
+        Commands::List { action } => match action {
+            ListAction::Create { name, title } => {
+                commands::list::create(&name, title.as_deref()).await?
+            }
+            ListAction::Add {
+                name,
+                pubkeys,
+                private,
+            } => commands::list::add(&name, &pubkeys, private).await?,
+            ListAction::Remove { name, pubkeys } => commands::list::remove(&name, &pubkeys).await?,
+            ListAction::Show { name } => commands::list::show(name.as_deref()).await?,
+        },
+        Commands::Mute { action } => match action {
+            MuteAction::Add {
+                target,
+                values,
+                private,
+            } => commands::mute::add(target, &values, private).await?,
+            MuteAction::Remove { target, values } => {
+                commands::mute::remove(target, &values).await?
+            }
+            MuteAction::List => commands::mute::list().await?,
+        },
         Commands::Dm { action } => match action {
             DmAction::Send {
                 npub,
@@ -531,6 +1031,7 @@ async fn dispatch(command: Commands) -> anyhow::Result<()> {
         Commands::Cache { action } => match action {
             CacheAction::Clear => commands::cache::clear().await?,
             CacheAction::Stats => commands::cache::stats().await?,
+            CacheAction::Reindex => commands::cache::reindex().await?,
         },
         Commands::Relay { action } => match action {
             RelayAction::Add { url } => commands::relay::add(&url).await?,
@@ -542,7 +1043,22 @@ async fn dispatch(command: Commands) -> anyhow::Result<()> {
             tag,
             content,
             file,
-        } => commands::event::run(kind, tag, content, file.as_deref()).await?,
+            batch,
+            concurrency,
+            rate,
+            resume,
+            options,
+        } => match batch {
+            Some(batch) => {
+                let batch_options = commands::event::BatchOptions {
+                    concurrency: concurrency.unwrap_or(1) as usize,
+                    rate,
+                    resume,
+                };
+                commands::event::run_batch(&batch, &options, &batch_options).await?
+            }
+            None => commands::event::run(kind, tag, content, file.as_deref(), &options).await?,
+        },
         Commands::Watch {
             webhook,
             npub,
@@ -573,7 +1089,29 @@ async fn dispatch(command: Commands) -> anyhow::Result<()> {
             .await?
         }
         Commands::Decode { entity } => commands::decode::run(&entity)?,
-        Commands::Get { event_id } => commands::get::run(&event_id).await?,
+        Commands::Encode {
+            entity_type,
+            value,
+            relay,
+            author,
+            kind,
+            identifier,
+        } => {
+            let options = commands::encode::EncodeOptions {
+                relays: relay,
+                author,
+                kind,
+                identifier,
+            };
+            commands::encode::run(entity_type, value.as_deref(), &options)?
+        }
+        Commands::Verify { source } => commands::verify::run(&source)?,
+        Commands::Lint { source } => commands::verify::lint(&source)?,
+        Commands::Get { event_id, raw } => commands::get::run(&event_id, raw).await?,
+        Commands::Thread { note } => commands::thread::run(&note).await?,
+        Commands::Notifications { since, no_mark } => {
+            commands::notifications::run(since.as_deref(), no_mark).await?
+        }
         Commands::Vanity {
             prefix,
             prefix_flag,
@@ -710,6 +1248,7 @@ mod tests {
                 tag,
                 content,
                 file,
+                ..
             } => {
                 // --kind is not required once --file is given.
                 assert_eq!(kind, None);
@@ -741,6 +1280,7 @@ mod tests {
                 tag,
                 content,
                 file,
+                ..
             } => {
                 assert_eq!(kind, Some(1));
                 assert_eq!(tag, vec!["t,nostr".to_string()]);
@@ -796,7 +1336,7 @@ mod tests {
     #[test]
     fn test_json_out_is_gated_to_the_commands_that_produce_it() {
         use clap::Parser;
-        let supported: [&[&str]; 5] = [
+        let supported: [&[&str]; 12] = [
             &["nostaro", "following"],
             &["nostaro", "followers"],
             &["nostaro", "timeline"],
@@ -805,6 +1345,13 @@ mod tests {
             // gate already covers it — this pins that it stays covered.
             &["nostaro", "timeline", "--global"],
             &["nostaro", "search", "query"],
+            &["nostaro", "thread", "note1abc"],
+            &["nostaro", "notifications"],
+            &["nostaro", "get", "note1abc"],
+            &["nostaro", "decode", "npub1abc"],
+            &["nostaro", "encode", "npub", "abc"],
+            &["nostaro", "verify", "-"],
+            &["nostaro", "lint", "-"],
         ];
         for args in supported {
             let cli = Cli::try_parse_from(args.iter().copied()).unwrap();
@@ -836,13 +1383,340 @@ mod tests {
                 limit,
                 with_reactions,
                 global,
+                follow,
+                list,
+                authors_file,
+                tags,
+                relay,
             } => {
                 assert_eq!(limit, 20);
+                assert!(list.is_none() && authors_file.is_none());
+                assert!(tags.is_empty() && relay.is_none());
                 assert!(!with_reactions);
                 assert!(!global, "timeline defaults to your follow set");
+                assert!(!follow, "timeline exits after one page by default");
+            }
+            _ => panic!("wrong command"),
+        }
+    }
+
+    #[test]
+    fn test_timeline_follow_combines_with_either_scope() {
+        use clap::Parser;
+        for args in [
+            vec!["nostaro", "timeline", "--follow"],
+            vec!["nostaro", "timeline", "-f", "--global", "--with-reactions"],
+        ] {
+            let cli = Cli::try_parse_from(args.iter().copied()).unwrap();
+            match cli.command {
+                Commands::Timeline { follow, .. } => assert!(follow, "{:?}", args),
+                _ => panic!("wrong command"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_timeline_list_and_authors_file_exclude_global_and_each_other() {
+        use clap::Parser;
+        let cli = Cli::try_parse_from(["nostaro", "timeline", "--list", "core", "-f"]).unwrap();
+        match cli.command {
+            Commands::Timeline { list, follow, .. } => {
+                assert_eq!(list.as_deref(), Some("core"));
+                assert!(follow);
+            }
+            _ => panic!("wrong command"),
+        }
+        for args in [
+            vec!["nostaro", "timeline", "--list", "core", "--global"],
+            vec!["nostaro", "timeline", "--authors-file", "a.txt", "--global"],
+            vec![
+                "nostaro",
+                "timeline",
+                "--list",
+                "core",
+                "--authors-file",
+                "a.txt",
+            ],
+        ] {
+            assert!(
+                Cli::try_parse_from(args.iter().copied()).is_err(),
+                "{:?}",
+                args
+            );
+        }
+    }
+
+    #[test]
+    fn test_timeline_tag_and_relay_combine_with_global_and_reactions() {
+        use clap::Parser;
+        let cli = Cli::try_parse_from([
+            "nostaro",
+            "timeline",
+            "--tag",
+            "#Nostr",
+            "--tag",
+            "zap",
+            "--relay",
+            "wss://relay.example.com",
+            "--global",
+            "--with-reactions",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Timeline {
+                tags,
+                relay,
+                global,
+                with_reactions,
+                ..
+            } => {
+                assert_eq!(tags, vec!["#Nostr", "zap"]);
+                assert_eq!(relay.as_deref(), Some("wss://relay.example.com"));
+                assert!(global && with_reactions);
             }
             _ => panic!("wrong command"),
         }
+        assert!(
+            Cli::try_parse_from(["nostaro", "timeline", "--tag", "a", "--list", "core"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_profile_set_takes_fields_unsets_and_a_file() {
+        use clap::Parser;
+        let cli = Cli::try_parse_from([
+            "nostaro",
+            "profile",
+            "set",
+            "--about",
+            "hi",
+            "--field",
+            "bot=true",
+            "--field",
+            "pronouns=it/its",
+            "--unset",
+            "lud06",
+            "--from-file",
+            "profile.json",
+            "-y",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Profile {
+                action: ProfileAction::Set(args),
+            } => {
+                assert_eq!(args.about.as_deref(), Some("hi"));
+                assert_eq!(args.fields, vec!["bot=true", "pronouns=it/its"]);
+                assert_eq!(args.unset, vec!["lud06"]);
+                assert_eq!(args.from_file, Some(PathBuf::from("profile.json")));
+                assert!(args.yes && !args.create);
+            }
+            _ => panic!("wrong command"),
+        }
+    }
+
+    #[test]
+    fn test_follow_takes_a_pubkey_or_a_history_action() {
+        use clap::Parser;
+        let cli = Cli::try_parse_from([
+            "nostaro",
+            "follow",
+            "npub1a",
+            "--petname",
+            "alice",
+            "--relay",
+            "wss://relay.example",
+            "--force",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Follow {
+                npub,
+                petname,
+                relay,
+                force,
+                action,
+                ..
+            } => {
+                assert_eq!(npub.as_deref(), Some("npub1a"));
+                assert_eq!(petname.as_deref(), Some("alice"));
+                assert_eq!(relay.as_deref(), Some("wss://relay.example"));
+                assert!(force);
+                assert!(action.is_none());
+            }
+            _ => panic!("wrong command"),
+        }
+
+        let cli = Cli::try_parse_from(["nostaro", "follow", "diff", "v2"]).unwrap();
+        match cli.command {
+            Commands::Follow {
+                npub: None,
+                action: Some(FollowAction::Diff { from, to }),
+                ..
+            } => {
+                assert_eq!(from.as_deref(), Some("v2"));
+                assert!(to.is_none());
+            }
+            _ => panic!("wrong command"),
+        }
+
+        let cli = Cli::try_parse_from(["nostaro", "follow", "restore", "3", "-y"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Follow {
+                action: Some(FollowAction::Restore { yes: true, .. }),
+                ..
+            }
+        ));
+        assert!(Cli::try_parse_from(["nostaro", "follow"]).is_err());
+        assert!(Cli::try_parse_from(["nostaro", "unfollow", "npub1a", "--force"]).is_ok());
+    }
+
+    #[test]
+    fn test_follow_and_unfollow_take_bulk_sources() {
+        use clap::Parser;
+        let cli = Cli::try_parse_from(["nostaro", "follow", "--file", "bots.txt", "-y"]).unwrap();
+        match cli.command {
+            Commands::Follow {
+                npub: None,
+                file,
+                yes,
+                ..
+            } => {
+                assert_eq!(file, Some(PathBuf::from("bots.txt")));
+                assert!(yes);
+            }
+            _ => panic!("wrong command"),
+        }
+        assert!(Cli::try_parse_from(["nostaro", "follow", "--copy-from", "npub1a"]).is_ok());
+        assert!(Cli::try_parse_from(["nostaro", "follow", "--from-list", "naddr1a"]).is_ok());
+
+        // One source at a time, and petnames only go with a single follow.
+        for args in [
+            vec!["nostaro", "follow", "npub1a", "--file", "bots.txt"],
+            vec![
+                "nostaro",
+                "follow",
+                "--copy-from",
+                "npub1a",
+                "--from-list",
+                "naddr1a",
+            ],
+            vec![
+                "nostaro",
+                "follow",
+                "--file",
+                "bots.txt",
+                "--petname",
+                "bots",
+            ],
+            vec![
+                "nostaro",
+                "follow",
+                "--copy-from",
+                "npub1a",
+                "--relay",
+                "wss://relay.example",
+            ],
+            vec!["nostaro", "unfollow", "npub1a", "--file", "bots.txt"],
+            vec!["nostaro", "unfollow"],
+        ] {
+            assert!(Cli::try_parse_from(&args).is_err(), "{:?}", args);
+        }
+
+        let cli = Cli::try_parse_from(["nostaro", "unfollow", "--file", "-", "--yes"]).unwrap();
+        match cli.command {
+            Commands::Unfollow {
+                npub: None,
+                file,
+                yes,
+                ..
+            } => {
+                assert_eq!(file, Some(PathBuf::from("-")));
+                assert!(yes);
+            }
+            _ => panic!("wrong command"),
+        }
+    }
+
+    #[test]
+    fn test_profile_history_and_restore() {
+        use clap::Parser;
+        let cli = Cli::try_parse_from(["nostaro", "profile", "history"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Profile {
+                action: ProfileAction::History
+            }
+        ));
+
+        let cli = Cli::try_parse_from(["nostaro", "profile", "restore", "v2", "--yes"]).unwrap();
+        match cli.command {
+            Commands::Profile {
+                action: ProfileAction::Restore { version, yes },
+            } => {
+                assert_eq!(version, "v2");
+                assert!(yes);
+            }
+            _ => panic!("wrong command"),
+        }
+        assert!(Cli::try_parse_from(["nostaro", "profile", "restore"]).is_err());
+    }
+
+    #[test]
+    fn test_list_add_takes_pubkeys_and_private() {
+        use clap::Parser;
+        let cli = Cli::try_parse_from([
+            "nostaro",
+            "list",
+            "add",
+            "core",
+            "npub1a",
+            "npub1b",
+            "--private",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::List {
+                action:
+                    ListAction::Add {
+                        name,
+                        pubkeys,
+                        private,
+                    },
+            } => {
+                assert_eq!(name, "core");
+                assert_eq!(pubkeys.len(), 2);
+                assert!(private);
+            }
+            _ => panic!("wrong command"),
+        }
+        assert!(Cli::try_parse_from(["nostaro", "list", "add", "core"]).is_err());
+    }
+
+    #[test]
+    fn test_mute_add_takes_a_target_type_and_values() {
+        use clap::Parser;
+        let cli =
+            Cli::try_parse_from(["nostaro", "mute", "add", "word", "gm", "wagmi", "--private"])
+                .unwrap();
+        match cli.command {
+            Commands::Mute {
+                action:
+                    MuteAction::Add {
+                        target,
+                        values,
+                        private,
+                    },
+            } => {
+                assert_eq!(target, commands::mute::MuteTarget::Word);
+                assert_eq!(values, vec!["gm", "wagmi"]);
+                assert!(private);
+            }
+            _ => panic!("wrong command"),
+        }
+        assert!(Cli::try_parse_from(["nostaro", "mute", "add", "person", "npub1a"]).is_err());
+        assert!(Cli::try_parse_from(["nostaro", "mute", "remove", "hashtag"]).is_err());
     }
 
     /// `--limit` means the same thing with and without `--global`: no separate
@@ -869,6 +1743,376 @@ mod tests {
         }
     }
 
+    /// post, reply and event take the same option set, from the same struct.
+    #[test]
+    fn test_post_options_are_shared_by_post_reply_and_event() {
+        use clap::Parser;
+        let flags = [
+            "--content-warning",
+            "--expires-in",
+            "2h",
+            "--protected",
+            "--lang",
+            "ja",
+            "--client",
+        ];
+        let expected = PostOptions {
+            content_warning: Some(String::new()),
+            expires_in: Some(std::time::Duration::from_secs(7200)),
+            protected: true,
+            lang: Some("ja".to_string()),
+            client: Some("nostaro".to_string()),
+        };
+
+        let heads: [&[&str]; 3] = [
+            &["nostaro", "post", "hi"],
+            &["nostaro", "reply", "note1abc", "hi"],
+            &["nostaro", "event", "--kind", "1"],
+        ];
+        for head in heads {
+            let mut args = head.to_vec();
+            args.extend_from_slice(&flags);
+            let options = match Cli::try_parse_from(args.iter().copied()).unwrap().command {
+                Commands::Post { options, .. }
+                | Commands::Reply { options, .. }
+                | Commands::Event { options, .. } => options,
+                _ => panic!("wrong command"),
+            };
+            assert_eq!(options, expected, "{:?}", head);
+        }
+    }
+
+    #[test]
+    fn test_post_option_values_are_optional_where_documented() {
+        use clap::Parser;
+        let cli = Cli::try_parse_from([
+            "nostaro",
+            "post",
+            "hi",
+            "--content-warning",
+            "spoilers",
+            "--client",
+            "my-bot",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Post { options, .. } => {
+                assert_eq!(options.content_warning.as_deref(), Some("spoilers"));
+                assert_eq!(options.client.as_deref(), Some("my-bot"));
+            }
+            _ => panic!("wrong command"),
+        }
+
+        assert_eq!(
+            parse_error(&["nostaro", "post", "hi", "--expires-in", "soon"]).kind(),
+            clap::error::ErrorKind::ValueValidation
+        );
+    }
+
+    #[test]
+    fn test_event_batch_stands_alone_and_owns_its_knobs() {
+        use clap::Parser;
+        let cli = Cli::try_parse_from([
+            "nostaro",
+            "event",
+            "--batch",
+            "events.jsonl",
+            "--concurrency",
+            "4",
+            "--rate",
+            "2.5",
+            "--resume",
+            "results.jsonl",
+        ])
+        .unwrap();
+        match cli.command {
+            Commands::Event {
+                batch,
+                concurrency,
+                rate,
+                resume,
+                kind,
+                ..
+            } => {
+                assert_eq!(batch, Some(PathBuf::from("events.jsonl")));
+                assert_eq!(concurrency, Some(4));
+                assert_eq!(rate, Some(2.5));
+                assert_eq!(resume, Some(PathBuf::from("results.jsonl")));
+                assert_eq!(kind, None, "--batch does not need --kind");
+            }
+            _ => panic!("wrong command"),
+        }
+
+        for args in [
+            &["nostaro", "event", "--batch", "-", "--kind", "1"][..],
+            &["nostaro", "event", "--batch", "-", "--file", "e.json"][..],
+            &["nostaro", "event", "--kind", "1", "--concurrency", "2"][..],
+            &["nostaro", "event", "--file", "e.json", "--rate", "1"][..],
+            &[
+                "nostaro", "event", "--kind", "1", "--tag", "t,x", "--resume", "r.jsonl",
+            ][..],
+            &["nostaro", "event", "--batch", "-", "--concurrency", "0"][..],
+        ] {
+            assert!(
+                Cli::try_parse_from(args.iter().copied()).is_err(),
+                "{:?}",
+                args
+            );
+        }
+    }
+
+    #[test]
+    fn test_repost_takes_an_optional_comment() {
+        use clap::Parser;
+        let cli = Cli::try_parse_from(["nostaro", "repost", "naddr1abc"]).unwrap();
+        match cli.command {
+            Commands::Repost { note_id, comment } => {
+                assert_eq!(note_id, "naddr1abc");
+                assert_eq!(comment, None);
+            }
+            _ => panic!("wrong command"),
+        }
+
+        let cli = Cli::try_parse_from(["nostaro", "repost", "note1abc", "so true"]).unwrap();
+        match cli.command {
+            Commands::Repost { comment, .. } => assert_eq!(comment.as_deref(), Some("so true")),
+            _ => panic!("wrong command"),
+        }
+    }
+
+    #[test]
+    fn test_article_publish_takes_a_file_and_an_optional_draft_flag() {
+        use clap::Parser;
+        let cli =
+            Cli::try_parse_from(["nostaro", "article", "publish", "post.md", "--draft"]).unwrap();
+        match cli.command {
+            Commands::Article {
+                action: ArticleAction::Publish { file, draft },
+            } => {
+                assert_eq!(file, PathBuf::from("post.md"));
+                assert!(draft);
+            }
+            _ => panic!("wrong command"),
+        }
+    }
+
+    #[test]
+    fn test_verify_and_lint_take_a_source() {
+        use clap::Parser;
+        match Cli::try_parse_from(["nostaro", "verify", "-"])
+            .unwrap()
+            .command
+        {
+            Commands::Verify { source } => assert_eq!(source, PathBuf::from("-")),
+            _ => panic!("wrong command"),
+        }
+        match Cli::try_parse_from(["nostaro", "lint", "events.jsonl"])
+            .unwrap()
+            .command
+        {
+            Commands::Lint { source } => assert_eq!(source, PathBuf::from("events.jsonl")),
+            _ => panic!("wrong command"),
+        }
+        // JSON Lines come from the global --out-format json, like every other
+        // command with a structured body.
+        assert!(Cli::try_parse_from(["nostaro", "lint", "events.jsonl", "--json"]).is_err());
+    }
+
+    #[test]
+    fn test_encode_takes_a_type_value_and_tlv_flags() {
+        use clap::Parser;
+        use nostaro::commands::encode::EntityType;
+        match Cli::try_parse_from([
+            "nostaro",
+            "encode",
+            "nevent",
+            "note1abc",
+            "--relay",
+            "wss://a.example",
+            "--relay",
+            "wss://b.example",
+            "--kind",
+            "1",
+        ])
+        .unwrap()
+        .command
+        {
+            Commands::Encode {
+                entity_type,
+                value,
+                relay,
+                kind,
+                ..
+            } => {
+                assert_eq!(entity_type, EntityType::Nevent);
+                assert_eq!(value.as_deref(), Some("note1abc"));
+                assert_eq!(relay.len(), 2);
+                assert_eq!(kind, Some(1));
+            }
+            _ => panic!("wrong command"),
+        }
+        match Cli::try_parse_from(["nostaro", "encode", "naddr", "--kind", "30023"])
+            .unwrap()
+            .command
+        {
+            Commands::Encode { value, .. } => assert!(value.is_none()),
+            _ => panic!("wrong command"),
+        }
+        assert!(Cli::try_parse_from(["nostaro", "encode", "nsec", "abc"]).is_err());
+        match Cli::try_parse_from(["nostaro", "decode", "nostr:npub1abc"])
+            .unwrap()
+            .command
+        {
+            Commands::Decode { entity } => assert_eq!(entity, "nostr:npub1abc"),
+            _ => panic!("wrong command"),
+        }
+        // JSON goes through the global --out-format, not a flag of its own.
+        assert!(Cli::try_parse_from(["nostaro", "decode", "npub1abc", "--json"]).is_err());
+    }
+
+    #[test]
+    fn test_search_query_is_optional_and_filters_repeat() {
+        use clap::Parser;
+        match Cli::try_parse_from([
+            "nostaro",
+            "search",
+            "--author",
+            "npub1a",
+            "--author",
+            "npub1b",
+            "--hashtag",
+            "nostr",
+            "--since",
+            "7d",
+            "--relay",
+            "wss://relay.nostr.band",
+        ])
+        .unwrap()
+        .command
+        {
+            Commands::Search { query, filters, .. } => {
+                assert!(query.is_none());
+                assert_eq!(filters.authors, vec!["npub1a", "npub1b"]);
+                assert_eq!(filters.hashtags, vec!["nostr"]);
+                assert_eq!(filters.since.as_deref(), Some("7d"));
+                assert_eq!(filters.relays, vec!["wss://relay.nostr.band"]);
+            }
+            _ => panic!("wrong command"),
+        }
+        match Cli::try_parse_from(["nostaro", "search", "bitcoin", "--kind", "30023"])
+            .unwrap()
+            .command
+        {
+            Commands::Search {
+                query,
+                local,
+                filters,
+                ..
+            } => {
+                assert_eq!(query.as_deref(), Some("bitcoin"));
+                assert!(!local);
+                assert_eq!(filters.kinds, vec![30023]);
+            }
+            _ => panic!("wrong command"),
+        }
+        match Cli::try_parse_from(["nostaro", "search", "bitcoin", "--local"])
+            .unwrap()
+            .command
+        {
+            Commands::Search { local, .. } => assert!(local),
+            _ => panic!("wrong command"),
+        }
+        // The cache has no relays to pick from.
+        assert!(Cli::try_parse_from([
+            "nostaro",
+            "search",
+            "x",
+            "--local",
+            "--relay",
+            "wss://relay.example.com"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_get_takes_any_event_reference_and_an_optional_raw_flag() {
+        use clap::Parser;
+        match Cli::try_parse_from(["nostaro", "get", "naddr1abc", "--raw"])
+            .unwrap()
+            .command
+        {
+            Commands::Get { event_id, raw } => {
+                assert_eq!(event_id, "naddr1abc");
+                assert!(raw);
+            }
+            _ => panic!("wrong command"),
+        }
+        match Cli::try_parse_from(["nostaro", "get", "note1abc"])
+            .unwrap()
+            .command
+        {
+            Commands::Get { raw, .. } => assert!(!raw),
+            _ => panic!("wrong command"),
+        }
+    }
+
+    #[test]
+    fn test_notifications_since_and_no_mark_are_optional() {
+        use clap::Parser;
+        match Cli::try_parse_from(["nostaro", "notifications"])
+            .unwrap()
+            .command
+        {
+            Commands::Notifications { since, no_mark } => {
+                assert_eq!(since, None);
+                assert!(!no_mark);
+            }
+            _ => panic!("wrong command"),
+        }
+        match Cli::try_parse_from(["nostaro", "notifications", "--since", "3d", "--no-mark"])
+            .unwrap()
+            .command
+        {
+            Commands::Notifications { since, no_mark } => {
+                assert_eq!(since.as_deref(), Some("3d"));
+                assert!(no_mark);
+            }
+            _ => panic!("wrong command"),
+        }
+    }
+
+    #[test]
+    fn test_dry_run_is_global_and_off_by_default() {
+        use clap::Parser;
+        let cases: [&[&str]; 6] = [
+            &["nostaro", "--dry-run", "post", "hi"],
+            &["nostaro", "follow", "npub1abc", "--dry-run"],
+            &["nostaro", "dm", "send", "npub1abc", "hi", "--dry-run"],
+            &["nostaro", "zap", "npub1abc", "21", "--dry-run"],
+            &[
+                "nostaro",
+                "channel",
+                "create",
+                "--name",
+                "test",
+                "--dry-run",
+            ],
+            &["nostaro", "event", "--batch", "events.jsonl", "--dry-run"],
+        ];
+        for args in cases {
+            assert!(
+                Cli::try_parse_from(args.iter().copied()).unwrap().dry_run,
+                "{:?}",
+                args
+            );
+        }
+        assert!(
+            !Cli::try_parse_from(["nostaro", "post", "hi"])
+                .unwrap()
+                .dry_run
+        );
+    }
+
     #[test]
     fn test_out_format_requires_out() {
         // --out-format alone would silently do nothing, so it is rejected.
@@ -0,0 +1,471 @@
+//! NIP-05: `name@domain` addresses, vouched for by the domain at
+//! `/.well-known/nostr.json?name=<name>`.
+//!
+//! A kind:0 can claim any address, so a claim is only shown as verified once
+//! the domain's document maps the name to that very pubkey. Results are kept in
+//! the cache with a TTL — a day when the check passed, an hour when it did not —
+//! so a timeline does not hit every author's domain on every run. A domain that
+//! cannot be reached leaves no result at all rather than a failed one: being
+//! offline is not evidence of impersonation.
+//!
+//! The other direction, an address typed in place of a pubkey, goes through
+//! [`resolve`], whose answers are cached the same way.
+
+use anyhow::{bail, Context, Result};
+use nostr_sdk::prelude::*;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::cache::CacheDb;
+
+/// How long a passed check is trusted before the domain is asked again.
+pub const VERIFIED_TTL_SECS: i64 = 24 * 60 * 60;
+/// How long a failed check is remembered; shorter, so a fixed document is
+/// picked up soon.
+pub const FAILED_TTL_SECS: i64 = 60 * 60;
+/// State key prefix for resolved addresses, followed by the address.
+const RESOLVED_KEY_PREFIX: &str = "nip05.resolved.";
+/// Kept short: `watch` verifies authors as their events stream in.
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A parsed `name@domain`. A bare `domain` is the root identifier `_@domain`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Address {
+    pub name: String,
+    pub domain: String,
+}
+
+impl Address {
+    /// Pure — names are case-insensitive in NIP-05, so both parts are
+    /// lowercased.
+    pub fn parse(input: &str) -> Result<Self> {
+        let input = input.trim();
+        let (name, domain) = input.split_once('@').unwrap_or(("_", input));
+        let name = name.to_lowercase();
+        let domain = domain.to_lowercase();
+        let name_ok = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+        let domain_ok = domain.contains('.') || domain.starts_with("localhost");
+        if !name_ok
+            || !domain_ok
+            || domain.contains(['/', '?', '#', '@'])
+            || domain.chars().any(char::is_whitespace)
+        {
+            bail!("Invalid NIP-05 address: {}", input);
+        }
+        Ok(Self { name, domain })
+    }
+
+    /// Where the domain publishes its names. Plain HTTP for a loopback host,
+    /// so a local stand-in can answer; HTTPS for everything else.
+    pub fn url(&self) -> String {
+        let host = self.domain.split(':').next().unwrap_or_default();
+        let scheme = match host {
+            "localhost" | "127.0.0.1" => "http",
+            _ => "https",
+        };
+        format!(
+            "{}://{}/.well-known/nostr.json?name={}",
+            scheme, self.domain, self.name
+        )
+    }
+}
+
+impl std::fmt::Display for Address {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}@{}", self.name, self.domain)
+    }
+}
+
+/// What a domain's document says about one name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub pubkey: PublicKey,
+    /// The optional `relays` hints for that pubkey.
+    pub relays: Vec<String>,
+}
+
+/// Find `name` in a `nostr.json` document.
+///
+/// Pure — `None` when the name is not listed or its value is not a pubkey.
+pub fn entry(document: &serde_json::Value, name: &str) -> Option<Entry> {
+    let names = document.get("names")?.as_object()?;
+    let hex = names
+        .get(name)
+        .or_else(|| {
+            names
+                .iter()
+                .find(|(listed, _)| listed.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value)
+        })?
+        .as_str()?;
+    let pubkey = PublicKey::from_hex(hex).ok()?;
+    let relays = document
+        .get("relays")
+        .and_then(|relays| relays.get(pubkey.to_hex()))
+        .and_then(|relays| relays.as_array())
+        .map(|relays| {
+            relays
+                .iter()
+                .filter_map(|relay| relay.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(Entry { pubkey, relays })
+}
+
+/// Ask the domain about `address`. `Ok(None)` when the domain answered but does
+/// not vouch for the name — an error status, a redirect (which NIP-05 says to
+/// ignore), or a document without it; `Err` when it could not be reached.
+pub async fn lookup(address: &Address) -> Result<Option<Entry>> {
+    let http_client = reqwest::Client::builder()
+        .timeout(LOOKUP_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?;
+    let response = http_client
+        .get(address.url())
+        .header("Accept", "application/json")
+        .send()
+        .await
+        .with_context(|| format!("could not reach {}", address.domain))?;
+    if !response.status().is_success() {
+        return Ok(None);
+    }
+    let document: serde_json::Value = match response.json().await {
+        Ok(document) => document,
+        Err(_) => return Ok(None),
+    };
+    Ok(entry(&document, &address.name))
+}
+
+/// The pubkey (and relay hints) `address` stands for, from the cache while the
+/// last answer is fresh — the same TTL as a passed check — and from the domain
+/// otherwise.
+pub async fn resolve(address: &Address, cache: Option<&CacheDb>) -> Result<Entry> {
+    let key = format!("{}{}", RESOLVED_KEY_PREFIX, address);
+    let cached = cache
+        .and_then(|cache| cache.get_state(&key).ok().flatten())
+        .and_then(|value| serde_json::from_str::<serde_json::Value>(&value).ok())
+        .filter(|value| {
+            let checked_at = value["checked_at"].as_i64().unwrap_or_default();
+            now() - checked_at < VERIFIED_TTL_SECS
+        })
+        .and_then(|value| entry(&value, &address.name));
+    if let Some(entry) = cached {
+        return Ok(entry);
+    }
+
+    let Some(entry) = lookup(address).await? else {
+        bail!(
+            "{} does not list a pubkey for \"{}\" (NIP-05)",
+            address.domain,
+            address.name
+        );
+    };
+    if let Some(cache) = cache {
+        // Stored in the shape of a nostr.json, so `entry` reads it back.
+        let value = serde_json::json!({
+            "names": { address.name.as_str(): entry.pubkey.to_hex() },
+            "relays": { entry.pubkey.to_hex(): entry.relays },
+            "checked_at": now(),
+        });
+        let _ = cache.set_state(&key, &value.to_string());
+    }
+    Ok(entry)
+}
+
+/// The outcome of checking a pubkey's claimed address.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Check {
+    pub address: String,
+    pub verified: bool,
+    #[serde(skip)]
+    pub checked_at: i64,
+}
+
+impl Check {
+    pub fn is_fresh(&self, now: i64) -> bool {
+        let ttl = if self.verified {
+            VERIFIED_TTL_SECS
+        } else {
+            FAILED_TTL_SECS
+        };
+        now - self.checked_at < ttl
+    }
+
+    /// The marker shown next to an author: `✔ bob@example.com`, or the claim
+    /// flagged as not backed by its domain.
+    pub fn label(&self) -> String {
+        if self.verified {
+            format!("✔ {}", self.address)
+        } else {
+            format!("✘ {} (unverified)", self.address)
+        }
+    }
+}
+
+fn now() -> i64 {
+    Timestamp::now().as_u64() as i64
+}
+
+/// A fresh check of the address `pubkey` currently claims, from the cache only.
+/// This is what the renderers read.
+pub fn cached(cache: Option<&CacheDb>, pubkey: &PublicKey) -> Option<Check> {
+    let row = cache?.get_nip05(&pubkey.to_hex()).ok().flatten()?;
+    let check = Check {
+        address: row.address?,
+        verified: row.verified?,
+        checked_at: row.checked_at?,
+    };
+    check.is_fresh(now()).then_some(check)
+}
+
+/// Check `claimed` for `pubkey`, answering from the cache while the last
+/// result is fresh and recording the new one otherwise. `None` when the domain
+/// could not be reached.
+pub async fn verify(cache: Option<&CacheDb>, pubkey: &PublicKey, claimed: &str) -> Option<Check> {
+    let hex = pubkey.to_hex();
+    if let Some(cache) = cache {
+        let _ = cache.store_nip05_claim(&hex, Some(claimed));
+        if let Some(check) = cached(Some(cache), pubkey) {
+            return Some(check);
+        }
+    }
+    let verified = match Address::parse(claimed) {
+        Ok(address) => match lookup(&address).await {
+            Ok(entry) => entry.is_some_and(|entry| entry.pubkey == *pubkey),
+            Err(_) => return None,
+        },
+        Err(_) => false,
+    };
+    let check = Check {
+        address: claimed.to_string(),
+        verified,
+        checked_at: now(),
+    };
+    if let Some(cache) = cache {
+        let _ = cache.store_nip05_check(&hex, &check.address, check.verified, check.checked_at);
+    }
+    Some(check)
+}
+
+/// Re-check, all at once, the cached claims of `pubkeys` that have no fresh
+/// result. Pubkeys whose kind:0 is not in the cache are skipped; fetch the
+/// profiles first.
+pub async fn refresh(cache: &CacheDb, pubkeys: &[PublicKey]) {
+    let now = now();
+    let mut lookups = tokio::task::JoinSet::new();
+    let unique: HashSet<&PublicKey> = pubkeys.iter().collect();
+    for pubkey in unique {
+        let Ok(Some(row)) = cache.get_nip05(&pubkey.to_hex()) else {
+            continue;
+        };
+        let Some(claimed) = row.address else {
+            continue;
+        };
+        if let (Some(verified), Some(checked_at)) = (row.verified, row.checked_at) {
+            let check = Check {
+                address: claimed.clone(),
+                verified,
+                checked_at,
+            };
+            if check.is_fresh(now) {
+                continue;
+            }
+        }
+        let pubkey = *pubkey;
+        lookups.spawn(async move {
+            let verified = match Address::parse(&claimed) {
+                Ok(address) => match lookup(&address).await {
+                    Ok(entry) => Some(entry.is_some_and(|entry| entry.pubkey == pubkey)),
+                    Err(_) => None,
+                },
+                Err(_) => Some(false),
+            };
+            (pubkey, claimed, verified)
+        });
+    }
+    while let Some(joined) = lookups.join_next().await {
+        if let Ok((pubkey, claimed, Some(verified))) = joined {
+            let _ = cache.store_nip05_check(&pubkey.to_hex(), &claimed, verified, now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// A one-request HTTP server on a loopback port standing in for a domain.
+    /// Returns the `domain` to put in an address, and the request line it got.
+    fn stand_in(status: &str, body: String) -> (String, std::thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let domain = format!("127.0.0.1:{}", listener.local_addr().unwrap().port());
+        let status = status.to_string();
+        let handle = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 4096];
+            let read = stream.read(&mut request).unwrap();
+            let request = String::from_utf8_lossy(&request[..read]).to_string();
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+            request.lines().next().unwrap_or_default().to_string()
+        });
+        (domain, handle)
+    }
+
+    fn document(name: &str, pubkey: &PublicKey) -> String {
+        serde_json::json!({
+            "names": { name: pubkey.to_hex() },
+            "relays": { pubkey.to_hex(): ["wss://relay.example.com"] },
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn addresses_parse_with_the_root_name_as_default() {
+        let address = Address::parse("Bob@Example.com").unwrap();
+        assert_eq!(address.to_string(), "bob@example.com");
+        assert_eq!(
+            address.url(),
+            "https://example.com/.well-known/nostr.json?name=bob"
+        );
+        assert_eq!(Address::parse("example.com").unwrap().name, "_");
+        assert_eq!(
+            Address::parse("_@localhost:8080").unwrap().url(),
+            "http://localhost:8080/.well-known/nostr.json?name=_"
+        );
+        for bad in [
+            "bob@",
+            "@example.com",
+            "bob smith@example.com",
+            "bob@a/b.com",
+            "npub1xyz",
+        ] {
+            assert!(Address::parse(bad).is_err(), "{} should not parse", bad);
+        }
+    }
+
+    #[test]
+    fn an_entry_needs_the_name_and_a_valid_pubkey() {
+        let pubkey = Keys::generate().public_key();
+        let doc: serde_json::Value = serde_json::from_str(&document("Bob", &pubkey)).unwrap();
+        let entry = entry(&doc, "bob").unwrap();
+        assert_eq!(entry.pubkey, pubkey);
+        assert_eq!(entry.relays, vec!["wss://relay.example.com"]);
+
+        assert!(super::entry(&doc, "alice").is_none());
+        let broken = serde_json::json!({ "names": { "bob": "not-a-key" } });
+        assert!(super::entry(&broken, "bob").is_none());
+    }
+
+    #[test]
+    fn checks_expire_sooner_when_they_failed() {
+        let check = |verified| Check {
+            address: "bob@example.com".to_string(),
+            verified,
+            checked_at: 0,
+        };
+        assert!(check(true).is_fresh(FAILED_TTL_SECS));
+        assert!(!check(false).is_fresh(FAILED_TTL_SECS));
+        assert!(!check(true).is_fresh(VERIFIED_TTL_SECS));
+        assert_eq!(check(true).label(), "✔ bob@example.com");
+        assert_eq!(check(false).label(), "✘ bob@example.com (unverified)");
+    }
+
+    #[tokio::test]
+    async fn a_matching_document_verifies_and_is_cached() {
+        let keys = Keys::generate();
+        let (domain, server) = stand_in("200 OK", document("bob", &keys.public_key()));
+        let cache = CacheDb::in_memory().unwrap();
+        let claimed = format!("bob@{}", domain);
+
+        let check = verify(Some(&cache), &keys.public_key(), &claimed)
+            .await
+            .unwrap();
+        assert!(check.verified);
+        assert_eq!(
+            server.join().unwrap(),
+            "GET /.well-known/nostr.json?name=bob HTTP/1.1"
+        );
+
+        // The stand-in is gone; the answer now comes from the cache.
+        let again = verify(Some(&cache), &keys.public_key(), &claimed).await;
+        assert_eq!(again, Some(check.clone()));
+        assert_eq!(cached(Some(&cache), &keys.public_key()), Some(check));
+    }
+
+    #[tokio::test]
+    async fn someone_elses_name_does_not_verify() {
+        let impostor = Keys::generate().public_key();
+        let (domain, server) = stand_in("200 OK", document("bob", &Keys::generate().public_key()));
+        let cache = CacheDb::in_memory().unwrap();
+
+        let check = verify(Some(&cache), &impostor, &format!("bob@{}", domain))
+            .await
+            .unwrap();
+        server.join().unwrap();
+        assert!(!check.verified);
+        assert_eq!(cached(Some(&cache), &impostor), Some(check));
+    }
+
+    #[tokio::test]
+    async fn an_address_resolves_to_its_pubkey_and_relays_once() {
+        let keys = Keys::generate();
+        let (domain, server) = stand_in("200 OK", document("_", &keys.public_key()));
+        let cache = CacheDb::in_memory().unwrap();
+        let address = Address::parse(&format!("_@{}", domain)).unwrap();
+
+        let entry = resolve(&address, Some(&cache)).await.unwrap();
+        server.join().unwrap();
+        assert_eq!(entry.pubkey, keys.public_key());
+        assert_eq!(entry.relays, vec!["wss://relay.example.com"]);
+        // Answered from the cache: nothing listens any more.
+        assert_eq!(resolve(&address, Some(&cache)).await.unwrap(), entry);
+
+        let (domain, server) = stand_in("200 OK", document("bob", &keys.public_key()));
+        let unknown = Address::parse(&format!("alice@{}", domain)).unwrap();
+        let error = resolve(&unknown, Some(&cache)).await.unwrap_err();
+        server.join().unwrap();
+        assert!(error.to_string().contains("does not list"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn refresh_checks_cached_claims_and_an_unreachable_domain_leaves_nothing() {
+        let keys = Keys::generate();
+        let (domain, server) = stand_in("404 Not Found", String::new());
+        let cache = CacheDb::in_memory().unwrap();
+        cache
+            .store_nip05_claim(
+                &keys.public_key().to_hex(),
+                Some(&format!("bob@{}", domain)),
+            )
+            .unwrap();
+
+        refresh(&cache, &[keys.public_key()]).await;
+        server.join().unwrap();
+        assert_eq!(
+            cached(Some(&cache), &keys.public_key()).map(|check| check.verified),
+            Some(false)
+        );
+
+        // Nothing listens on the port any more.
+        let offline = Keys::generate().public_key();
+        assert_eq!(
+            verify(Some(&cache), &offline, &format!("bob@{}", domain)).await,
+            None
+        );
+        assert_eq!(cached(Some(&cache), &offline), None);
+    }
+}
@@ -126,6 +126,38 @@ pub fn write_json(value: &serde_json::Value) -> Result<()> {
     Ok(())
 }
 
+/// Declare a JSON Lines body before writing any of it: the `--out` file exists
+/// and counts as written even if no line ever follows, since an empty file is
+/// a valid (empty) JSON Lines stream.
+pub fn open_json_lines() -> Result<()> {
+    let mut sink = sink();
+    if sink.path.is_some() && sink.format == OutFormat::Json {
+        sink.writer()?;
+        sink.json_written = true;
+    }
+    Ok(())
+}
+
+/// Write one compact JSON value as a line of a JSON Lines body, and flush it
+/// so a reader tailing the file sees each line as soon as it is written. For
+/// commands that keep running and append as they go. Only called when
+/// [`is_json`] is true.
+pub fn write_json_line(value: &serde_json::Value) -> Result<()> {
+    let text = serde_json::to_string(value)?;
+    let mut sink = sink();
+    if sink.path.is_none() {
+        println!("{}", text);
+        return Ok(());
+    }
+    let writer = sink.writer()?;
+    writeln!(writer, "{}", text).context("failed to write to the output file")?;
+    writer
+        .flush()
+        .context("failed to write to the output file")?;
+    sink.json_written = true;
+    Ok(())
+}
+
 /// Flush and close the output file, then print the one-line summary on stdout.
 ///
 /// Called once, at the end of a successful run.
@@ -0,0 +1,226 @@
+//! Optional tags shared by every command that writes a note: `post`, `reply`
+//! and `event`.
+//!
+//! The flags are declared once here and flattened into each command, and the
+//! tags are built once here, so the three commands cannot drift apart on what
+//! `--expires-in 2h` or `--content-warning` puts on the wire. The reading side
+//! (is this event expired, does it carry a warning) lives here too, so the
+//! timeline and watch agree with what nostaro itself writes.
+
+use anyhow::{bail, Result};
+use nostr_sdk::prelude::*;
+use std::time::Duration;
+
+use crate::utils::parse_duration;
+
+/// Client name used by a bare `--client`.
+pub const DEFAULT_CLIENT_NAME: &str = "nostaro";
+
+/// The NIP-32 namespace for `--lang`.
+const LANGUAGE_NAMESPACE: &str = "ISO-639-1";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, clap::Args)]
+pub struct PostOptions {
+    /// Mark the note as sensitive (NIP-36), optionally with a reason:
+    /// `--content-warning` or `--content-warning spoilers`
+    #[arg(long, num_args = 0..=1, default_missing_value = "", value_name = "REASON")]
+    pub content_warning: Option<String>,
+
+    /// Ask relays and clients to drop the note after this long (NIP-40),
+    /// e.g. 30m, 2h, 7d
+    #[arg(long, value_parser = parse_duration, value_name = "DURATION")]
+    pub expires_in: Option<Duration>,
+
+    /// Mark the note as protected (NIP-70 `-` tag): relays should only accept
+    /// it from its author
+    #[arg(long)]
+    pub protected: bool,
+
+    /// Label the note's language (NIP-32, two-letter ISO-639-1 code, e.g. en, ja)
+    #[arg(long, value_name = "CODE")]
+    pub lang: Option<String>,
+
+    /// Add a `client` tag naming the app that published the note (default
+    /// name: nostaro). Without the flag no client tag is added
+    #[arg(long, num_args = 0..=1, default_missing_value = DEFAULT_CLIENT_NAME, value_name = "NAME")]
+    pub client: Option<String>,
+}
+
+impl PostOptions {
+    /// The tags these options stand for. `now` anchors `--expires-in`.
+    ///
+    /// Pure — it builds tags and talks to no relay.
+    pub fn tags(&self, now: Timestamp) -> Result<Vec<Tag>> {
+        let mut tags = Vec::new();
+
+        if let Some(reason) = &self.content_warning {
+            let reason = reason.trim();
+            tags.push(if reason.is_empty() {
+                Tag::parse(["content-warning"])?
+            } else {
+                Tag::parse(["content-warning", reason])?
+            });
+        }
+
+        if let Some(expires_in) = self.expires_in {
+            if expires_in.is_zero() {
+                bail!("--expires-in must be longer than zero");
+            }
+            let at = now.as_u64().saturating_add(expires_in.as_secs());
+            tags.push(Tag::expiration(Timestamp::from(at)));
+        }
+
+        if self.protected {
+            tags.push(Tag::parse(["-"])?);
+        }
+
+        if let Some(lang) = &self.lang {
+            let lang = lang.trim().to_ascii_lowercase();
+            if lang.len() != 2 || !lang.bytes().all(|b| b.is_ascii_lowercase()) {
+                bail!(
+                    "--lang takes a two-letter ISO-639-1 code such as en or ja, got \"{}\"",
+                    lang
+                );
+            }
+            tags.push(Tag::parse(["L", LANGUAGE_NAMESPACE])?);
+            tags.push(Tag::parse(["l", lang.as_str(), LANGUAGE_NAMESPACE])?);
+        }
+
+        if let Some(name) = &self.client {
+            let name = name.trim();
+            if name.is_empty() {
+                bail!("--client needs a non-empty name");
+            }
+            tags.push(Tag::parse(["client", name])?);
+        }
+
+        Ok(tags)
+    }
+}
+
+fn first_tag<'a>(event: &'a Event, name: &str) -> Option<&'a [String]> {
+    event
+        .tags
+        .iter()
+        .map(|tag| tag.as_slice())
+        .find(|values| values.first().map(String::as_str) == Some(name))
+}
+
+/// The NIP-40 expiration time, if the event carries a well-formed one.
+pub fn expiration(event: &Event) -> Option<Timestamp> {
+    first_tag(event, "expiration")?
+        .get(1)?
+        .parse::<u64>()
+        .ok()
+        .map(Timestamp::from)
+}
+
+/// Whether the event has expired (NIP-40) as of `now`. Relays are supposed to
+/// drop expired events, but not all do, and a cache may still hold them.
+pub fn is_expired(event: &Event, now: Timestamp) -> bool {
+    expiration(event).is_some_and(|at| at <= now)
+}
+
+/// The NIP-36 content warning: `Some("")` for a warning without a reason,
+/// `None` when the event carries no warning at all.
+pub fn content_warning(event: &Event) -> Option<String> {
+    let values = first_tag(event, "content-warning")?;
+    Some(values.get(1).cloned().unwrap_or_default())
+}
+
+/// The content as it should be shown to a reader: a placeholder in place of
+/// anything behind a content warning. The raw event is still available to
+/// anyone who asks for it (JSON output, `get`).
+pub fn display_content(event: &Event) -> String {
+    match content_warning(event) {
+        Some(reason) if reason.is_empty() => "[content warning]".to_string(),
+        Some(reason) => format!("[content warning: {}]", reason),
+        None => event.content.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag_vecs(options: &PostOptions, now: u64) -> Vec<Vec<String>> {
+        options
+            .tags(Timestamp::from(now))
+            .unwrap()
+            .into_iter()
+            .map(|tag| tag.to_vec())
+            .collect()
+    }
+
+    fn note_with(tags: Vec<Tag>) -> Event {
+        EventBuilder::text_note("secret")
+            .tags(tags)
+            .sign_with_keys(&Keys::generate())
+            .unwrap()
+    }
+
+    #[test]
+    fn no_options_means_no_tags() {
+        assert!(tag_vecs(&PostOptions::default(), 0).is_empty());
+    }
+
+    #[test]
+    fn every_option_becomes_its_tag() {
+        let options = PostOptions {
+            content_warning: Some("spoilers".to_string()),
+            expires_in: Some(Duration::from_secs(3600)),
+            protected: true,
+            lang: Some("JA".to_string()),
+            client: Some(DEFAULT_CLIENT_NAME.to_string()),
+        };
+        assert_eq!(
+            tag_vecs(&options, 1000),
+            vec![
+                vec!["content-warning", "spoilers"],
+                vec!["expiration", "4600"],
+                vec!["-"],
+                vec!["L", "ISO-639-1"],
+                vec!["l", "ja", "ISO-639-1"],
+                vec!["client", "nostaro"],
+            ]
+        );
+    }
+
+    #[test]
+    fn a_content_warning_without_a_reason_is_a_bare_tag() {
+        let options = PostOptions {
+            content_warning: Some(String::new()),
+            ..PostOptions::default()
+        };
+        assert_eq!(tag_vecs(&options, 0), vec![vec!["content-warning"]]);
+    }
+
+    #[test]
+    fn bad_language_codes_are_rejected() {
+        for lang in ["english", "e", "e1"] {
+            let options = PostOptions {
+                lang: Some(lang.to_string()),
+                ..PostOptions::default()
+            };
+            assert!(options.tags(Timestamp::now()).is_err(), "{}", lang);
+        }
+    }
+
+    #[test]
+    fn expiration_is_read_back_and_compared_to_now() {
+        let event = note_with(vec![Tag::expiration(Timestamp::from(100))]);
+        assert_eq!(expiration(&event), Some(Timestamp::from(100)));
+        assert!(!is_expired(&event, Timestamp::from(99)));
+        assert!(is_expired(&event, Timestamp::from(100)));
+        assert!(!is_expired(&note_with(vec![]), Timestamp::from(u64::MAX)));
+    }
+
+    #[test]
+    fn content_behind_a_warning_is_masked_for_display() {
+        let warned = note_with(vec![Tag::parse(["content-warning", "nsfw"]).unwrap()]);
+        assert_eq!(display_content(&warned), "[content warning: nsfw]");
+        let bare = note_with(vec![Tag::parse(["content-warning"]).unwrap()]);
+        assert_eq!(display_content(&bare), "[content warning]");
+        assert_eq!(display_content(&note_with(vec![])), "secret");
+    }
+}
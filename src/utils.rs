@@ -1,7 +1,14 @@
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use nostr_sdk::prelude::*;
+use std::time::Duration;
+
+use crate::cache::CacheDb;
+use crate::nip05;
 
 /// Resolve a pubkey string from npub, hex, or nprofile (NIP-19 TLV) format.
+///
+/// Offline only; commands taking a pubkey from the user go through
+/// [`resolve_pubkey_with_hints`], which also accepts NIP-05 addresses.
 pub fn resolve_pubkey(input: &str) -> Result<PublicKey> {
     // Try npub / hex first
     if let Ok(pk) = PublicKey::parse(input) {
@@ -15,3 +22,310 @@ pub fn resolve_pubkey(input: &str) -> Result<PublicKey> {
 
     bail!("Invalid pubkey, npub, or nprofile: {}", input)
 }
+
+/// Resolve a pubkey the way a user may type it: npub, hex, nprofile, or a
+/// NIP-05 address (`name@domain`, `_@domain`) looked up at the domain. Relay
+/// hints ride along — an nprofile's, or the `relays` the domain lists.
+pub async fn resolve_pubkey_with_hints(input: &str) -> Result<(PublicKey, Vec<String>)> {
+    let input = input.trim();
+    let input = input.strip_prefix("nostr:").unwrap_or(input);
+
+    if let Ok(profile) = Nip19Profile::from_bech32(input) {
+        let relays = profile
+            .relays
+            .iter()
+            .map(|relay| relay.to_string())
+            .collect();
+        return Ok((profile.public_key, relays));
+    }
+    if let Ok(pk) = PublicKey::parse(input) {
+        return Ok((pk, Vec::new()));
+    }
+    if input.contains('@') {
+        let address = nip05::Address::parse(input)?;
+        let cache = CacheDb::open().ok();
+        let entry = nip05::resolve(&address, cache.as_ref())
+            .await
+            .with_context(|| format!("could not resolve {}", address))?;
+        return Ok((entry.pubkey, entry.relays));
+    }
+
+    bail!(
+        "Invalid pubkey, npub, nprofile, or NIP-05 address: {}",
+        input
+    )
+}
+
+/// [`resolve_pubkey_with_hints`] without the hints.
+pub async fn resolve_pubkey_online(input: &str) -> Result<PublicKey> {
+    Ok(resolve_pubkey_with_hints(input).await?.0)
+}
+
+/// Every input through [`resolve_pubkey_online`], in order.
+pub async fn resolve_pubkeys(inputs: &[String]) -> Result<Vec<PublicKey>> {
+    let mut pubkeys = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        pubkeys.push(resolve_pubkey_online(input).await?);
+    }
+    Ok(pubkeys)
+}
+
+/// Parse a reference to an event: hex id, `note1`, or `nevent1` (whose relay
+/// hints are returned alongside), with or without a `nostr:` prefix.
+pub fn parse_event_reference(input: &str) -> Result<(EventId, Vec<String>)> {
+    let input = input.trim();
+    let input = input.strip_prefix("nostr:").unwrap_or(input);
+
+    if input.starts_with("nevent1") {
+        let nip19_event = Nip19Event::from_bech32(input)?;
+        let relays = nip19_event
+            .relays
+            .iter()
+            .map(|relay| relay.to_string())
+            .collect();
+        return Ok((nip19_event.event_id, relays));
+    }
+
+    if let Ok(id) = EventId::parse(input).or_else(|_| EventId::from_bech32(input)) {
+        return Ok((id, Vec::new()));
+    }
+
+    bail!("Invalid event id, note, or nevent: {}", input)
+}
+
+/// What a command was pointed at: a specific event (hex, note1, nevent1) or the
+/// newest version of an addressable one (naddr1). Relay hints from the bech32
+/// entity ride along.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventTarget {
+    Event(EventId, Vec<String>),
+    Address(Coordinate, Vec<String>),
+}
+
+impl EventTarget {
+    pub fn relay_hints(&self) -> &[String] {
+        match self {
+            EventTarget::Event(_, hints) | EventTarget::Address(_, hints) => hints,
+        }
+    }
+}
+
+/// Parse an event reference that may also be an `naddr1` (with or without a
+/// `nostr:` prefix).
+pub fn parse_event_target(input: &str) -> Result<EventTarget> {
+    let trimmed = input.trim();
+    let bare = trimmed.strip_prefix("nostr:").unwrap_or(trimmed);
+    if bare.starts_with("naddr1") {
+        let nip19 = Nip19Coordinate::from_bech32(bare)
+            .with_context(|| format!("not an naddr: {}", input))?;
+        let relays = nip19.relays.iter().map(|r| r.to_string()).collect();
+        return Ok(EventTarget::Address(nip19.coordinate, relays));
+    }
+    let (id, relays) = parse_event_reference(bare)?;
+    Ok(EventTarget::Event(id, relays))
+}
+
+/// Parse a human duration: `90s`, `15m`, `2h`, `3d`, `1w`, or a combination
+/// such as `1h30m`. A bare number is seconds.
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let input = input.trim();
+    if input.is_empty() {
+        bail!("empty duration; expected something like 30m, 2h, 3d or 1w");
+    }
+    if let Ok(secs) = input.parse::<u64>() {
+        return Ok(Duration::from_secs(secs));
+    }
+
+    let mut total: u64 = 0;
+    let mut digits = String::new();
+    for ch in input.chars() {
+        if ch.is_ascii_digit() {
+            digits.push(ch);
+            continue;
+        }
+        let unit = match ch {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => bail!(
+                "invalid duration \"{}\": unknown unit '{}' (use s, m, h, d or w)",
+                input,
+                ch
+            ),
+        };
+        let Ok(amount) = digits.parse::<u64>() else {
+            bail!(
+                "invalid duration \"{}\": '{}' has no number before it",
+                input,
+                ch
+            );
+        };
+        total = total.saturating_add(amount.saturating_mul(unit));
+        digits.clear();
+    }
+    if !digits.is_empty() {
+        bail!(
+            "invalid duration \"{}\": \"{}\" has no unit (use s, m, h, d or w)",
+            input,
+            digits
+        );
+    }
+
+    Ok(Duration::from_secs(total))
+}
+
+/// The start of a time window: a Unix timestamp (`1714521600`), as a bare
+/// `since` is everywhere else in Nostr; a duration back from `now` with a unit
+/// (`30m`, `2h`, `3d`, see [`parse_duration`]); or a UTC date (`2024-05-01`)
+/// or date-time (`2024-05-01T12:00:00Z`).
+pub fn parse_since(input: &str, now: Timestamp) -> Result<Timestamp> {
+    let input = input.trim();
+    if let Ok(secs) = input.parse::<u64>() {
+        return Ok(Timestamp::from(secs));
+    }
+    if let Ok(at) = chrono::DateTime::parse_from_rfc3339(input) {
+        return Ok(Timestamp::from(at.timestamp().max(0) as u64));
+    }
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        let at = date
+            .and_hms_opt(0, 0, 0)
+            .expect("midnight exists")
+            .and_utc();
+        return Ok(Timestamp::from(at.timestamp().max(0) as u64));
+    }
+    let ago = parse_duration(input).map_err(|err| {
+        anyhow::anyhow!(
+            "{:#}; a date such as 2024-05-01 or a Unix timestamp is accepted too",
+            err
+        )
+    })?;
+    Ok(Timestamp::from(now.as_u64().saturating_sub(ago.as_secs())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn event_references_accept_hex_note_and_nevent() {
+        let keys = Keys::generate();
+        let event = EventBuilder::text_note("hi").sign_with_keys(&keys).unwrap();
+        let hex = event.id.to_hex();
+        let note = event.id.to_bech32().unwrap();
+
+        assert_eq!(parse_event_reference(&hex).unwrap(), (event.id, vec![]));
+        assert_eq!(parse_event_reference(&note).unwrap(), (event.id, vec![]));
+        assert_eq!(
+            parse_event_reference(&format!("nostr:{}", note)).unwrap().0,
+            event.id
+        );
+
+        let nevent = Nip19Event::new(event.id)
+            .relays([RelayUrl::parse("wss://relay.example.com").unwrap()])
+            .to_bech32()
+            .unwrap();
+        let (id, relays) = parse_event_reference(&nevent).unwrap();
+        assert_eq!(id, event.id);
+        assert_eq!(relays.len(), 1);
+        assert!(relays[0].starts_with("wss://relay.example.com"));
+
+        assert!(parse_event_reference("npub1nope").is_err());
+    }
+
+    #[tokio::test]
+    async fn pubkeys_keep_their_nprofile_hints_and_bad_input_names_nip05() {
+        let pubkey = Keys::generate().public_key();
+        let nprofile = Nip19Profile::new(pubkey, vec!["wss://relay.example.com".to_string()])
+            .unwrap()
+            .to_bech32()
+            .unwrap();
+        let (resolved, relays) = resolve_pubkey_with_hints(&format!("nostr:{}", nprofile))
+            .await
+            .unwrap();
+        assert_eq!(resolved, pubkey);
+        assert_eq!(relays.len(), 1);
+        assert!(relays[0].starts_with("wss://relay.example.com"));
+
+        assert_eq!(
+            resolve_pubkey_with_hints(&pubkey.to_hex()).await.unwrap(),
+            (pubkey, vec![])
+        );
+        let error = resolve_pubkey_with_hints("bob").await.unwrap_err();
+        assert!(error.to_string().contains("NIP-05"), "{}", error);
+        // An address is parsed before anything is looked up.
+        assert!(resolve_pubkey_with_hints("bob smith@example.com")
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn durations_accept_single_and_combined_units() {
+        assert_eq!(parse_duration("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("45s").unwrap(), Duration::from_secs(45));
+        assert_eq!(parse_duration("15m").unwrap(), Duration::from_secs(900));
+        assert_eq!(parse_duration("2h").unwrap(), Duration::from_secs(7200));
+        assert_eq!(parse_duration("2d").unwrap(), Duration::from_secs(172_800));
+        assert_eq!(parse_duration("1w").unwrap(), Duration::from_secs(604_800));
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(5400));
+    }
+
+    #[test]
+    fn durations_reject_unknown_units_and_dangling_numbers() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("3y").is_err());
+        assert!(parse_duration("1h30").is_err());
+        assert!(parse_duration("h").is_err());
+    }
+
+    #[test]
+    fn since_takes_a_duration_back_or_a_date() {
+        let now = Timestamp::from(1_700_000_000);
+        assert_eq!(
+            parse_since("2h", now).unwrap().as_u64(),
+            1_700_000_000 - 7200
+        );
+        assert_eq!(
+            parse_since("2024-05-01", now).unwrap().as_u64(),
+            1_714_521_600
+        );
+        assert_eq!(
+            parse_since("2024-05-01T12:00:00Z", now).unwrap().as_u64(),
+            1_714_564_800
+        );
+        assert_eq!(
+            parse_since("1714521600", now).unwrap().as_u64(),
+            1_714_521_600,
+            "a bare number is a timestamp, not seconds ago"
+        );
+        assert!(parse_since("yesterday", now).is_err());
+    }
+
+    #[test]
+    fn targets_accept_notes_nevents_and_naddrs() {
+        let keys = Keys::generate();
+        let note = EventBuilder::text_note("hi").sign_with_keys(&keys).unwrap();
+        assert_eq!(
+            parse_event_target(&note.id.to_bech32().unwrap()).unwrap(),
+            EventTarget::Event(note.id, vec![])
+        );
+
+        let naddr = crate::commands::article::naddr(
+            Kind::from(30023u16),
+            keys.public_key(),
+            "hello",
+            &["wss://relay.example.com".to_string()],
+        )
+        .unwrap();
+        match parse_event_target(&format!("nostr:{}", naddr)).unwrap() {
+            EventTarget::Address(coordinate, relays) => {
+                assert_eq!(coordinate.kind, Kind::from(30023u16));
+                assert_eq!(coordinate.public_key, keys.public_key());
+                assert_eq!(coordinate.identifier, "hello");
+                assert_eq!(relays.len(), 1);
+            }
+            other => panic!("expected an address, got {:?}", other),
+        }
+    }
+}
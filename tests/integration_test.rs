@@ -17,6 +17,7 @@ fn config_save_load_roundtrip() {
         default_relays: vec!["wss://default.example.com".to_string()],
         blossom_server: None,
         coinos_api_token_path: None,
+        contact_drop_limit: None,
     };
 
     config.save_to(&path).unwrap();
@@ -425,6 +426,148 @@ fn event_file_missing_path_names_the_file() {
     assert!(err.contains("failed to read event file"), "got: {}", err);
 }
 
+// ── Batch input (`event --batch`) ────────────────────────────────────
+
+use nostaro::commands::event::{
+    batch_record, dry_run_record, match_resume, parse_batch, parse_resume,
+};
+
+#[test]
+fn event_batch_numbers_events_and_skips_blank_lines() {
+    let text = "{\"kind\": 1, \"content\": \"one\"}\n\n{\"kind\": 7, \"content\": \"+\"}\n";
+    let entries = parse_batch(text).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!((entries[0].index, entries[0].line), (0, 1));
+    assert_eq!((entries[1].index, entries[1].line), (1, 3));
+    assert_eq!(entries[1].spec.kind, 7);
+}
+
+#[test]
+fn event_batch_reports_every_invalid_line_before_publishing() {
+    let text = [
+        r#"{"kind": 1, "content": "fine"}"#,
+        r#"{"kind": 1, "sig": "abc"}"#,
+        r#"{"kind": 3, "tags": [["p", "npub1abc"]]}"#,
+        r#"not json"#,
+    ]
+    .join("\n");
+    let err = format!("{:#}", parse_batch(&text).unwrap_err());
+    assert!(err.contains("3 of 4 line(s)"), "{}", err);
+    assert!(err.contains("line 2:"), "{}", err);
+    assert!(err.contains("line 3:"), "{}", err);
+    assert!(err.contains("line 4:"), "{}", err);
+    assert!(!err.contains("line 1:"), "{}", err);
+}
+
+#[test]
+fn event_batch_rejects_an_empty_batch() {
+    assert!(parse_batch("\n\n").is_err());
+}
+
+#[test]
+fn event_batch_results_record_per_relay_outcomes() {
+    use std::collections::{HashMap, HashSet};
+
+    let entry = &parse_batch(r#"{"kind": 1, "content": "x"}"#).unwrap()[0];
+    let id = nostr_sdk::prelude::EventId::all_zeros();
+    let accepted = nostr_sdk::prelude::RelayUrl::parse("wss://ok.example.com").unwrap();
+    let rejected = nostr_sdk::prelude::RelayUrl::parse("wss://no.example.com").unwrap();
+    let output = nostr_sdk::prelude::Output {
+        val: id,
+        success: HashSet::from([accepted]),
+        failed: HashMap::from([(rejected, "blocked: spam".to_string())]),
+    };
+
+    let record = batch_record(entry, Some(id), Ok(&output));
+    assert_eq!(record["index"], 0);
+    assert_eq!(record["line"], 1);
+    assert_eq!(record["digest"], entry.digest.as_str());
+    assert_eq!(record["ok"], true);
+    assert_eq!(record["id"], id.to_hex());
+    assert_eq!(record["accepted"][0], "wss://ok.example.com");
+    assert_eq!(record["rejected"]["wss://no.example.com"], "blocked: spam");
+    assert!(record["error"].is_null());
+
+    let failed = batch_record(entry, Some(id), Err("timeout".to_string()));
+    assert_eq!(failed["ok"], false);
+    assert_eq!(failed["error"], "timeout");
+}
+
+#[test]
+fn event_batch_resume_keeps_only_successes() {
+    let previous = [
+        r#"{"index": 0, "line": 1, "digest": "aa", "ok": true, "id": "a1"}"#,
+        r#"{"index": 1, "line": 2, "digest": "bb", "ok": false, "error": "timeout"}"#,
+        r#"garbage"#,
+        r#"{"index": 2, "line": 3, "ok": true, "id": "no digest"}"#,
+        r#"{"index": 3, "line": 4, "digest": "aa", "ok": true, "id": "a2"}"#,
+    ]
+    .join("\n");
+    let done = parse_resume(&previous);
+    assert_eq!(done.len(), 1);
+    let ids: Vec<&str> = done["aa"]
+        .iter()
+        .map(|r| r["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, vec!["a1", "a2"]);
+}
+
+#[test]
+fn event_batch_resume_follows_lines_not_positions() {
+    let first = "{\"kind\": 1, \"content\": \"one\"}\n{\"kind\": 1, \"content\": \"two\"}";
+    let entries = parse_batch(first).unwrap();
+    let previous: String = entries
+        .iter()
+        .map(|entry| {
+            let mut record = batch_record(entry, None, Err(String::new()));
+            record["ok"] = true.into();
+            format!("{}\n", record)
+        })
+        .collect();
+
+    // A line inserted at the top, and the second line changed.
+    let edited = "{\"kind\": 1, \"content\": \"new\"}\n\
+                  {\"kind\": 1, \"content\": \"one\"}\n\
+                  {\"kind\": 1, \"content\": \"two, edited\"}";
+    let entries = parse_batch(edited).unwrap();
+    let done = match_resume(&entries, parse_resume(&previous));
+
+    assert_eq!(done.len(), 1, "only the unchanged line counts as done");
+    assert_eq!(done[&1]["index"], 1);
+    assert_eq!(done[&1]["line"], 2);
+}
+
+#[test]
+fn event_batch_resume_from_a_dry_run_publishes_everything() {
+    use nostr_sdk::prelude::*;
+
+    let batch = "{\"kind\": 1, \"content\": \"one\"}\n{\"kind\": 1, \"content\": \"two\"}";
+    let entries = parse_batch(batch).unwrap();
+    let keys = Keys::generate();
+    let preview: String = entries
+        .iter()
+        .map(|entry| {
+            let event = EventBuilder::text_note(entry.spec.content.clone())
+                .sign_with_keys(&keys)
+                .unwrap();
+            format!("{}\n", dry_run_record(entry, &event))
+        })
+        .collect();
+
+    // Even a dry-run record that names its line is not a published event.
+    let mut labelled = dry_run_record(
+        &entries[0],
+        &EventBuilder::text_note("one")
+            .sign_with_keys(&keys)
+            .unwrap(),
+    );
+    labelled["digest"] = entries[0].digest.clone().into();
+    let previous = format!("{}{}\n", preview, labelled);
+
+    assert!(parse_resume(&previous).is_empty());
+    assert!(match_resume(&entries, parse_resume(&previous)).is_empty());
+}
+
 // ── Output sink (`--out`) ────────────────────────────────────────────
 
 // The sink is process-global, so a single test owns it for the whole binary.